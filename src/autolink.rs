@@ -0,0 +1,300 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::{
+    collections::{HashMap, HashSet},
+    io::BufReader,
+    process::{Child, Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    model::AppConfig,
+    system::{self, AutoLinkSummary},
+};
+
+/// Quiet time after the last relevant graph change before linking; ports follow their node.
+const SETTLE_DELAY: Duration = Duration::from_millis(500);
+/// Pause before restarting `pw-dump --monitor` once it exits (e.g. PipeWire restarted).
+const RESPAWN_DELAY: Duration = Duration::from_secs(2);
+
+/// Follows node and port additions in `pw-dump --monitor` batches.
+#[derive(Debug, Default)]
+struct GraphTracker {
+    /// Stream and peer nodes that auto-link.
+    relevant: HashSet<String>,
+    nodes: HashMap<u32, String>,
+    ports: HashSet<u32>,
+}
+
+impl GraphTracker {
+    fn new(cfg: &AppConfig) -> Self {
+        Self {
+            relevant: system::autolinked_nodes(cfg).into_iter().collect(),
+            ..Self::default()
+        }
+    }
+
+    /// Returns whether a relevant node, or a port of one, appeared in `batch`.
+    fn update(&mut self, batch: &Value) -> bool {
+        let mut appeared = false;
+        for object in batch.as_array().into_iter().flatten() {
+            let Some(id) = object
+                .get("id")
+                .and_then(Value::as_u64)
+                .and_then(|id| u32::try_from(id).ok())
+            else {
+                continue;
+            };
+            // Removed objects come back as `"info": null`.
+            let Some(info) = object.get("info").filter(|info| !info.is_null()) else {
+                self.nodes.remove(&id);
+                self.ports.remove(&id);
+                continue;
+            };
+            let props = info.get("props");
+
+            match object.get("type").and_then(Value::as_str) {
+                Some("PipeWire:Interface:Node") => {
+                    let Some(name) = props
+                        .and_then(|props| props.get("node.name"))
+                        .and_then(Value::as_str)
+                    else {
+                        continue;
+                    };
+                    let is_new = self.nodes.insert(id, name.to_string()).is_none();
+                    appeared |= is_new && self.relevant.contains(name);
+                }
+                Some("PipeWire:Interface:Port") => {
+                    let node = props
+                        .and_then(|props| props.get("node.id"))
+                        .and_then(Value::as_u64)
+                        .and_then(|node| self.nodes.get(&(node as u32)));
+                    let is_new = self.ports.insert(id);
+                    appeared |= is_new && node.is_some_and(|name| self.relevant.contains(name));
+                }
+                _ => {}
+            }
+        }
+        appeared
+    }
+}
+
+#[derive(Debug, Default)]
+struct WatchState {
+    passes: u32,
+    last: Option<AutoLinkSummary>,
+}
+
+/// Streams `pw-dump --monitor` batches until the process exits.
+fn spawn_monitor(child_slot: &Mutex<Option<Child>>) -> Result<mpsc::Receiver<Value>> {
+    let mut child = Command::new("pw-dump")
+        .arg("--monitor")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .context("Could not execute `pw-dump --monitor`")?;
+    let stdout = child.stdout.take().context("pw-dump has no stdout")?;
+    *child_slot.lock().unwrap() = Some(child);
+
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let batches = serde_json::Deserializer::from_reader(BufReader::new(stdout)).into_iter();
+        for batch in batches {
+            let Ok(batch) = batch else {
+                break;
+            };
+            if tx.send(batch).is_err() {
+                break;
+            }
+        }
+    });
+    Ok(rx)
+}
+
+fn stop_monitor(child_slot: &Mutex<Option<Child>>) {
+    if let Some(mut child) = child_slot.lock().unwrap().take() {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
+
+fn sleep_unless_stopped(duration: Duration, stop: &AtomicBool) {
+    let deadline = Instant::now() + duration;
+    while !stop.load(Ordering::Relaxed) && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(100));
+    }
+}
+
+fn watch_graph(
+    cfg: &AppConfig,
+    stop: &AtomicBool,
+    child_slot: &Mutex<Option<Child>>,
+    state: &Mutex<WatchState>,
+    passes: &AtomicU32,
+) -> Result<()> {
+    while !stop.load(Ordering::Relaxed) {
+        // A fresh monitor starts with the whole graph, so every relevant node counts as new.
+        let mut tracker = GraphTracker::new(cfg);
+        let batches = spawn_monitor(child_slot)?;
+        let mut pending_since: Option<Instant> = None;
+
+        while !stop.load(Ordering::Relaxed) {
+            match batches.recv_timeout(Duration::from_millis(100)) {
+                Ok(batch) => {
+                    if tracker.update(&batch) {
+                        pending_since = Some(Instant::now());
+                    }
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+
+            if pending_since.is_some_and(|since| since.elapsed() >= SETTLE_DELAY) {
+                pending_since = None;
+                let summary = system::autolink_streams(cfg).unwrap_or_else(|e| AutoLinkSummary {
+                    issues: vec![format!("{e:#}")],
+                    ..AutoLinkSummary::default()
+                });
+                let mut state = state.lock().unwrap();
+                state.passes += 1;
+                state.last = Some(summary);
+                passes.store(state.passes, Ordering::Relaxed);
+            }
+        }
+
+        stop_monitor(child_slot);
+        sleep_unless_stopped(RESPAWN_DELAY, stop);
+    }
+    Ok(())
+}
+
+/// Re-runs auto-linking whenever a stream or its source/sink (re)appears in the graph.
+pub struct AutoLinkWatcher {
+    cfg: AppConfig,
+    stop: Arc<AtomicBool>,
+    child: Arc<Mutex<Option<Child>>>,
+    state: Arc<Mutex<WatchState>>,
+    passes: Arc<AtomicU32>,
+    handle: Option<JoinHandle<Result<()>>>,
+}
+
+impl AutoLinkWatcher {
+    pub fn spawn(cfg: &AppConfig) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let child = Arc::new(Mutex::new(None));
+        let state = Arc::new(Mutex::new(WatchState::default()));
+        let passes = Arc::new(AtomicU32::new(0));
+        let handle = {
+            let cfg = cfg.clone();
+            let stop = Arc::clone(&stop);
+            let child = Arc::clone(&child);
+            let state = Arc::clone(&state);
+            let passes = Arc::clone(&passes);
+            thread::spawn(move || watch_graph(&cfg, &stop, &child, &state, &passes))
+        };
+
+        Self {
+            cfg: cfg.clone(),
+            stop,
+            child,
+            state,
+            passes,
+            handle: Some(handle),
+        }
+    }
+
+    /// Whether the watcher already follows the streams of `cfg`.
+    pub fn watches(&self, cfg: &AppConfig) -> bool {
+        self.cfg.autolink.unlink_manual_links == cfg.autolink.unlink_manual_links
+            && self.cfg.sends.len() == cfg.sends.len()
+            && self.cfg.sends.iter().zip(&cfg.sends).all(|(a, b)| {
+                a.enabled == b.enabled
+                    && a.target_object == b.target_object
+                    && a.node_name == b.node_name
+                    && a.channel_map == b.channel_map
+            })
+            && self.cfg.recvs.len() == cfg.recvs.len()
+            && self.cfg.recvs.iter().zip(&cfg.recvs).all(|(a, b)| {
+                a.enabled == b.enabled
+                    && a.target_sink == b.target_sink
+                    && a.node_name == b.node_name
+            })
+    }
+
+    /// Number of link passes run so far.
+    pub fn passes(&self) -> u32 {
+        self.passes.load(Ordering::Relaxed)
+    }
+
+    pub fn last_summary(&self) -> Option<AutoLinkSummary> {
+        self.state.lock().unwrap().last.clone()
+    }
+
+    /// Returns the thread error once the watcher has stopped on its own.
+    pub fn take_error(&mut self) -> Option<anyhow::Error> {
+        if !self.handle.as_ref()?.is_finished() {
+            return None;
+        }
+        match self.handle.take()?.join() {
+            Ok(Ok(())) => None,
+            Ok(Err(e)) => Some(e),
+            Err(_) => Some(anyhow::anyhow!("auto-link watcher thread panicked")),
+        }
+    }
+}
+
+impl Drop for AutoLinkWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        stop_monitor(&self.child);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::VbanSend;
+    use serde_json::json;
+
+    fn node(id: u32, name: &str) -> Value {
+        json!({ "id": id, "type": "PipeWire:Interface:Node", "info": { "props": { "node.name": name } } })
+    }
+
+    fn port(id: u32, node: u32) -> Value {
+        json!({ "id": id, "type": "PipeWire:Interface:Port", "info": { "props": { "node.id": node } } })
+    }
+
+    #[test]
+    fn tracker_fires_when_a_source_comes_back() {
+        let cfg = AppConfig {
+            sends: vec![VbanSend {
+                target_object: "alsa_input.usb".into(),
+                node_name: "vban-send-a".into(),
+                ..VbanSend::default()
+            }],
+            ..AppConfig::default()
+        };
+        let mut tracker = GraphTracker::new(&cfg);
+
+        assert!(tracker.update(&json!([
+            node(30, "alsa_input.usb"),
+            node(40, "vban-send-a")
+        ])));
+        assert!(!tracker.update(&json!([node(50, "alsa_output.hdmi"), port(51, 50)])));
+        // Property updates of known nodes are not appearances.
+        assert!(!tracker.update(&json!([node(30, "alsa_input.usb")])));
+        assert!(tracker.update(&json!([port(31, 30)])));
+
+        // Unplug and replug: the node comes back under a new id.
+        assert!(!tracker.update(&json!([{ "id": 30, "info": null }, { "id": 31, "info": null }])));
+        assert!(tracker.update(&json!([node(60, "alsa_input.usb")])));
+    }
+}
@@ -0,0 +1,1364 @@
+use anyhow::{bail, Context, Result};
+use clap::{Args, Parser, Subcommand};
+use std::{
+    fs,
+    net::UdpSocket,
+    path::PathBuf,
+    sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+use uuid::Uuid;
+
+use crate::{
+    autolink, discovery, generator, import, live,
+    model::{AppConfig, ChannelMap, RelayDestination, VbanRecv, VbanRelay, VbanSend},
+    monitor, player, recorder, relay, service, status, system, text_remote,
+};
+
+#[derive(Debug, Parser)]
+#[command(name = "rustban", version, about = "VBAN control panel for PipeWire")]
+pub struct Cli {
+    /// Run a headless command instead of opening the GUI.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Manage VBAN send streams.
+    Send {
+        #[command(subcommand)]
+        action: SendAction,
+    },
+    /// Manage VBAN recv streams.
+    Recv {
+        #[command(subcommand)]
+        action: RecvAction,
+    },
+    /// Send VBAN-TEXT commands to configured targets.
+    Remote {
+        #[command(subcommand)]
+        action: RemoteAction,
+    },
+    /// Manage and run VBAN relays (one listen port fanned out to several hosts).
+    Relay {
+        #[command(subcommand)]
+        action: RelayAction,
+    },
+    /// Send a test tone as a VBAN stream (e.g. to 127.0.0.1 to check a recv).
+    Tone(ToneArgs),
+    /// Import hand-written vban-send/recv modules from PipeWire config files.
+    Import {
+        /// Files to read (defaults to the non-RustBAN files in pipewire.conf.d).
+        paths: Vec<PathBuf>,
+        /// Add the streams to the config (only lists them otherwise).
+        #[arg(long)]
+        adopt: bool,
+        /// Delete the original files after adopting (files with other modules are kept).
+        #[arg(long, requires = "adopt")]
+        delete_originals: bool,
+        /// Read the vban modules loaded in the running PipeWire that are not in the config.
+        #[arg(long, conflicts_with_all = ["paths", "delete_originals"])]
+        running: bool,
+    },
+    /// Write PipeWire fragments and auto-link send sources.
+    Apply {
+        /// Restart the PipeWire user services after writing fragments.
+        #[arg(long)]
+        restart: bool,
+        /// Show what would be created, modified or deleted (with diffs) without writing.
+        #[arg(long, conflicts_with = "restart")]
+        dry_run: bool,
+        /// Swap only the changed vban modules in the running PipeWire instead of restarting it.
+        #[arg(long, conflicts_with = "restart")]
+        live: bool,
+    },
+    /// Keep sends and recvs linked in the foreground, re-linking when a stream, source or sink
+    /// (re)appears (USB replug, PipeWire restart). Follows config changes.
+    Daemon,
+    /// Answer VBAN PING0 requests in the foreground using the host info emulation identity.
+    PingResponder {
+        /// UDP port to answer on (defaults to the configured ping port).
+        #[arg(long)]
+        port: Option<u16>,
+    },
+    /// Show configured streams, fragments on disk and the last auto-link result.
+    Status {
+        /// Print a versioned JSON document instead of text.
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SendAction {
+    /// List configured sends.
+    List,
+    /// Add a new send.
+    Add(SendFields),
+    /// Edit an existing send (by index, id or node.name).
+    Edit {
+        entry: String,
+        #[command(flatten)]
+        fields: SendFields,
+    },
+    /// Remove a send (by index, id or node.name).
+    Remove { entry: String },
+    /// Send a VBAN PING0 and list the hosts that reply.
+    Discover {
+        /// UDP port to ping (defaults to the configured discovery port).
+        #[arg(long)]
+        port: Option<u16>,
+        /// Extra address to ping, `host` or `host:port` (repeatable).
+        #[arg(long = "address")]
+        addresses: Vec<String>,
+        /// Do not broadcast on the local subnet.
+        #[arg(long)]
+        no_broadcast: bool,
+        /// How long to wait for replies, in seconds.
+        #[arg(long, default_value_t = 2)]
+        seconds: u64,
+    },
+    /// Stream a WAV file with a send's destination, stream name and format.
+    Play {
+        /// Send (by index, id or node.name) to take the settings from.
+        entry: String,
+        /// WAV file to play.
+        #[arg(long)]
+        file: PathBuf,
+        /// Start over at the end of the file until interrupted.
+        #[arg(long = "loop")]
+        looping: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum RecvAction {
+    /// List configured recvs.
+    List,
+    /// Add a new recv.
+    Add(RecvFields),
+    /// Edit an existing recv (by index, id or node.name).
+    Edit {
+        entry: String,
+        #[command(flatten)]
+        fields: RecvFields,
+    },
+    /// Remove a recv (by index, id or node.name).
+    Remove { entry: String },
+    /// Listen for VBAN streams and list what is received.
+    Scan {
+        /// UDP port to listen on (must not be bound by PipeWire).
+        #[arg(long, default_value_t = 6980)]
+        port: u16,
+        /// How long to listen, in seconds.
+        #[arg(long, default_value_t = 3)]
+        seconds: u64,
+    },
+    /// Report packet rate, loss, reordering and jitter of incoming streams.
+    Monitor {
+        /// Recv (by index, id or node.name) whose settings the headers are checked against.
+        entry: Option<String>,
+        /// Mirror port to listen on (defaults to the recv's source port, or 6980).
+        #[arg(long)]
+        port: Option<u16>,
+        /// Stop after this many seconds (runs until interrupted otherwise).
+        #[arg(long)]
+        seconds: Option<u64>,
+    },
+    /// Record one incoming stream to a WAV file.
+    Record {
+        /// Stream name (sess.name) to record.
+        #[arg(long)]
+        name: String,
+        /// WAV file to write.
+        #[arg(long, short)]
+        output: PathBuf,
+        /// UDP port to listen on (must not be bound by PipeWire).
+        #[arg(long, default_value_t = 6980)]
+        port: u16,
+        /// Stop after this many seconds of audio.
+        #[arg(long, default_value_t = 60)]
+        seconds: u64,
+        /// Also stop once the audio data reaches this many megabytes.
+        #[arg(long)]
+        max_mb: Option<u64>,
+        /// Also stop (and keep what was recorded) after this many seconds without audio, once
+        /// the stream has started. Ctrl-C stops and keeps the recording too.
+        #[arg(long, default_value_t = 5)]
+        idle_seconds: u64,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum RelayAction {
+    /// List configured relays.
+    List,
+    /// Add a new relay.
+    Add(RelayFields),
+    /// Edit an existing relay (by index or name).
+    Edit {
+        entry: String,
+        #[command(flatten)]
+        fields: RelayFields,
+    },
+    /// Remove a relay (by index or name).
+    Remove { entry: String },
+    /// Run the enabled relays (or only `entry`) in the foreground with counters.
+    Run {
+        entry: Option<String>,
+        /// Seconds between counter reports.
+        #[arg(long, default_value_t = 5)]
+        interval: u64,
+        /// Stop after this many seconds (runs until interrupted otherwise).
+        #[arg(long)]
+        seconds: Option<u64>,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct ToneArgs {
+    #[arg(long, default_value = "127.0.0.1")]
+    ip: String,
+    #[arg(long, default_value_t = 6980, value_parser = clap::value_parser!(u16).range(1..))]
+    port: u16,
+    /// Stream name (sess.name) the recv matches on.
+    #[arg(long, default_value = "Stream1")]
+    name: String,
+    #[arg(long, default_value_t = 48_000)]
+    rate: u32,
+    #[arg(long, default_value_t = 2, value_parser = clap::value_parser!(u16).range(1..=32))]
+    channels: u16,
+    /// Audio format, e.g. S16LE, S24LE, F32LE.
+    #[arg(long, default_value = "S16LE")]
+    format: String,
+    /// Sine frequency in Hz.
+    #[arg(long, default_value_t = 440.0)]
+    frequency: f32,
+    /// Send pink noise instead of a sine.
+    #[arg(long)]
+    pink: bool,
+    /// Linear level, 0.0..=1.0.
+    #[arg(long, default_value_t = 0.25)]
+    level: f32,
+    /// Stop after this many seconds (runs until interrupted otherwise).
+    #[arg(long)]
+    seconds: Option<u64>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum RemoteAction {
+    /// List VBAN-TEXT targets and macros.
+    List,
+    /// Send a raw command to a target (by index or name).
+    Send { target: String, command: String },
+    /// Send a saved macro (by index or name).
+    Macro { name: String },
+}
+
+#[derive(Debug, Args)]
+pub struct RelayFields {
+    /// Relay name.
+    #[arg(long)]
+    name: Option<String>,
+    /// UDP port to listen on (must not be bound by PipeWire).
+    #[arg(long, value_parser = clap::value_parser!(u16).range(1..))]
+    port: Option<u16>,
+    /// Only forward this stream name (empty = all streams).
+    #[arg(long)]
+    filter: Option<String>,
+    /// Rename forwarded streams (empty = keep the name).
+    #[arg(long)]
+    rename: Option<String>,
+    /// Destination `host` or `host:port` (repeatable, replaces the list). Relays send over
+    /// IPv4 only, so IPv6 addresses are rejected.
+    #[arg(long = "dest", value_parser = parse_destination)]
+    destinations: Vec<RelayDestination>,
+    #[arg(long)]
+    enabled: Option<bool>,
+}
+
+impl RelayFields {
+    fn apply_to(self, relay: &mut VbanRelay) {
+        if let Some(name) = self.name {
+            relay.name = name;
+        }
+        if let Some(port) = self.port {
+            relay.listen_port = port;
+        }
+        if let Some(filter) = self.filter {
+            relay.stream_filter = filter;
+        }
+        if let Some(rename) = self.rename {
+            relay.rename = rename;
+        }
+        if !self.destinations.is_empty() {
+            relay.destinations = self.destinations;
+        }
+        if let Some(enabled) = self.enabled {
+            relay.enabled = enabled;
+        }
+    }
+}
+
+fn parse_destination(value: &str) -> Result<RelayDestination, String> {
+    let value = value.trim();
+    if value.starts_with('[') || value.matches(':').count() > 1 {
+        return Err(format!(
+            "`{value}` looks like an IPv6 address; relays only send over IPv4"
+        ));
+    }
+    let (ip, port) = match value.rsplit_once(':') {
+        Some((ip, port)) => (
+            ip,
+            port.parse::<u16>()
+                .map_err(|_| format!("invalid port in `{value}`"))?,
+        ),
+        None => (value, RelayDestination::default().port),
+    };
+    if ip.is_empty() || port == 0 {
+        return Err(format!("`{value}` is not a valid host:port"));
+    }
+    Ok(RelayDestination {
+        ip: ip.to_string(),
+        port,
+    })
+}
+
+/// `--channel-map` value; `None` is `auto`.
+#[derive(Debug, Clone)]
+struct ChannelMapArg(Option<ChannelMap>);
+
+fn parse_channel_map(value: &str) -> Result<ChannelMapArg, String> {
+    if value.trim().eq_ignore_ascii_case("auto") {
+        return Ok(ChannelMapArg(None));
+    }
+    value.parse().map(|map| ChannelMapArg(Some(map)))
+}
+
+#[derive(Debug, Args)]
+pub struct SendFields {
+    /// Destination IP.
+    #[arg(long)]
+    ip: Option<String>,
+    /// Destination port.
+    #[arg(long, value_parser = clap::value_parser!(u16).range(1..))]
+    port: Option<u16>,
+    /// Stream name (sess.name).
+    #[arg(long)]
+    name: Option<String>,
+    /// Session media (sess.media).
+    #[arg(long)]
+    media: Option<String>,
+    /// Audio format, e.g. S16LE.
+    #[arg(long)]
+    format: Option<String>,
+    /// Audio rate in Hz.
+    #[arg(long, value_parser = clap::value_parser!(u32).range(8_000..=384_000))]
+    rate: Option<u32>,
+    /// Audio channels.
+    #[arg(long, value_parser = clap::value_parser!(u8).range(1..=32))]
+    channels: Option<u8>,
+    /// Source node to link into the send (target.object).
+    #[arg(long)]
+    target: Option<String>,
+    /// Source-to-send channel routes, e.g. `3:1,4:2` or `1:1,1:2` for mono upmix; `auto`
+    /// matches ports by channel.
+    #[arg(long, value_parser = parse_channel_map)]
+    channel_map: Option<ChannelMapArg>,
+    /// node.name of the send.
+    #[arg(long)]
+    node_name: Option<String>,
+    /// node.description of the send.
+    #[arg(long)]
+    description: Option<String>,
+    #[arg(long)]
+    enabled: Option<bool>,
+    #[arg(long)]
+    always_process: Option<bool>,
+}
+
+impl SendFields {
+    fn apply_to(self, send: &mut VbanSend) {
+        if let Some(ip) = self.ip {
+            send.destination_ip = ip;
+        }
+        if let Some(port) = self.port {
+            send.destination_port = port;
+        }
+        if let Some(name) = self.name {
+            send.sess_name = name;
+        }
+        if let Some(media) = self.media {
+            send.sess_media = media;
+        }
+        if let Some(format) = self.format {
+            send.audio_format = format;
+        }
+        if let Some(rate) = self.rate {
+            send.audio_rate = rate;
+        }
+        if let Some(channels) = self.channels {
+            send.audio_channels = channels;
+        }
+        if let Some(target) = self.target {
+            send.target_object = target;
+        }
+        if let Some(ChannelMapArg(channel_map)) = self.channel_map {
+            send.channel_map = channel_map;
+        }
+        if let Some(node_name) = self.node_name {
+            send.node_name = node_name;
+        }
+        if let Some(description) = self.description {
+            send.node_description = description;
+        }
+        if let Some(enabled) = self.enabled {
+            send.enabled = enabled;
+        }
+        if let Some(always_process) = self.always_process {
+            send.always_process = always_process;
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct RecvFields {
+    /// Source IP.
+    #[arg(long)]
+    ip: Option<String>,
+    /// Source port.
+    #[arg(long, value_parser = clap::value_parser!(u16).range(1..))]
+    port: Option<u16>,
+    /// Stream name to match (empty = all streams).
+    #[arg(long)]
+    name: Option<String>,
+    /// Latency in milliseconds.
+    #[arg(long, value_parser = clap::value_parser!(u32).range(0..=5_000))]
+    latency: Option<u32>,
+    /// Playback sink to link the recv into (target.object); empty to patch by hand.
+    #[arg(long)]
+    sink: Option<String>,
+    /// node.name of the recv.
+    #[arg(long)]
+    node_name: Option<String>,
+    /// node.description of the recv.
+    #[arg(long)]
+    description: Option<String>,
+    #[arg(long)]
+    enabled: Option<bool>,
+    #[arg(long)]
+    always_process: Option<bool>,
+}
+
+impl RecvFields {
+    fn apply_to(self, recv: &mut VbanRecv) {
+        if let Some(ip) = self.ip {
+            recv.source_ip = ip;
+        }
+        if let Some(port) = self.port {
+            recv.source_port = port;
+        }
+        if let Some(name) = self.name {
+            recv.stream_name = name;
+        }
+        if let Some(latency) = self.latency {
+            recv.latency_msec = latency;
+        }
+        if let Some(sink) = self.sink {
+            recv.target_sink = sink;
+        }
+        if let Some(node_name) = self.node_name {
+            recv.node_name = node_name;
+        }
+        if let Some(description) = self.description {
+            recv.node_description = description;
+        }
+        if let Some(enabled) = self.enabled {
+            recv.enabled = enabled;
+        }
+        if let Some(always_process) = self.always_process {
+            recv.always_process = always_process;
+        }
+    }
+}
+
+pub fn run(command: Command) -> Result<()> {
+    match command {
+        Command::Send { action } => run_send(action),
+        Command::Recv { action } => run_recv(action),
+        Command::Remote { action } => run_remote(action),
+        Command::Relay { action } => run_relay(action),
+        Command::Tone(args) => run_tone(args),
+        Command::Import { adopt, running, .. } if running => run_import_running(adopt),
+        Command::Import {
+            paths,
+            adopt,
+            delete_originals,
+            ..
+        } => run_import(paths, adopt, delete_originals),
+        Command::Apply {
+            restart,
+            dry_run,
+            live,
+        } => run_apply(restart, dry_run, live),
+        Command::Daemon => run_daemon(),
+        Command::PingResponder { port } => run_ping_responder(port),
+        Command::Status { json } => run_status(json),
+    }
+}
+
+fn run_send(action: SendAction) -> Result<()> {
+    let mut cfg = system::load_app_config()?;
+
+    match action {
+        SendAction::List => {
+            if cfg.sends.is_empty() {
+                println!("No send configured.");
+            }
+            for (i, send) in cfg.sends.iter().enumerate() {
+                println!("{}", format_send_line(i, send));
+            }
+            return Ok(());
+        }
+        SendAction::Add(fields) => {
+            let mut send = VbanSend::default();
+            fields.apply_to(&mut send);
+            println!("Send added: {}", format_send_line(cfg.sends.len(), &send));
+            cfg.sends.push(send);
+        }
+        SendAction::Edit { entry, fields } => {
+            let i = find_entry(&cfg.sends, &entry)?;
+            fields.apply_to(&mut cfg.sends[i]);
+            println!("Send updated: {}", format_send_line(i, &cfg.sends[i]));
+        }
+        SendAction::Remove { entry } => {
+            let i = find_entry(&cfg.sends, &entry)?;
+            let send = cfg.sends.remove(i);
+            println!("Send removed: {}", format_send_line(i, &send));
+        }
+        SendAction::Discover {
+            port,
+            addresses,
+            no_broadcast,
+            seconds,
+        } => {
+            let mut settings = cfg.host_discovery.clone();
+            settings.addresses.extend(addresses);
+            if let Some(port) = port {
+                settings.port = port;
+            }
+            if no_broadcast {
+                settings.broadcast = false;
+            }
+
+            let identity = service::Ping0::from_config(&cfg);
+            let hosts =
+                discovery::discover_hosts(&settings, &identity, Duration::from_secs(seconds))?;
+            if hosts.is_empty() {
+                println!("No VBAN host replied.");
+            }
+            for host in hosts {
+                println!("{:<15} {}", host.addr.ip().to_string(), host.label());
+            }
+            return Ok(());
+        }
+        SendAction::Play {
+            entry,
+            file,
+            looping,
+        } => {
+            let i = find_entry(&cfg.sends, &entry)?;
+            let settings = player::PlaySettings::from_send(&cfg.sends[i], file, looping);
+            println!(
+                "Playing {} as \"{}\" to {}:{} ({} Hz, {}ch, {}).",
+                settings.path.display(),
+                settings.stream_name,
+                settings.destination_ip,
+                settings.destination_port,
+                settings.audio_rate,
+                settings.audio_channels,
+                settings.audio_format
+            );
+
+            let packets = AtomicU32::new(0);
+            player::play_file(&settings, &AtomicBool::new(false), &packets)?;
+            println!("{} packet(s) sent.", packets.into_inner());
+            return Ok(());
+        }
+    }
+
+    system::save_app_config(&cfg)?;
+    println!("Config saved. Run `rustban apply` to update PipeWire.");
+    Ok(())
+}
+
+fn run_recv(action: RecvAction) -> Result<()> {
+    let mut cfg = system::load_app_config()?;
+
+    match action {
+        RecvAction::List => {
+            if cfg.recvs.is_empty() {
+                println!("No recv configured.");
+            }
+            for (i, recv) in cfg.recvs.iter().enumerate() {
+                println!("{}", format_recv_line(i, recv));
+            }
+            return Ok(());
+        }
+        RecvAction::Add(fields) => {
+            let mut recv = VbanRecv::default();
+            fields.apply_to(&mut recv);
+            println!("Recv added: {}", format_recv_line(cfg.recvs.len(), &recv));
+            cfg.recvs.push(recv);
+        }
+        RecvAction::Edit { entry, fields } => {
+            let i = find_entry(&cfg.recvs, &entry)?;
+            fields.apply_to(&mut cfg.recvs[i]);
+            println!("Recv updated: {}", format_recv_line(i, &cfg.recvs[i]));
+        }
+        RecvAction::Remove { entry } => {
+            let i = find_entry(&cfg.recvs, &entry)?;
+            let recv = cfg.recvs.remove(i);
+            println!("Recv removed: {}", format_recv_line(i, &recv));
+        }
+        RecvAction::Scan { port, seconds } => {
+            let streams = discovery::scan_streams(port, Duration::from_secs(seconds))?;
+            if streams.is_empty() {
+                println!("No VBAN stream received on UDP port {port}.");
+            }
+            for stream in streams {
+                println!(
+                    "{:<15} \"{}\"  {}  ({} packets)",
+                    stream.source_ip.to_string(),
+                    stream.stream_name,
+                    stream.format_label(),
+                    stream.packets
+                );
+            }
+            return Ok(());
+        }
+        RecvAction::Monitor {
+            entry,
+            port,
+            seconds,
+        } => {
+            let recv = entry
+                .map(|entry| find_entry(&cfg.recvs, &entry).map(|i| cfg.recvs[i].clone()))
+                .transpose()?;
+            let port = port
+                .or(recv.as_ref().map(|recv| recv.source_port))
+                .unwrap_or(6980);
+            return run_monitor(port, recv, seconds.map(Duration::from_secs));
+        }
+        RecvAction::Record {
+            name,
+            output,
+            port,
+            seconds,
+            max_mb,
+            idle_seconds,
+        } => {
+            let settings = recorder::RecordSettings {
+                port,
+                stream_name: name,
+                path: output,
+                max_seconds: Some(seconds),
+                max_bytes: max_mb.map(|mb| mb * 1024 * 1024),
+                idle_timeout: Some(Duration::from_secs(idle_seconds.max(1))),
+            };
+            println!(
+                "Recording \"{}\" from UDP port {port} to {}...",
+                settings.stream_name,
+                settings.path.display()
+            );
+            stop_on_ctrl_c();
+            let summary = recorder::record_stream(&settings, &INTERRUPTED, &AtomicU64::new(0))?;
+            println!(
+                "Saved {} ({}, {:.1}s, {} bytes of audio).",
+                summary.path.display(),
+                summary.format,
+                summary.seconds,
+                summary.data_bytes
+            );
+            if summary.skipped > 0 {
+                println!(
+                    "{} packet(s) skipped (format change or non-PCM codec).",
+                    summary.skipped
+                );
+            }
+            return Ok(());
+        }
+    }
+
+    system::save_app_config(&cfg)?;
+    println!("Config saved. Run `rustban apply` to update PipeWire.");
+    Ok(())
+}
+
+/// Set by the SIGINT handler of [`stop_on_ctrl_c`].
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Makes Ctrl-C set [`INTERRUPTED`] instead of killing the process, so a loop polling it can
+/// finish its output.
+fn stop_on_ctrl_c() {
+    extern "C" fn on_sigint(_: libc::c_int) {
+        INTERRUPTED.store(true, Ordering::Relaxed);
+    }
+    // SAFETY: the handler only stores to an atomic, which is async-signal-safe.
+    unsafe {
+        libc::signal(libc::SIGINT, on_sigint as *const () as libc::sighandler_t);
+    }
+}
+
+fn run_monitor(port: u16, recv: Option<VbanRecv>, duration: Option<Duration>) -> Result<()> {
+    let mut stream_monitor = monitor::StreamMonitor::spawn(port, recv)?;
+    println!("Monitoring UDP port {port}...");
+
+    let deadline = duration.map(|duration| Instant::now() + duration);
+    while deadline.is_none_or(|d| Instant::now() < d) {
+        thread::sleep(Duration::from_secs(1));
+        if let Some(e) = stream_monitor.take_error() {
+            return Err(e);
+        }
+
+        let streams = stream_monitor.snapshot();
+        if streams.is_empty() {
+            println!("No VBAN stream received yet.");
+        }
+        for stats in streams {
+            println!(
+                "{:<15} \"{}\"  {}  {}",
+                stats.stream.source_ip.to_string(),
+                stats.stream.stream_name,
+                stats.stream.format_label(),
+                stats.summary()
+            );
+            for mismatch in &stats.mismatches {
+                println!("  ! {mismatch}");
+            }
+        }
+    }
+    Ok(())
+}
+
+fn run_relay(action: RelayAction) -> Result<()> {
+    let mut cfg = system::load_app_config()?;
+
+    match action {
+        RelayAction::List => {
+            if cfg.relays.is_empty() {
+                println!("No relay configured.");
+            }
+            for (i, relay) in cfg.relays.iter().enumerate() {
+                println!("{}", format_relay_line(i, relay));
+            }
+            return Ok(());
+        }
+        RelayAction::Add(fields) => {
+            let mut relay = VbanRelay::default();
+            fields.apply_to(&mut relay);
+            println!(
+                "Relay added: {}",
+                format_relay_line(cfg.relays.len(), &relay)
+            );
+            cfg.relays.push(relay);
+        }
+        RelayAction::Edit { entry, fields } => {
+            let i = find_named(&cfg.relays, &entry, |relay| &relay.name)?;
+            fields.apply_to(&mut cfg.relays[i]);
+            println!("Relay updated: {}", format_relay_line(i, &cfg.relays[i]));
+        }
+        RelayAction::Remove { entry } => {
+            let i = find_named(&cfg.relays, &entry, |relay| &relay.name)?;
+            let relay = cfg.relays.remove(i);
+            println!("Relay removed: {}", format_relay_line(i, &relay));
+        }
+        RelayAction::Run {
+            entry,
+            interval,
+            seconds,
+        } => {
+            let configs = match entry {
+                Some(entry) => {
+                    vec![cfg.relays[find_named(&cfg.relays, &entry, |relay| &relay.name)?].clone()]
+                }
+                None => cfg
+                    .relays
+                    .iter()
+                    .filter(|relay| relay.enabled)
+                    .cloned()
+                    .collect(),
+            };
+            if configs.is_empty() {
+                bail!("No enabled relay to run");
+            }
+            return run_relays(&configs, interval, seconds.map(Duration::from_secs));
+        }
+    }
+
+    system::save_app_config(&cfg)?;
+    println!("Config saved.");
+    Ok(())
+}
+
+fn run_relays(configs: &[VbanRelay], interval: u64, duration: Option<Duration>) -> Result<()> {
+    let mut relays = Vec::new();
+    for config in configs {
+        let relay = relay::Relay::spawn(config)
+            .with_context(|| format!("Could not start relay `{}`", config.name))?;
+        println!(
+            "Relay `{}` listening on UDP port {}.",
+            config.name, config.listen_port
+        );
+        relays.push((config, relay));
+    }
+
+    let deadline = duration.map(|duration| Instant::now() + duration);
+    while deadline.is_none_or(|d| Instant::now() < d) {
+        thread::sleep(Duration::from_secs(interval.max(1)));
+        for (config, relay) in &mut relays {
+            if let Some(e) = relay.take_error() {
+                return Err(e.context(format!("Relay `{}` stopped", config.name)));
+            }
+            println!("{}: {} packet(s) received", config.name, relay.received());
+            for stats in relay.destination_stats() {
+                println!(
+                    "  -> {:<21} {} packet(s), {} bytes, {} error(s)",
+                    stats.label, stats.packets, stats.bytes, stats.errors
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+fn run_remote(action: RemoteAction) -> Result<()> {
+    let cfg = system::load_app_config()?;
+    let remote = &cfg.text_remote;
+
+    match action {
+        RemoteAction::List => {
+            println!("Targets:");
+            for (i, target) in remote.targets.iter().enumerate() {
+                println!(
+                    "{:>2} {}  {}:{}  stream=\"{}\"",
+                    i + 1,
+                    target.name,
+                    target.ip,
+                    target.port,
+                    target.stream_name
+                );
+            }
+            println!("Macros:");
+            for (i, text_macro) in remote.macros.iter().enumerate() {
+                let target = remote
+                    .targets
+                    .iter()
+                    .find(|target| target.id == text_macro.target_id)
+                    .map(|target| target.name.as_str())
+                    .unwrap_or("(no target)");
+                println!(
+                    "{:>2} {}  -> {}  {}",
+                    i + 1,
+                    text_macro.name,
+                    target,
+                    text_macro.command
+                );
+            }
+        }
+        RemoteAction::Send { target, command } => {
+            let i = find_named(&remote.targets, &target, |t| t.name.as_str())?;
+            text_remote::send_text_command(&remote.targets[i], &command)?;
+            println!("Sent to {}.", remote.targets[i].name);
+        }
+        RemoteAction::Macro { name } => {
+            let i = find_named(&remote.macros, &name, |m| m.name.as_str())?;
+            text_remote::send_macro(remote, &remote.macros[i])?;
+            println!("Macro `{}` sent.", remote.macros[i].name);
+        }
+    }
+    Ok(())
+}
+
+fn run_tone(args: ToneArgs) -> Result<()> {
+    let settings = generator::ToneSettings {
+        destination_ip: args.ip,
+        destination_port: args.port,
+        stream_name: args.name,
+        audio_rate: args.rate,
+        audio_channels: args.channels,
+        audio_format: args.format,
+        waveform: if args.pink {
+            generator::Waveform::PinkNoise
+        } else {
+            generator::Waveform::Sine {
+                frequency: args.frequency,
+            }
+        },
+        amplitude: args.level,
+    };
+    println!(
+        "Sending \"{}\" to {}:{} ({} Hz, {}ch, {}).",
+        settings.stream_name,
+        settings.destination_ip,
+        settings.destination_port,
+        settings.audio_rate,
+        settings.audio_channels,
+        settings.audio_format
+    );
+
+    let packets = AtomicU32::new(0);
+    generator::run_tone(
+        &settings,
+        args.seconds.map(Duration::from_secs),
+        &AtomicBool::new(false),
+        &packets,
+    )?;
+    println!("{} packet(s) sent.", packets.into_inner());
+    Ok(())
+}
+
+fn run_import(paths: Vec<PathBuf>, adopt: bool, delete_originals: bool) -> Result<()> {
+    let fragments = if paths.is_empty() {
+        let scan = import::scan_dropin_dir()?;
+        for error in &scan.errors {
+            eprintln!("Skipped: {error}");
+        }
+        scan.fragments
+    } else {
+        paths
+            .iter()
+            .map(|path| import::import_file(path))
+            .collect::<Result<Vec<_>>>()?
+    };
+    if fragments.is_empty() {
+        println!("No VBAN module found.");
+        return Ok(());
+    }
+
+    for fragment in &fragments {
+        println!("{}", fragment.label());
+        for (i, send) in fragment.sends.iter().enumerate() {
+            println!("  send {}", format_send_line(i, send));
+        }
+        for (i, recv) in fragment.recvs.iter().enumerate() {
+            println!("  recv {}", format_recv_line(i, recv));
+        }
+    }
+    if !adopt {
+        println!("Run with --adopt to add them to the config.");
+        return Ok(());
+    }
+
+    let mut cfg = system::load_app_config()?;
+    let mut summary = import::adopt(&mut cfg, &fragments);
+    system::save_app_config(&cfg)?;
+    println!(
+        "Adopted {} send(s) and {} recv(s), {} already configured.",
+        summary.sends, summary.recvs, summary.skipped
+    );
+
+    if delete_originals {
+        import::delete_originals(&fragments, &mut summary)?;
+        for path in &summary.deleted {
+            println!("Deleted {}", path.display());
+        }
+        for path in &summary.kept {
+            println!(
+                "Kept {} (it has other modules, remove the VBAN ones by hand)",
+                path.display()
+            );
+        }
+    } else {
+        println!("The original files are still loaded by PipeWire; remove them before `rustban apply` to avoid duplicate streams.");
+    }
+    println!("Run `rustban apply` to update PipeWire.");
+    Ok(())
+}
+
+fn run_import_running(adopt: bool) -> Result<()> {
+    let mut cfg = system::load_app_config()?;
+    let modules = import::scan_running_modules(&cfg)?;
+    if modules.is_empty() {
+        println!("No unmanaged VBAN module is loaded.");
+        return Ok(());
+    }
+
+    for module in &modules {
+        println!("{}", module.label());
+    }
+    if !adopt {
+        println!("Run with --adopt to add them to the config.");
+        return Ok(());
+    }
+
+    let summary = import::adopt_modules(&mut cfg, &modules);
+    system::save_app_config(&cfg)?;
+    println!(
+        "Adopted {} send(s) and {} recv(s), {} already configured.",
+        summary.sends, summary.recvs, summary.skipped
+    );
+    println!("Unload the original modules before `rustban apply` to avoid duplicate streams.");
+    Ok(())
+}
+
+fn run_apply(restart: bool, dry_run: bool, live: bool) -> Result<()> {
+    let cfg: AppConfig = system::load_app_config()?;
+    let plan = system::plan_pipewire_fragments(&cfg)?;
+    for change in &plan.changes {
+        println!("{:<9} {}", change.kind.label(), change.file_name);
+        if dry_run {
+            print!("{}", change.diff);
+        }
+    }
+    if dry_run {
+        println!("Dry run ({}), nothing written.", plan.summary());
+        return Ok(());
+    }
+
+    if live {
+        let summary = live::hot_apply(&plan)?;
+        println!(
+            "Fragments applied ({}), {} module(s) unloaded and {} loaded live.",
+            plan.summary(),
+            summary.unloaded,
+            summary.loaded
+        );
+        for issue in &summary.issues {
+            eprintln!("Live apply warning: {issue}");
+        }
+    } else {
+        plan.apply(restart)?;
+        println!("Fragments applied ({}).", plan.summary());
+        if restart {
+            println!("PipeWire restarted and healthy.");
+        }
+    }
+
+    let summary = if live || restart {
+        let timeout = Duration::from_secs(cfg.autolink.wait_timeout_secs);
+        system::autolink_streams_when_ready(&cfg, timeout, |progress| eprintln!("{progress}"))?
+    } else {
+        system::autolink_streams(&cfg)?
+    };
+    if let Some(changes) = summary.changes() {
+        println!("Auto-link: {changes}.");
+    }
+    for issue in &summary.issues {
+        eprintln!("Auto-link warning: {issue}");
+    }
+    Ok(())
+}
+
+fn run_daemon() -> Result<()> {
+    let config_path = system::config_dir()?.join("config.toml");
+    let modified = || {
+        fs::metadata(&config_path)
+            .and_then(|meta| meta.modified())
+            .ok()
+    };
+    let watched = |cfg: &AppConfig| {
+        let sends = cfg
+            .sends
+            .iter()
+            .filter(|send| send.enabled && !send.target_object.trim().is_empty());
+        let recvs = cfg
+            .recvs
+            .iter()
+            .filter(|recv| recv.enabled && !recv.target_sink.trim().is_empty());
+        sends.count() + recvs.count()
+    };
+
+    let mut cfg = system::load_app_config()?;
+    let mut seen = modified();
+    let mut watcher = autolink::AutoLinkWatcher::spawn(&cfg);
+    let mut passes = 0;
+    println!(
+        "Watching the PipeWire graph for {} auto-linked stream(s).",
+        watched(&cfg)
+    );
+
+    loop {
+        thread::sleep(Duration::from_secs(1));
+        if let Some(e) = watcher.take_error() {
+            return Err(e.context("Auto-link watcher stopped"));
+        }
+        if watcher.passes() != passes {
+            passes = watcher.passes();
+            if let Some(summary) = watcher.last_summary() {
+                println!(
+                    "Auto-link pass {passes}: {}.",
+                    summary.changes().as_deref().unwrap_or("no change")
+                );
+                for issue in &summary.issues {
+                    eprintln!("Auto-link warning: {issue}");
+                }
+            }
+        }
+
+        if modified() != seen {
+            seen = modified();
+            cfg = system::load_app_config()?;
+            if !watcher.watches(&cfg) {
+                watcher = autolink::AutoLinkWatcher::spawn(&cfg);
+                passes = 0;
+                println!(
+                    "Config changed, watching {} auto-linked stream(s).",
+                    watched(&cfg)
+                );
+            }
+        }
+    }
+}
+
+fn run_ping_responder(port: Option<u16>) -> Result<()> {
+    let cfg = system::load_app_config()?;
+    let port = port.unwrap_or(cfg.host_info_emulation.ping_port);
+    let identity = service::Ping0::from_config(&cfg);
+
+    let socket = UdpSocket::bind(("0.0.0.0", port))
+        .with_context(|| format!("Could not listen on UDP port {port}"))?;
+    socket.set_read_timeout(Some(Duration::from_millis(200)))?;
+    println!(
+        "Answering VBAN PING0 on UDP port {port} as `{}` ({}@{}).",
+        identity.device_name, identity.user_name, identity.host_name
+    );
+    service::serve_pings(&socket, &identity, &AtomicBool::new(false))
+}
+
+fn run_status(json: bool) -> Result<()> {
+    let report = status::collect_status()?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    println!("Sends:");
+    for (i, send) in report.config.sends.iter().enumerate() {
+        println!("  {}", format_send_line(i, send));
+    }
+    println!("Recvs:");
+    for (i, recv) in report.config.recvs.iter().enumerate() {
+        println!("  {}", format_recv_line(i, recv));
+    }
+
+    println!("Fragments in {}:", report.fragments.dir.display());
+    for file in &report.fragments.files {
+        println!(
+            "  {}{}",
+            file.file_name,
+            if file.configured { "" } else { "  (stale)" }
+        );
+    }
+
+    match &report.autolink {
+        Some(record) => {
+            println!(
+                "Last auto-link: {} link(s) created, {} removed, {} issue(s).",
+                record.summary.links_created,
+                record.summary.links_removed,
+                record.summary.issues.len()
+            );
+            for issue in &record.summary.issues {
+                println!("  {issue}");
+            }
+        }
+        None => println!("Last auto-link: never run."),
+    }
+    Ok(())
+}
+
+/// Common lookup keys for send and recv entries.
+trait StreamEntry {
+    fn id(&self) -> Uuid;
+    fn node_name(&self) -> &str;
+}
+
+impl StreamEntry for VbanSend {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn node_name(&self) -> &str {
+        &self.node_name
+    }
+}
+
+impl StreamEntry for VbanRecv {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn node_name(&self) -> &str {
+        &self.node_name
+    }
+}
+
+/// Resolves a CLI target (1-based index, full/simple UUID or node.name) to an index.
+fn find_entry<T: StreamEntry>(entries: &[T], target: &str) -> Result<usize> {
+    let target = target.trim();
+
+    if let Ok(index) = target.parse::<usize>() {
+        if (1..=entries.len()).contains(&index) {
+            return Ok(index - 1);
+        }
+    }
+
+    if let Ok(id) = Uuid::parse_str(target) {
+        if let Some(i) = entries.iter().position(|entry| entry.id() == id) {
+            return Ok(i);
+        }
+    }
+
+    entries
+        .iter()
+        .position(|entry| entry.node_name() == target)
+        .with_context(|| format!("No entry matches `{target}` (use an index, id or node.name)"))
+}
+
+/// Resolves a 1-based index or an exact name.
+fn find_named<T>(entries: &[T], target: &str, name: impl Fn(&T) -> &str) -> Result<usize> {
+    let target = target.trim();
+    if let Ok(index) = target.parse::<usize>() {
+        if (1..=entries.len()).contains(&index) {
+            return Ok(index - 1);
+        }
+    }
+
+    entries
+        .iter()
+        .position(|entry| name(entry) == target)
+        .with_context(|| format!("No entry matches `{target}` (use an index or name)"))
+}
+
+fn format_send_line(i: usize, send: &VbanSend) -> String {
+    let target = send.target_object.trim();
+    format!(
+        "{:>2} [{}] {}  \"{}\" -> {}:{}  {} {}Hz {}ch  node={}{}{}",
+        i + 1,
+        if send.enabled { "on " } else { "off" },
+        send.id.simple(),
+        send.sess_name,
+        send.destination_ip,
+        send.destination_port,
+        send.audio_format,
+        send.audio_rate,
+        send.audio_channels,
+        send.node_name,
+        if target.is_empty() {
+            String::new()
+        } else {
+            format!("  source={target}")
+        },
+        send.channel_map
+            .as_ref()
+            .map(|map| format!("  map={map}"))
+            .unwrap_or_default(),
+    )
+}
+
+fn format_recv_line(i: usize, recv: &VbanRecv) -> String {
+    let stream_name = recv.stream_name.trim();
+    let sink = recv.target_sink.trim();
+    format!(
+        "{:>2} [{}] {}  \"{}\" <- {}:{}  latency={}ms  node={}{}",
+        i + 1,
+        if recv.enabled { "on " } else { "off" },
+        recv.id.simple(),
+        if stream_name.is_empty() {
+            "*"
+        } else {
+            stream_name
+        },
+        recv.source_ip,
+        recv.source_port,
+        recv.latency_msec,
+        recv.node_name,
+        if sink.is_empty() {
+            String::new()
+        } else {
+            format!("  sink={sink}")
+        },
+    )
+}
+
+fn format_relay_line(i: usize, relay: &VbanRelay) -> String {
+    let filter = relay.stream_filter.trim();
+    let rename = relay.rename.trim();
+    let destinations: Vec<_> = relay
+        .destinations
+        .iter()
+        .map(|destination| format!("{}:{}", destination.ip, destination.port))
+        .collect();
+    format!(
+        "{:>2} [{}] {}  :{} \"{}\"{} -> {}",
+        i + 1,
+        if relay.enabled { "on " } else { "off" },
+        relay.name,
+        relay.listen_port,
+        if filter.is_empty() { "*" } else { filter },
+        if rename.is_empty() {
+            String::new()
+        } else {
+            format!(" as \"{rename}\"")
+        },
+        if destinations.is_empty() {
+            "(no destination)".to_string()
+        } else {
+            destinations.join(", ")
+        }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn cli_definition_is_valid() {
+        Cli::command().debug_assert();
+    }
+
+    fn sends() -> Vec<VbanSend> {
+        vec![
+            VbanSend {
+                node_name: "mic-a".into(),
+                ..VbanSend::default()
+            },
+            VbanSend {
+                node_name: "mic-b".into(),
+                ..VbanSend::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn finds_entry_by_index_id_and_node_name() {
+        let sends = sends();
+        assert_eq!(find_entry(&sends, "2").unwrap(), 1);
+        assert_eq!(
+            find_entry(&sends, &sends[1].id.simple().to_string()).unwrap(),
+            1
+        );
+        assert_eq!(find_entry(&sends, &sends[0].id.to_string()).unwrap(), 0);
+        assert_eq!(find_entry(&sends, "mic-b").unwrap(), 1);
+        assert!(find_entry(&sends, "3").is_err());
+        assert!(find_entry(&sends, "unknown").is_err());
+    }
+
+    #[test]
+    fn parses_ipv4_destinations_and_rejects_ipv6() {
+        let dest = parse_destination(" 10.0.0.5:6981 ").unwrap();
+        assert_eq!((dest.ip.as_str(), dest.port), ("10.0.0.5", 6981));
+        let dest = parse_destination("mixer.local").unwrap();
+        assert_eq!((dest.ip.as_str(), dest.port), ("mixer.local", 6980));
+        assert!(parse_destination("10.0.0.5:0").is_err());
+        assert!(parse_destination("[::1]:6980")
+            .unwrap_err()
+            .contains("IPv6"));
+        assert!(parse_destination("fe80::1").unwrap_err().contains("IPv6"));
+    }
+}
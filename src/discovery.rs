@@ -0,0 +1,287 @@
+use anyhow::{Context, Result};
+use std::{
+    io::ErrorKind,
+    net::{IpAddr, Ipv4Addr, SocketAddr, ToSocketAddrs, UdpSocket},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    model::HostDiscovery,
+    service::{device_type_label, Ping0, ServicePacket},
+    vban::{Codec, DataType, VbanHeader, MAX_PACKET_SIZE},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredStream {
+    pub source_ip: IpAddr,
+    pub stream_name: String,
+    pub sample_rate: Option<u32>,
+    pub channels: u16,
+    pub data_type: DataType,
+    pub codec: Codec,
+    pub packets: u64,
+}
+
+impl DiscoveredStream {
+    pub fn format_label(&self) -> String {
+        let rate = self
+            .sample_rate
+            .map(|rate| format!("{rate} Hz"))
+            .unwrap_or_else(|| "? Hz".to_string());
+        let codec = if self.codec == Codec::Pcm {
+            String::new()
+        } else {
+            format!(" {:?}", self.codec)
+        };
+        format!(
+            "{rate}, {}ch, {}{codec}",
+            self.channels,
+            self.data_type.label()
+        )
+    }
+}
+
+/// Aggregates VBAN audio packets per (source IP, stream name).
+#[derive(Debug, Default)]
+struct StreamScan {
+    streams: Vec<DiscoveredStream>,
+}
+
+impl StreamScan {
+    fn record(&mut self, source_ip: IpAddr, packet: &[u8]) {
+        let Ok(header) = VbanHeader::parse(packet) else {
+            return;
+        };
+        let Some(codec) = header.codec() else {
+            return;
+        };
+
+        if let Some(stream) = self
+            .streams
+            .iter_mut()
+            .find(|s| s.source_ip == source_ip && s.stream_name == header.stream_name)
+        {
+            stream.packets += 1;
+            stream.sample_rate = header.sample_rate();
+            stream.channels = header.channels;
+            stream.data_type = header.data_type;
+            stream.codec = codec;
+            return;
+        }
+
+        self.streams.push(DiscoveredStream {
+            source_ip,
+            sample_rate: header.sample_rate(),
+            channels: header.channels,
+            data_type: header.data_type,
+            codec,
+            stream_name: header.stream_name,
+            packets: 1,
+        });
+    }
+
+    fn finish(mut self) -> Vec<DiscoveredStream> {
+        self.streams.sort_by(|a, b| {
+            a.source_ip
+                .cmp(&b.source_ip)
+                .then_with(|| a.stream_name.cmp(&b.stream_name))
+        });
+        self.streams
+    }
+}
+
+/// Listens on `port` for `duration` and returns the VBAN audio streams seen.
+pub fn scan_streams(port: u16, duration: Duration) -> Result<Vec<DiscoveredStream>> {
+    let socket = UdpSocket::bind(("0.0.0.0", port)).with_context(|| {
+        format!("Could not listen on UDP port {port} (is a PipeWire vban-recv using it?)")
+    })?;
+    socket.set_read_timeout(Some(Duration::from_millis(100)))?;
+
+    let deadline = Instant::now() + duration;
+    let mut scan = StreamScan::default();
+    let mut buf = [0u8; MAX_PACKET_SIZE];
+
+    while Instant::now() < deadline {
+        match socket.recv_from(&mut buf) {
+            Ok((len, addr)) => scan.record(addr.ip(), &buf[..len]),
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(e) => return Err(e).context("Could not receive VBAN packet"),
+        }
+    }
+
+    Ok(scan.finish())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredHost {
+    pub addr: SocketAddr,
+    pub info: Ping0,
+}
+
+impl DiscoveredHost {
+    pub fn label(&self) -> String {
+        let name = if self.info.device_name.is_empty() {
+            self.addr.ip().to_string()
+        } else {
+            self.info.device_name.clone()
+        };
+        format!(
+            "{name} @ {} ({}, {})",
+            self.info.host_name,
+            self.info.application_name,
+            device_type_label(self.info.device_type)
+        )
+    }
+}
+
+/// Keeps the latest PING0 reply per responding IP.
+#[derive(Debug, Default)]
+struct HostScan {
+    hosts: Vec<DiscoveredHost>,
+}
+
+impl HostScan {
+    fn record(&mut self, addr: SocketAddr, packet: &[u8]) {
+        let Ok(reply) = ServicePacket::parse(packet) else {
+            return;
+        };
+        if !reply.is_ping_reply() {
+            return;
+        }
+        let Ok(info) = Ping0::parse(&reply.payload) else {
+            return;
+        };
+
+        let host = DiscoveredHost { addr, info };
+        match self.hosts.iter_mut().find(|h| h.addr.ip() == addr.ip()) {
+            Some(existing) => *existing = host,
+            None => self.hosts.push(host),
+        }
+    }
+
+    fn finish(mut self) -> Vec<DiscoveredHost> {
+        self.hosts.sort_by_key(|host| host.addr.ip());
+        self.hosts
+    }
+}
+
+/// Sends a PING0 to the broadcast address and/or configured addresses and collects replies.
+pub fn discover_hosts(
+    settings: &HostDiscovery,
+    identity: &Ping0,
+    duration: Duration,
+) -> Result<Vec<DiscoveredHost>> {
+    let socket = UdpSocket::bind(("0.0.0.0", 0)).context("Could not open UDP socket")?;
+    socket.set_broadcast(true)?;
+    socket.set_read_timeout(Some(Duration::from_millis(100)))?;
+
+    let mut targets = Vec::new();
+    if settings.broadcast {
+        targets.push(SocketAddr::from((Ipv4Addr::BROADCAST, settings.port)));
+    }
+    for address in &settings.addresses {
+        let address = address.trim();
+        if address.is_empty() {
+            continue;
+        }
+        let resolved = if address.contains(':') {
+            address.to_socket_addrs()
+        } else {
+            (address, settings.port).to_socket_addrs()
+        }
+        .with_context(|| format!("Could not resolve `{address}`"))?;
+        targets.extend(resolved.filter(SocketAddr::is_ipv4));
+    }
+    if targets.is_empty() {
+        anyhow::bail!("No discovery target: enable broadcast or add addresses");
+    }
+
+    let request = ServicePacket::ping(0, identity).to_bytes()?;
+    for target in &targets {
+        socket
+            .send_to(&request, target)
+            .with_context(|| format!("Could not send PING0 to {target}"))?;
+    }
+
+    let deadline = Instant::now() + duration;
+    let mut scan = HostScan::default();
+    let mut buf = [0u8; MAX_PACKET_SIZE];
+    while Instant::now() < deadline {
+        match socket.recv_from(&mut buf) {
+            Ok((len, addr)) => scan.record(addr, &buf[..len]),
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(e) => return Err(e).context("Could not receive PING0 reply"),
+        }
+    }
+
+    Ok(scan.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::DEVICE_TYPE_VIRTUAL_MIXER;
+
+    fn audio_packet(name: &str, rate: u32, channels: u16) -> Vec<u8> {
+        let header = VbanHeader::audio(name, rate, channels, 256, DataType::Int16).unwrap();
+        let mut packet = header.to_bytes().unwrap().to_vec();
+        packet.extend_from_slice(&vec![0; 256 * usize::from(channels) * 2]);
+        packet
+    }
+
+    #[test]
+    fn groups_packets_by_source_and_stream_name() {
+        let a = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 5));
+        let b = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+        let mut scan = StreamScan::default();
+
+        scan.record(a, &audio_packet("Mic1", 48_000, 2));
+        scan.record(a, &audio_packet("Mic1", 48_000, 2));
+        scan.record(a, &audio_packet("Desk", 44_100, 1));
+        scan.record(b, &audio_packet("Mic1", 48_000, 2));
+        scan.record(b, b"not a vban packet at all, really");
+
+        let mut service = audio_packet("Ping", 48_000, 1);
+        service[4] = 0x60;
+        scan.record(b, &service);
+
+        let streams = scan.finish();
+        let summary: Vec<_> = streams
+            .iter()
+            .map(|s| (s.source_ip, s.stream_name.as_str(), s.packets))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![(b, "Mic1", 1), (a, "Desk", 1), (a, "Mic1", 2)]
+        );
+        assert_eq!(streams[1].sample_rate, Some(44_100));
+        assert_eq!(streams[1].channels, 1);
+        assert_eq!(streams[1].format_label(), "44100 Hz, 1ch, 16-bit");
+    }
+
+    #[test]
+    fn keeps_ping_replies_only() {
+        let info = Ping0 {
+            device_type: DEVICE_TYPE_VIRTUAL_MIXER,
+            device_name: "Voicemeeter".into(),
+            host_name: "studio-pc".into(),
+            application_name: "Voicemeeter Potato".into(),
+            ..Ping0::default()
+        };
+        let addr: SocketAddr = "192.168.1.20:6980".parse().unwrap();
+        let request = ServicePacket::ping(1, &info);
+        let reply = request.reply_to(&info);
+        let mut scan = HostScan::default();
+
+        scan.record(addr, &request.to_bytes().unwrap());
+        scan.record(addr, &reply.to_bytes().unwrap());
+        scan.record(addr, &reply.to_bytes().unwrap());
+
+        let hosts = scan.finish();
+        assert_eq!(hosts.len(), 1);
+        assert_eq!(
+            hosts[0].label(),
+            "Voicemeeter @ studio-pc (Voicemeeter Potato, virtual mixer)"
+        );
+    }
+}
@@ -0,0 +1,249 @@
+use anyhow::{bail, Result};
+use std::{
+    f32::consts::TAU,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    stream_out::{samples_per_frame, AudioStreamOut},
+    vban::{DataType, VbanHeader},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Waveform {
+    Sine { frequency: f32 },
+    PinkNoise,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToneSettings {
+    pub destination_ip: String,
+    pub destination_port: u16,
+    pub stream_name: String,
+    pub audio_rate: u32,
+    pub audio_channels: u16,
+    /// PipeWire-style format name, e.g. `S16LE`.
+    pub audio_format: String,
+    pub waveform: Waveform,
+    /// Linear gain, 0.0..=1.0.
+    pub amplitude: f32,
+}
+
+impl Default for ToneSettings {
+    fn default() -> Self {
+        Self {
+            destination_ip: "127.0.0.1".into(),
+            destination_port: 6980,
+            stream_name: "Stream1".into(),
+            audio_rate: 48_000,
+            audio_channels: 2,
+            audio_format: "S16LE".into(),
+            waveform: Waveform::Sine { frequency: 440.0 },
+            amplitude: 0.25,
+        }
+    }
+}
+
+/// Produces mono samples for a waveform; every channel gets the same signal.
+struct Oscillator {
+    waveform: Waveform,
+    rate: f32,
+    phase: f32,
+    rng: u32,
+    pink: [f32; 7],
+}
+
+impl Oscillator {
+    fn new(waveform: Waveform, rate: u32) -> Self {
+        Self {
+            waveform,
+            rate: rate as f32,
+            phase: 0.0,
+            rng: 0x1234_5678,
+            pink: [0.0; 7],
+        }
+    }
+
+    fn next_sample(&mut self) -> f32 {
+        match self.waveform {
+            Waveform::Sine { frequency } => {
+                let sample = (self.phase * TAU).sin();
+                self.phase = (self.phase + frequency / self.rate).fract();
+                sample
+            }
+            Waveform::PinkNoise => {
+                // Paul Kellet's economy pink filter over xorshift white noise.
+                self.rng ^= self.rng << 13;
+                self.rng ^= self.rng >> 17;
+                self.rng ^= self.rng << 5;
+                let white = (self.rng as f32 / u32::MAX as f32) * 2.0 - 1.0;
+
+                let b = &mut self.pink;
+                b[0] = 0.99886 * b[0] + white * 0.0555179;
+                b[1] = 0.99332 * b[1] + white * 0.0750759;
+                b[2] = 0.96900 * b[2] + white * 0.153852;
+                b[3] = 0.86650 * b[3] + white * 0.3104856;
+                b[4] = 0.55000 * b[4] + white * 0.5329522;
+                b[5] = -0.7616 * b[5] - white * 0.0168980;
+                let pink = b[0] + b[1] + b[2] + b[3] + b[4] + b[5] + b[6] + white * 0.5362;
+                b[6] = white * 0.115926;
+                pink * 0.11
+            }
+        }
+    }
+
+    fn fill_frame(
+        &mut self,
+        samples: u16,
+        channels: u16,
+        data_type: DataType,
+        amplitude: f32,
+        out: &mut Vec<u8>,
+    ) -> Result<()> {
+        out.clear();
+        for _ in 0..samples {
+            let sample = self.next_sample() * amplitude;
+            for _ in 0..channels {
+                data_type.encode_sample(sample, out)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Streams the tone until `stop` is set or `duration` elapses, publishing the packet count.
+pub fn run_tone(
+    settings: &ToneSettings,
+    duration: Option<Duration>,
+    stop: &AtomicBool,
+    packets: &AtomicU32,
+) -> Result<()> {
+    let Some(data_type) = DataType::from_audio_format(&settings.audio_format) else {
+        bail!("Unsupported audio format `{}`", settings.audio_format);
+    };
+    let samples = samples_per_frame(settings.audio_channels, data_type)?;
+    let header = VbanHeader::audio(
+        settings.stream_name.trim(),
+        settings.audio_rate,
+        settings.audio_channels,
+        samples,
+        data_type,
+    )?;
+    let mut out =
+        AudioStreamOut::connect(&settings.destination_ip, settings.destination_port, header)?;
+
+    let mut oscillator = Oscillator::new(settings.waveform, settings.audio_rate);
+    let amplitude = settings.amplitude.clamp(0.0, 1.0);
+    let deadline = duration.map(|duration| Instant::now() + duration);
+    let mut payload = Vec::new();
+
+    while !stop.load(Ordering::Relaxed) && deadline.is_none_or(|d| Instant::now() < d) {
+        oscillator.fill_frame(
+            samples,
+            settings.audio_channels,
+            data_type,
+            amplitude,
+            &mut payload,
+        )?;
+        out.send(&payload)?;
+        packets.store(out.frames_sent(), Ordering::Relaxed);
+    }
+    Ok(())
+}
+
+/// Background test-tone stream, stopped when dropped.
+pub struct ToneGenerator {
+    settings: ToneSettings,
+    stop: Arc<AtomicBool>,
+    packets: Arc<AtomicU32>,
+    handle: Option<JoinHandle<Result<()>>>,
+}
+
+impl ToneGenerator {
+    pub fn spawn(settings: ToneSettings) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let packets = Arc::new(AtomicU32::new(0));
+        let handle = {
+            let settings = settings.clone();
+            let stop = Arc::clone(&stop);
+            let packets = Arc::clone(&packets);
+            thread::spawn(move || run_tone(&settings, None, &stop, &packets))
+        };
+
+        Self {
+            settings,
+            stop,
+            packets,
+            handle: Some(handle),
+        }
+    }
+
+    pub fn settings(&self) -> &ToneSettings {
+        &self.settings
+    }
+
+    pub fn packets_sent(&self) -> u32 {
+        self.packets.load(Ordering::Relaxed)
+    }
+
+    /// Returns the thread error once the generator has stopped on its own.
+    pub fn take_error(&mut self) -> Option<anyhow::Error> {
+        if !self.handle.as_ref()?.is_finished() {
+            return None;
+        }
+        match self.handle.take()?.join() {
+            Ok(Ok(())) => None,
+            Ok(Err(e)) => Some(e),
+            Err(_) => Some(anyhow::anyhow!("tone thread panicked")),
+        }
+    }
+}
+
+impl Drop for ToneGenerator {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sine_starts_at_zero_and_peaks_at_quarter_period() {
+        let mut oscillator = Oscillator::new(Waveform::Sine { frequency: 1_000.0 }, 48_000);
+        let samples: Vec<f32> = (0..13).map(|_| oscillator.next_sample()).collect();
+
+        assert_eq!(samples[0], 0.0);
+        assert!((samples[12] - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn pink_noise_stays_in_range() {
+        let mut oscillator = Oscillator::new(Waveform::PinkNoise, 48_000);
+        let samples: Vec<f32> = (0..48_000).map(|_| oscillator.next_sample()).collect();
+
+        assert!(samples.iter().all(|s| s.abs() <= 1.0));
+        assert!(samples.iter().any(|s| s.abs() > 0.05));
+    }
+
+    #[test]
+    fn frame_is_interleaved_per_channel() {
+        let mut oscillator = Oscillator::new(Waveform::Sine { frequency: 440.0 }, 48_000);
+        let mut out = Vec::new();
+        oscillator
+            .fill_frame(256, 2, DataType::Int16, 0.5, &mut out)
+            .unwrap();
+
+        assert_eq!(out.len(), 256 * 2 * 2);
+        assert_eq!(out[4..6], out[6..8]);
+    }
+}
@@ -0,0 +1,527 @@
+use anyhow::{Context, Result};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    model::{AppConfig, VbanRecv, VbanSend},
+    pipewire_conf::{parse_document, SpaObject, SpaValue},
+    system::{self, PipewireModule},
+};
+
+const VBAN_SEND_MODULE: &str = "libpipewire-module-vban-send";
+const VBAN_RECV_MODULE: &str = "libpipewire-module-vban-recv";
+
+/// VBAN modules found in one hand-written PipeWire config file.
+#[derive(Debug, Clone)]
+pub struct ImportedFragment {
+    pub path: PathBuf,
+    pub sends: Vec<VbanSend>,
+    pub recvs: Vec<VbanRecv>,
+    /// Other modules in the same file; the file is never deleted when there are any.
+    pub other_modules: usize,
+}
+
+impl ImportedFragment {
+    pub fn label(&self) -> String {
+        let mut label = format!(
+            "{}: {} send(s), {} recv(s)",
+            self.path.display(),
+            self.sends.len(),
+            self.recvs.len()
+        );
+        if self.other_modules > 0 {
+            label.push_str(&format!(", {} other module(s)", self.other_modules));
+        }
+        label
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AdoptSummary {
+    pub sends: usize,
+    pub recvs: usize,
+    /// Entries whose node.name was already configured.
+    pub skipped: usize,
+    pub deleted: Vec<PathBuf>,
+    pub kept: Vec<PathBuf>,
+}
+
+/// The stream of a vban module loaded in the running PipeWire.
+#[derive(Debug, Clone)]
+pub enum ModuleStream {
+    Send(VbanSend),
+    Recv(VbanRecv),
+}
+
+/// A running vban module whose node.name is not in the config (e.g. loaded by `pw-cli`).
+#[derive(Debug, Clone)]
+pub struct UnmanagedModule {
+    pub module_id: u32,
+    pub stream: ModuleStream,
+}
+
+impl ModuleStream {
+    pub fn node_name(&self) -> &str {
+        match self {
+            Self::Send(send) => &send.node_name,
+            Self::Recv(recv) => &recv.node_name,
+        }
+    }
+}
+
+impl UnmanagedModule {
+    pub fn label(&self) -> String {
+        match &self.stream {
+            ModuleStream::Send(send) => format!(
+                "module {}: send \"{}\" -> {}:{}  node={}",
+                self.module_id,
+                send.sess_name,
+                send.destination_ip,
+                send.destination_port,
+                send.node_name
+            ),
+            ModuleStream::Recv(recv) => format!(
+                "module {}: recv <- {}:{}  node={}",
+                self.module_id, recv.source_ip, recv.source_port, recv.node_name
+            ),
+        }
+    }
+}
+
+fn text(object: &SpaObject, key: &str) -> Option<String> {
+    object.get(key).and_then(SpaValue::as_text)
+}
+
+fn int<T: TryFrom<i64>>(object: &SpaObject, key: &str) -> Option<T> {
+    object
+        .get(key)
+        .and_then(SpaValue::as_int)
+        .and_then(|value| T::try_from(value).ok())
+}
+
+fn object<'a>(parent: &'a SpaObject, key: &str) -> Option<&'a SpaObject> {
+    parent.get(key).and_then(SpaValue::as_object)
+}
+
+fn send_from_args(args: &SpaObject) -> VbanSend {
+    let mut send = VbanSend::default();
+    let empty = SpaObject::new();
+    let props = object(args, "stream.props").unwrap_or(&empty);
+
+    if let Some(ip) = text(args, "destination.ip") {
+        send.destination_ip = ip;
+    }
+    if let Some(port) = int(args, "destination.port") {
+        send.destination_port = port;
+    }
+    if let Some(name) = text(args, "sess.name") {
+        send.sess_name = name;
+    }
+    if let Some(media) = text(args, "sess.media") {
+        send.sess_media = media;
+    }
+    if let Some(format) = text(args, "audio.format") {
+        send.audio_format = format;
+    }
+    if let Some(rate) = int(args, "audio.rate") {
+        send.audio_rate = rate;
+    }
+    if let Some(channels) = int(args, "audio.channels") {
+        send.audio_channels = channels;
+    }
+    if let Some(always_process) = props
+        .get("node.always-process")
+        .or_else(|| args.get("node.always-process"))
+        .and_then(SpaValue::as_bool)
+    {
+        send.always_process = always_process;
+    }
+    if let Some(node_name) = text(props, "node.name") {
+        send.node_name = node_name;
+    }
+    if let Some(description) = text(props, "node.description") {
+        send.node_description = description;
+    }
+    if let Some(target) = text(props, "target.object") {
+        send.target_object = target;
+    }
+    send
+}
+
+fn recv_from_args(args: &SpaObject) -> VbanRecv {
+    let mut recv = VbanRecv::default();
+    if let Some(ip) = text(args, "source.ip") {
+        recv.source_ip = ip;
+    }
+    if let Some(port) = int(args, "source.port") {
+        recv.source_port = port;
+    }
+    if let Some(latency) = int(args, "sess.latency.msec") {
+        recv.latency_msec = latency;
+    }
+    if let Some(always_process) = args.get("node.always-process").and_then(SpaValue::as_bool) {
+        recv.always_process = always_process;
+    }
+
+    // Newer configs create streams through rules; older ones put stream.props in args.
+    let rule = args
+        .get("stream.rules")
+        .and_then(SpaValue::as_array)
+        .and_then(|rules| rules.first())
+        .and_then(SpaValue::as_object);
+    let props = match rule {
+        Some(rule) => {
+            let sess_name = rule
+                .get("matches")
+                .and_then(SpaValue::as_array)
+                .and_then(|matches| matches.first())
+                .and_then(SpaValue::as_object)
+                .and_then(|matches| text(matches, "sess.name"));
+            if let Some(sess_name) = sess_name {
+                recv.stream_name = if sess_name == "~.*" {
+                    String::new()
+                } else {
+                    sess_name
+                };
+            }
+            object(rule, "actions")
+                .and_then(|actions| object(actions, "create-stream"))
+                .and_then(|create| object(create, "stream.props"))
+        }
+        None => object(args, "stream.props"),
+    };
+
+    if let Some(props) = props {
+        if let Some(node_name) = text(props, "node.name") {
+            recv.node_name = node_name;
+        }
+        if let Some(description) = text(props, "node.description") {
+            recv.node_description = description;
+        }
+        if let Some(target) = text(props, "target.object") {
+            recv.target_sink = target;
+        }
+    }
+    recv
+}
+
+/// Extracts the vban-send/recv modules of a parsed config file.
+fn extract_fragment(path: &Path, root: &SpaObject) -> ImportedFragment {
+    let mut fragment = ImportedFragment {
+        path: path.to_path_buf(),
+        sends: Vec::new(),
+        recvs: Vec::new(),
+        other_modules: 0,
+    };
+    let modules = root
+        .get("context.modules")
+        .and_then(SpaValue::as_array)
+        .unwrap_or_default();
+
+    for module in modules.iter().filter_map(SpaValue::as_object) {
+        let empty = SpaObject::new();
+        let args = object(module, "args").unwrap_or(&empty);
+        match text(module, "name").as_deref() {
+            Some(VBAN_SEND_MODULE) => fragment.sends.push(send_from_args(args)),
+            Some(VBAN_RECV_MODULE) => fragment.recvs.push(recv_from_args(args)),
+            _ => fragment.other_modules += 1,
+        }
+    }
+    fragment
+}
+
+pub fn import_file(path: &Path) -> Result<ImportedFragment> {
+    let raw =
+        fs::read_to_string(path).with_context(|| format!("Could not read {}", path.display()))?;
+    let root =
+        parse_document(&raw).with_context(|| format!("Could not parse {}", path.display()))?;
+    Ok(extract_fragment(path, &root))
+}
+
+/// Fragments with VBAN modules found by a drop-in scan, and the files that could not be read.
+#[derive(Debug, Default)]
+pub struct DropinScan {
+    pub fragments: Vec<ImportedFragment>,
+    pub errors: Vec<String>,
+}
+
+/// Reads the `.conf` files of the PipeWire drop-in directory that RustBAN did not write.
+pub fn scan_dropin_dir() -> Result<DropinScan> {
+    let dir = system::pipewire_dropin_dir()?;
+    if !dir.is_dir() {
+        return Ok(DropinScan::default());
+    }
+    scan_conf_dir(&dir, &system::list_rustban_fragments()?)
+}
+
+/// Reads every `.conf` file of `dir` except `rustban_fragments`; a file that fails to parse
+/// is reported in `errors` without stopping the scan.
+fn scan_conf_dir(dir: &Path, rustban_fragments: &[String]) -> Result<DropinScan> {
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_conf = path.extension().is_some_and(|ext| ext == "conf");
+        let is_ours = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| rustban_fragments.iter().any(|ours| ours == name));
+        if is_conf && !is_ours && path.is_file() {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    let mut scan = DropinScan::default();
+    for path in paths {
+        match import_file(&path) {
+            Ok(fragment) if !fragment.sends.is_empty() || !fragment.recvs.is_empty() => {
+                scan.fragments.push(fragment);
+            }
+            Ok(_) => {}
+            Err(e) => scan.errors.push(format!("{e:#}")),
+        }
+    }
+    Ok(scan)
+}
+
+/// Reads the stream a running vban module was loaded with.
+pub fn module_stream(module: &PipewireModule) -> Result<Option<ModuleStream>> {
+    let args = if module.args.trim().is_empty() {
+        SpaObject::new()
+    } else {
+        parse_document(&module.args)
+            .with_context(|| format!("Could not parse the args of module {}", module.id))?
+    };
+    Ok(match module.name.as_str() {
+        VBAN_SEND_MODULE => {
+            let mut send = send_from_args(&args);
+            // Without a node.name in the args, name it after the module so rescans match.
+            if send.node_name == format!("vban-send-{}", send.id.simple()) {
+                send.node_name = format!("vban-send-module-{}", module.id);
+            }
+            Some(ModuleStream::Send(send))
+        }
+        VBAN_RECV_MODULE => {
+            let mut recv = recv_from_args(&args);
+            if recv.node_name == format!("vban-recv-{}", recv.id.simple()) {
+                recv.node_name = format!("vban-recv-module-{}", module.id);
+            }
+            Some(ModuleStream::Recv(recv))
+        }
+        _ => None,
+    })
+}
+
+/// Turns the running vban modules that no config entry manages into streams.
+fn unmanaged_modules(cfg: &AppConfig, modules: &[PipewireModule]) -> Result<Vec<UnmanagedModule>> {
+    let mut unmanaged = Vec::new();
+    for module in modules {
+        let Some(stream) = module_stream(module)? else {
+            continue;
+        };
+        let managed = match &stream {
+            ModuleStream::Send(send) => cfg.sends.iter().any(|s| s.node_name == send.node_name),
+            ModuleStream::Recv(recv) => cfg.recvs.iter().any(|r| r.node_name == recv.node_name),
+        };
+        if !managed {
+            unmanaged.push(UnmanagedModule {
+                module_id: module.id,
+                stream,
+            });
+        }
+    }
+    Ok(unmanaged)
+}
+
+/// Lists the vban modules of the running PipeWire that are not in `cfg`.
+pub fn scan_running_modules(cfg: &AppConfig) -> Result<Vec<UnmanagedModule>> {
+    unmanaged_modules(cfg, &system::list_vban_modules()?)
+}
+
+fn adopt_send(cfg: &mut AppConfig, send: &VbanSend, summary: &mut AdoptSummary) {
+    if cfg.sends.iter().any(|s| s.node_name == send.node_name) {
+        summary.skipped += 1;
+        return;
+    }
+    cfg.sends.push(send.clone());
+    summary.sends += 1;
+}
+
+fn adopt_recv(cfg: &mut AppConfig, recv: &VbanRecv, summary: &mut AdoptSummary) {
+    if cfg.recvs.iter().any(|r| r.node_name == recv.node_name) {
+        summary.skipped += 1;
+        return;
+    }
+    cfg.recvs.push(recv.clone());
+    summary.recvs += 1;
+}
+
+/// Adds the imported streams to `cfg`, skipping node names that are already configured.
+pub fn adopt(cfg: &mut AppConfig, fragments: &[ImportedFragment]) -> AdoptSummary {
+    let mut summary = AdoptSummary::default();
+    for fragment in fragments {
+        for send in &fragment.sends {
+            adopt_send(cfg, send, &mut summary);
+        }
+        for recv in &fragment.recvs {
+            adopt_recv(cfg, recv, &mut summary);
+        }
+    }
+    summary
+}
+
+/// Adds the streams of running modules to `cfg`, like [`adopt`].
+pub fn adopt_modules(cfg: &mut AppConfig, modules: &[UnmanagedModule]) -> AdoptSummary {
+    let mut summary = AdoptSummary::default();
+    for module in modules {
+        match &module.stream {
+            ModuleStream::Send(send) => adopt_send(cfg, send, &mut summary),
+            ModuleStream::Recv(recv) => adopt_recv(cfg, recv, &mut summary),
+        }
+    }
+    summary
+}
+
+/// Removes the original files once their streams live in the config.
+pub fn delete_originals(fragments: &[ImportedFragment], summary: &mut AdoptSummary) -> Result<()> {
+    for fragment in fragments {
+        if fragment.other_modules > 0 {
+            summary.kept.push(fragment.path.clone());
+            continue;
+        }
+        fs::remove_file(&fragment.path)
+            .with_context(|| format!("Could not delete {}", fragment.path.display()))?;
+        summary.deleted.push(fragment.path.clone());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_send_and_recv_modules() {
+        let root = parse_document(
+            r#"
+context.modules = [
+  { name = libpipewire-module-rtp-sink args = { } }
+  { name = libpipewire-module-vban-send
+    args = {
+      destination.ip = "10.0.0.9"
+      destination.port = 6981
+      sess.name = "Desk mic"
+      audio.channels = 1
+      stream.props = {
+        node.name = "vban-desk"
+        node.always-process = true
+        target.object = "alsa_input.usb"
+      }
+    }
+  }
+  { name = libpipewire-module-vban-recv
+    args = {
+      source.ip = 0.0.0.0
+      sess.latency.msec = 50
+      stream.rules = [
+        { matches = [ { sess.name = "~.*" } ]
+          actions = { create-stream = { stream.props = { node.name = "vban-in" } } }
+        }
+      ]
+    }
+  }
+]
+"#,
+        )
+        .unwrap();
+        let fragment = extract_fragment(Path::new("old.conf"), &root);
+
+        assert_eq!(fragment.other_modules, 1);
+        let send = &fragment.sends[0];
+        assert_eq!(send.destination_ip, "10.0.0.9");
+        assert_eq!(send.destination_port, 6981);
+        assert_eq!(send.sess_name, "Desk mic");
+        assert_eq!(send.audio_channels, 1);
+        assert_eq!(send.audio_rate, 48_000);
+        assert!(send.always_process);
+        assert_eq!(send.node_name, "vban-desk");
+        assert_eq!(send.target_object, "alsa_input.usb");
+
+        let recv = &fragment.recvs[0];
+        assert_eq!(recv.source_ip, "0.0.0.0");
+        assert_eq!(recv.latency_msec, 50);
+        assert_eq!(recv.stream_name, "");
+        assert_eq!(recv.node_name, "vban-in");
+
+        let mut cfg = AppConfig::default();
+        let first = adopt(&mut cfg, std::slice::from_ref(&fragment));
+        let second = adopt(&mut cfg, &[fragment]);
+        assert_eq!((first.sends, first.recvs, first.skipped), (1, 1, 0));
+        assert_eq!((second.sends, second.recvs, second.skipped), (0, 0, 2));
+    }
+
+    #[test]
+    fn running_modules_already_in_the_config_are_managed() {
+        let module = |id, name: &str, args: &str| PipewireModule {
+            id,
+            name: name.into(),
+            args: args.into(),
+        };
+        let modules = [
+            module(
+                7,
+                VBAN_SEND_MODULE,
+                "{ destination.ip = 10.0.0.2 stream.props = { node.name = ours } }",
+            ),
+            module(
+                8,
+                VBAN_SEND_MODULE,
+                r#"{ "destination.ip": "10.0.0.3", "sess.name": "Theirs" }"#,
+            ),
+            module(9, VBAN_RECV_MODULE, ""),
+        ];
+        let mut cfg = AppConfig::default();
+        cfg.sends.push(VbanSend {
+            node_name: "ours".into(),
+            ..VbanSend::default()
+        });
+
+        let unmanaged = unmanaged_modules(&cfg, &modules).unwrap();
+        assert_eq!(unmanaged.len(), 2);
+        assert_eq!(unmanaged[0].module_id, 8);
+        assert!(matches!(
+            &unmanaged[0].stream,
+            ModuleStream::Send(send) if send.destination_ip == "10.0.0.3" && send.sess_name == "Theirs"
+        ));
+        assert!(matches!(unmanaged[1].stream, ModuleStream::Recv(_)));
+
+        let summary = adopt_modules(&mut cfg, &unmanaged);
+        assert_eq!((summary.sends, summary.recvs), (1, 1));
+        assert!(unmanaged_modules(&cfg, &modules).unwrap().is_empty());
+    }
+
+    #[test]
+    fn scan_reports_a_bad_file_and_keeps_the_others() {
+        let dir = std::env::temp_dir().join(format!("rustban-import-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let send = "context.modules = [ { name = libpipewire-module-vban-send args = { } } ]";
+        fs::write(dir.join("10-good.conf"), send).unwrap();
+        fs::write(dir.join("20-broken.conf"), "context.modules = [ { name = ").unwrap();
+        fs::write(dir.join("30-other.conf"), "context.properties = { }").unwrap();
+        fs::write(dir.join("99-rustban-send-x.conf"), send).unwrap();
+
+        let scan = scan_conf_dir(&dir, &["99-rustban-send-x.conf".to_string()]).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(scan.fragments.len(), 1);
+        assert!(scan.fragments[0].path.ends_with("10-good.conf"));
+        assert_eq!(scan.errors.len(), 1);
+        assert!(
+            scan.errors[0].contains("20-broken.conf"),
+            "{}",
+            scan.errors[0]
+        );
+    }
+}
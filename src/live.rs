@@ -0,0 +1,341 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    os::unix::process::CommandExt,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    thread,
+    time::Duration,
+};
+use uuid::Uuid;
+
+use crate::{
+    import,
+    pipewire_conf::render_live_host,
+    system::{self, ApplyPlan, FragmentChange, FragmentChangeKind, PipewireModule},
+};
+
+/// How long (`HOST_EXIT_POLLS * HOST_EXIT_POLL`) a stopped host of ours is waited for.
+const HOST_EXIT_POLLS: u32 = 20;
+const HOST_EXIT_POLL: Duration = Duration::from_millis(50);
+
+/// How long hot-loaded send nodes get to show up before auto-linking.
+const LIVE_NODE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A stream hot-loaded by a `pipewire -c` host process, keyed by its config entry id.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LiveModule {
+    pub stream_id: Uuid,
+    pub pid: u32,
+    pub node_name: String,
+    pub host_config: PathBuf,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LiveApplySummary {
+    pub loaded: usize,
+    pub unloaded: usize,
+    pub issues: Vec<String>,
+}
+
+fn state_path() -> Result<PathBuf> {
+    Ok(system::config_dir()?.join("live-modules.json"))
+}
+
+fn host_dir() -> Result<PathBuf> {
+    Ok(system::config_dir()?.join("live"))
+}
+
+/// Whether the host process still runs; also guards against a recycled pid.
+fn is_running(module: &LiveModule) -> bool {
+    fs::read(format!("/proc/{}/cmdline", module.pid)).is_ok_and(|cmdline| {
+        let host_config = module.host_config.to_string_lossy();
+        cmdline
+            .split(|byte| *byte == 0)
+            .any(|arg| arg == host_config.as_bytes())
+    })
+}
+
+/// Hot-loaded streams whose host process is still running.
+pub fn load_live_modules() -> Result<Vec<LiveModule>> {
+    let path = state_path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let raw = fs::read_to_string(&path)?;
+    let modules: Vec<LiveModule> = serde_json::from_str(&raw)
+        .with_context(|| format!("Could not parse {}", path.display()))?;
+    Ok(modules.into_iter().filter(is_running).collect())
+}
+
+fn save_live_modules(modules: &[LiveModule]) -> Result<()> {
+    let path = state_path()?;
+    fs::create_dir_all(path.parent().context("State file has no parent")?)?;
+    fs::write(&path, serde_json::to_string_pretty(modules)?)?;
+    Ok(())
+}
+
+/// `99-rustban-send-<id>.conf` -> `<id>`.
+fn stream_id(file_name: &str) -> Option<Uuid> {
+    let id = file_name
+        .strip_prefix("99-rustban-send-")
+        .or_else(|| file_name.strip_prefix("99-rustban-recv-"))?
+        .strip_suffix(".conf")?;
+    Uuid::parse_str(id).ok()
+}
+
+fn fragment_node_name(path: &Path) -> Result<Option<String>> {
+    let fragment = import::import_file(path)?;
+    Ok(fragment
+        .sends
+        .first()
+        .map(|send| send.node_name.clone())
+        .or_else(|| fragment.recvs.first().map(|recv| recv.node_name.clone())))
+}
+
+fn stop_host(module: &LiveModule) -> Result<()> {
+    let _ = fs::remove_file(&module.host_config);
+    if !is_running(module) {
+        return Ok(());
+    }
+    let pid = libc::pid_t::try_from(module.pid).context("Host pid out of range")?;
+    // SAFETY: plain syscalls on a pid that `is_running` just matched to this host.
+    if unsafe { libc::kill(pid, libc::SIGTERM) } != 0 {
+        return Err(std::io::Error::last_os_error())
+            .with_context(|| format!("Could not stop the host of `{}`", module.node_name));
+    }
+    // Reaps the host when this process started it; `waitpid` fails at once for other pids.
+    for _ in 0..HOST_EXIT_POLLS {
+        if unsafe { libc::waitpid(pid, std::ptr::null_mut(), libc::WNOHANG) } != 0 {
+            break;
+        }
+        thread::sleep(HOST_EXIT_POLL);
+    }
+    Ok(())
+}
+
+/// Starts a `pipewire -c` for the fragment and returns its pid. The host gets its own
+/// process group, so Ctrl-C in our terminal does not stop it, and it outlives us.
+fn spawn_host(host_config: &Path) -> Result<u32> {
+    let child = Command::new("pipewire")
+        .arg("-c")
+        .arg(host_config)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .process_group(0)
+        .spawn()
+        .with_context(|| format!("Could not start `pipewire -c {}`", host_config.display()))?;
+    Ok(child.id())
+}
+
+/// Stops every hot-loaded host, e.g. before restarting PipeWire loads the fragments itself.
+pub fn stop_all() -> Result<()> {
+    for module in load_live_modules()? {
+        stop_host(&module)?;
+    }
+    save_live_modules(&[])
+}
+
+/// Streams whose fragment `plan` creates, modifies or deletes; other files are skipped.
+fn changed_streams(plan: &ApplyPlan) -> Vec<(Uuid, &FragmentChange)> {
+    plan.changes
+        .iter()
+        .filter(|change| change.kind != FragmentChangeKind::Unchanged)
+        .filter_map(|change| Some((stream_id(&change.file_name)?, change)))
+        .collect()
+}
+
+/// Ids of the daemon's vban modules that create `node_name`.
+fn daemon_modules_of(modules: &[PipewireModule], node_name: &str) -> Vec<u32> {
+    modules
+        .iter()
+        .filter(|module| {
+            import::module_stream(module)
+                .ok()
+                .flatten()
+                .is_some_and(|stream| stream.node_name() == node_name)
+        })
+        .map(|module| module.id)
+        .collect()
+}
+
+/// Writes a host config for the written fragment of `change` and starts it.
+fn start_host(
+    plan: &ApplyPlan,
+    id: Uuid,
+    change: &FragmentChange,
+    fragment: &str,
+    host_dir: &Path,
+) -> Result<LiveModule> {
+    let node_name = fragment_node_name(&plan.dir.join(&change.file_name))?
+        .context("Fragment has no vban module")?;
+    // A new file per load, so stopping the instance this one replaces cannot remove it.
+    let host_config = host_dir.join(format!("{}-{}", Uuid::new_v4().simple(), change.file_name));
+    fs::write(&host_config, render_live_host(fragment)?)?;
+    match spawn_host(&host_config) {
+        Ok(pid) => Ok(LiveModule {
+            stream_id: id,
+            pid,
+            node_name,
+            host_config,
+        }),
+        Err(e) => {
+            let _ = fs::remove_file(&host_config);
+            Err(e)
+        }
+    }
+}
+
+/// Writes the fragments of `plan`, then swaps only the affected vban modules in the running
+/// PipeWire: new instances run in a `pipewire -c` host until the next restart loads the
+/// fragments instead, and only once one started is the old instance destroyed (daemon
+/// modules) or stopped (earlier hot loads).
+pub fn hot_apply(plan: &ApplyPlan) -> Result<LiveApplySummary> {
+    let mut summary = LiveApplySummary::default();
+    let changed = changed_streams(plan);
+
+    // Everything that can fail is read before the fragments are replaced.
+    let mut old_nodes = Vec::new();
+    for (id, change) in &changed {
+        let path = plan.dir.join(&change.file_name);
+        if path.is_file() {
+            if let Some(node_name) = fragment_node_name(&path)? {
+                old_nodes.push((*id, node_name));
+            }
+        }
+    }
+    let daemon_modules = system::list_vban_modules()?;
+    let mut live = load_live_modules()?;
+    let host_dir = host_dir()?;
+    fs::create_dir_all(&host_dir)?;
+
+    plan.apply(false)?;
+
+    let mut expected_nodes = Vec::new();
+    for (id, change) in changed {
+        let started = match change.contents() {
+            Some(fragment) => match start_host(plan, id, change, fragment, &host_dir) {
+                Ok(module) => Some(module),
+                Err(e) => {
+                    // The old instance, if any, keeps running.
+                    summary.issues.push(format!("{}: {e:#}", change.file_name));
+                    continue;
+                }
+            },
+            None => None,
+        };
+
+        if let Some(index) = live.iter().position(|module| module.stream_id == id) {
+            let module = live.remove(index);
+            match stop_host(&module) {
+                Ok(()) => summary.unloaded += 1,
+                Err(e) => summary.issues.push(format!("{e:#}")),
+            }
+        } else if let Some((_, node_name)) = old_nodes.iter().find(|(old, _)| *old == id) {
+            for module_id in daemon_modules_of(&daemon_modules, node_name) {
+                match system::destroy_pipewire_object(module_id) {
+                    Ok(()) => summary.unloaded += 1,
+                    Err(e) => summary.issues.push(format!("{e:#}")),
+                }
+            }
+        }
+
+        if let Some(module) = started {
+            if change.file_name.starts_with("99-rustban-send-") && !module.node_name.is_empty() {
+                expected_nodes.push(module.node_name.clone());
+            }
+            live.push(module);
+            summary.loaded += 1;
+        }
+    }
+
+    save_live_modules(&live)?;
+    if let Err(e) = system::wait_for_pipewire(&expected_nodes, LIVE_NODE_TIMEOUT) {
+        summary.issues.push(format!("{e:#}"));
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{AppConfig, VbanRecv, VbanSend};
+
+    #[test]
+    fn stream_id_comes_from_the_fragment_name() {
+        let id = Uuid::new_v4();
+        let send = crate::pipewire_conf::filename_send(&id.simple().to_string());
+        let recv = crate::pipewire_conf::filename_recv(&id.simple().to_string());
+
+        assert_eq!(stream_id(&send), Some(id));
+        assert_eq!(stream_id(&recv), Some(id));
+        assert_eq!(stream_id("50-hand-written.conf"), None);
+        assert_eq!(stream_id("99-rustban-send-stale.conf"), None);
+    }
+
+    #[test]
+    fn only_changed_rustban_fragments_are_swapped() {
+        let dir = std::env::temp_dir().join(format!("rustban-live-{}", Uuid::new_v4()));
+        let mut cfg = AppConfig::default();
+        cfg.sends.push(VbanSend::default());
+        cfg.sends.push(VbanSend::default());
+        system::plan_fragments_in(&dir, &cfg)
+            .unwrap()
+            .apply(false)
+            .unwrap();
+
+        let stale = Uuid::new_v4();
+        let stale_file = crate::pipewire_conf::filename_send(&stale.simple().to_string());
+        fs::write(dir.join(&stale_file), "").unwrap();
+        fs::write(dir.join("99-rustban-send-stale.conf"), "").unwrap();
+        cfg.sends[1].destination_port = 6981;
+        cfg.recvs.push(VbanRecv::default());
+
+        let plan = system::plan_fragments_in(&dir, &cfg).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        let changed: Vec<_> = changed_streams(&plan)
+            .into_iter()
+            .map(|(id, change)| (id, change.kind))
+            .collect();
+        assert_eq!(
+            changed,
+            [
+                (cfg.sends[1].id, FragmentChangeKind::Modify),
+                (cfg.recvs[0].id, FragmentChangeKind::Create),
+                (stale, FragmentChangeKind::Delete),
+            ]
+        );
+    }
+
+    #[test]
+    fn old_daemon_modules_are_matched_by_node_name() {
+        let module = |id, name: &str, args: &str| PipewireModule {
+            id,
+            name: name.into(),
+            args: args.into(),
+        };
+        let modules = [
+            module(
+                3,
+                "libpipewire-module-vban-send",
+                "{ stream.props = { node.name = vban-mic } }",
+            ),
+            module(
+                4,
+                "libpipewire-module-vban-send",
+                "{ stream.props = { node.name = vban-other } }",
+            ),
+            module(
+                5,
+                "libpipewire-module-vban-recv",
+                r#"{ stream.rules = [ { actions = { create-stream = { stream.props = { node.name = "vban-mic" } } } } ] }"#,
+            ),
+            module(6, "libpipewire-module-vban-send", "{ broken"),
+        ];
+
+        assert_eq!(daemon_modules_of(&modules, "vban-mic"), [3, 5]);
+        assert!(daemon_modules_of(&modules, "vban-gone").is_empty());
+    }
+}
@@ -1,20 +1,76 @@
+mod autolink;
+mod cli;
+mod diff;
+mod discovery;
+mod generator;
+mod import;
+mod live;
 mod model;
+mod monitor;
 mod pipewire_conf;
+mod player;
+mod recorder;
+mod relay;
+mod runtime;
+mod service;
+mod status;
+mod stream_out;
 mod system;
+mod text_remote;
+mod vban;
+mod wav;
 
-use crate::model::{AppConfig, VbanRecv, VbanSend};
+use crate::model::{
+    AppConfig, ChannelMap, ChannelRoute, RelayDestination, TextMacro, TextTarget, VbanRecv,
+    VbanRelay, VbanSend,
+};
 use anyhow::Result;
+use clap::Parser;
 use eframe::egui;
 use eframe::egui::{Color32, RichText, Stroke};
-use std::sync::Arc;
+use std::{
+    sync::{mpsc, Arc},
+    thread,
+    time::{Duration, Instant},
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Tab {
     Sends,
     Recvs,
+    Remote,
+    Relays,
     Settings,
 }
 
+/// What happens after the previewed fragments are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ApplyMode {
+    Fragments,
+    Restart,
+    /// Swap only the changed modules in the running PipeWire.
+    Live,
+}
+
+impl ApplyMode {
+    fn title(self) -> &'static str {
+        match self {
+            Self::Fragments => "Apply fragments",
+            Self::Restart => "Apply fragments + restart",
+            Self::Live => "Apply live",
+        }
+    }
+}
+
+/// Messages from the background apply job.
+enum ApplyEvent {
+    Progress(String),
+    Done(Result<(String, system::AutoLinkSummary)>),
+}
+
+/// How often the per-stream node/fragment status is refreshed.
+const RUNTIME_REFRESH: Duration = Duration::from_secs(3);
+
 const APP_ID: &str = "com.rustban.app";
 const APP_ICON_BYTES: &[u8] = include_bytes!("../app_icon.png");
 
@@ -24,6 +80,42 @@ struct App {
     status: String,
     theme_applied: bool,
     microphone_sources: Vec<system::AudioSourceDevice>,
+    playback_sinks: Vec<system::AudioSinkDevice>,
+    scan_port: u16,
+    scan_seconds: u64,
+    stream_scan: Option<mpsc::Receiver<Result<Vec<discovery::DiscoveredStream>>>>,
+    discovered_streams: Vec<discovery::DiscoveredStream>,
+    ping_responder: Option<service::PingResponder>,
+    host_discovery: Option<mpsc::Receiver<Result<Vec<discovery::DiscoveredHost>>>>,
+    discovered_hosts: Vec<discovery::DiscoveredHost>,
+    discovery_addresses: String,
+    remote_target: uuid::Uuid,
+    remote_command: String,
+    tone_settings: generator::ToneSettings,
+    tone: Option<generator::ToneGenerator>,
+    monitor_port: u16,
+    stream_monitor: Option<monitor::StreamMonitor>,
+    record_settings: recorder::RecordSettings,
+    recorder: Option<recorder::Recorder>,
+    play_send: uuid::Uuid,
+    play_path: String,
+    play_loop: bool,
+    player: Option<player::FilePlayer>,
+    relays: Vec<relay::Relay>,
+    import_path: String,
+    /// Files of the last drop-in scan that could not be parsed.
+    import_errors: Vec<String>,
+    import_delete_originals: bool,
+    imported: Vec<import::ImportedFragment>,
+    unmanaged: Vec<import::UnmanagedModule>,
+    autolink_watcher: Option<autolink::AutoLinkWatcher>,
+    autolink_passes_seen: u32,
+    runtime: runtime::RuntimeSnapshot,
+    runtime_job: Option<mpsc::Receiver<Result<runtime::RuntimeSnapshot>>>,
+    runtime_refreshed_at: Option<Instant>,
+    /// Fragment changes waiting for confirmation in the preview dialog.
+    pending_apply: Option<(system::ApplyPlan, ApplyMode)>,
+    apply_job: Option<mpsc::Receiver<ApplyEvent>>,
 }
 
 impl App {
@@ -39,10 +131,54 @@ impl App {
             status,
             theme_applied: false,
             microphone_sources: Vec::new(),
+            playback_sinks: Vec::new(),
+            scan_port: 6980,
+            scan_seconds: 3,
+            stream_scan: None,
+            discovered_streams: Vec::new(),
+            ping_responder: None,
+            host_discovery: None,
+            discovered_hosts: Vec::new(),
+            discovery_addresses: String::new(),
+            remote_target: uuid::Uuid::nil(),
+            remote_command: String::new(),
+            tone_settings: generator::ToneSettings::default(),
+            tone: None,
+            monitor_port: 6981,
+            stream_monitor: None,
+            record_settings: recorder::RecordSettings::default(),
+            recorder: None,
+            play_send: uuid::Uuid::nil(),
+            play_path: String::new(),
+            play_loop: false,
+            player: None,
+            relays: Vec::new(),
+            import_path: String::new(),
+            import_errors: Vec::new(),
+            import_delete_originals: true,
+            imported: Vec::new(),
+            unmanaged: Vec::new(),
+            autolink_watcher: None,
+            autolink_passes_seen: 0,
+            runtime: runtime::RuntimeSnapshot::default(),
+            runtime_job: None,
+            runtime_refreshed_at: None,
+            pending_apply: None,
+            apply_job: None,
         };
+        app.discovery_addresses = app.cfg.host_discovery.addresses.join(", ");
+        app.sync_ping_responder();
+        app.sync_relays();
 
-        if let Err(e) = app.load_microphone_sources() {
-            let scan_error = format!("Microphone scan error: {e:#}");
+        let scan_errors = [
+            app.load_microphone_sources()
+                .err()
+                .map(|e| format!("Source scan error: {e:#}")),
+            app.load_playback_sinks()
+                .err()
+                .map(|e| format!("Sink scan error: {e:#}")),
+        ];
+        for scan_error in scan_errors.into_iter().flatten() {
             app.status = if app.status == "Ready." {
                 scan_error
             } else {
@@ -58,32 +194,284 @@ impl App {
             Ok(()) => "Config saved.".into(),
             Err(e) => format!("Save error: {e:#}"),
         };
+        self.sync_ping_responder();
+        self.sync_relays();
+        if self
+            .autolink_watcher
+            .as_ref()
+            .is_some_and(|watcher| !watcher.watches(&self.cfg))
+        {
+            self.set_autolink_watcher(true);
+        }
+    }
+
+    fn set_autolink_watcher(&mut self, on: bool) {
+        self.autolink_watcher = on.then(|| autolink::AutoLinkWatcher::spawn(&self.cfg));
+        self.autolink_passes_seen = 0;
+    }
+
+    fn poll_autolink_watcher(&mut self) {
+        let Some(watcher) = &mut self.autolink_watcher else {
+            return;
+        };
+        if let Some(e) = watcher.take_error() {
+            self.status = format!("Auto-link watcher error: {e:#}");
+            self.autolink_watcher = None;
+            return;
+        }
+        if watcher.passes() == self.autolink_passes_seen {
+            return;
+        }
+        self.autolink_passes_seen = watcher.passes();
+        let Some(summary) = watcher.last_summary() else {
+            return;
+        };
+        if let Some(changes) = summary.changes() {
+            self.status = format!("Auto-link watcher: {changes}.");
+        }
+        if !summary.issues.is_empty() {
+            self.status = format!("Auto-link watcher warnings: {}", summary.issues.join(" | "));
+        }
+    }
+
+    fn sync_ping_responder(&mut self) {
+        let emulation = &self.cfg.host_info_emulation;
+        if !(emulation.enabled && emulation.ping_responder) {
+            self.ping_responder = None;
+            return;
+        }
+
+        let port = emulation.ping_port;
+        let identity = service::Ping0::from_config(&self.cfg);
+        if self
+            .ping_responder
+            .as_ref()
+            .is_some_and(|responder| responder.matches(port, &identity))
+        {
+            return;
+        }
+
+        // Release the previous socket before binding the port again.
+        self.ping_responder = None;
+        match service::PingResponder::spawn(port, identity) {
+            Ok(responder) => self.ping_responder = Some(responder),
+            Err(e) => self.status = format!("Ping responder error: {e:#}"),
+        }
+    }
+
+    /// Keeps one running relay per enabled entry, restarting the ones whose settings changed.
+    fn sync_relays(&mut self) {
+        let configs: Vec<&VbanRelay> = self.cfg.relays.iter().filter(|r| r.enabled).collect();
+        // Stop changed relays first so their ports are free to bind again.
+        self.relays
+            .retain(|relay| configs.iter().any(|config| relay.matches(config)));
+
+        let mut errors = Vec::new();
+        for config in configs {
+            if self.relays.iter().any(|relay| relay.id() == config.id) {
+                continue;
+            }
+            match relay::Relay::spawn(config) {
+                Ok(relay) => self.relays.push(relay),
+                Err(e) => errors.push(format!("{}: {e:#}", config.name)),
+            }
+        }
+        if !errors.is_empty() {
+            self.status = format!("Relay error: {}", errors.join(" | "));
+        }
+    }
+
+    fn scan_imports(&mut self) {
+        match import::scan_dropin_dir() {
+            Ok(scan) => {
+                self.status = format!(
+                    "Found {} hand-written VBAN fragment(s) to import.",
+                    scan.fragments.len()
+                );
+                if !scan.errors.is_empty() {
+                    self.status.push_str(&format!(
+                        " {} file(s) could not be read.",
+                        scan.errors.len()
+                    ));
+                }
+                self.imported = scan.fragments;
+                self.import_errors = scan.errors;
+            }
+            Err(e) => self.status = format!("Import error: {e:#}"),
+        }
+    }
+
+    fn add_import_file(&mut self) {
+        let path = std::path::PathBuf::from(self.import_path.trim());
+        match import::import_file(&path) {
+            Ok(fragment) => {
+                self.status = format!("Read {}.", fragment.label());
+                self.imported.retain(|f| f.path != fragment.path);
+                self.imported.push(fragment);
+            }
+            Err(e) => self.status = format!("Import error: {e:#}"),
+        }
+    }
+
+    fn scan_unmanaged(&mut self) {
+        match import::scan_running_modules(&self.cfg) {
+            Ok(modules) => {
+                self.status = format!(
+                    "Found {} unmanaged VBAN module(s) in the running PipeWire.",
+                    modules.len()
+                );
+                self.unmanaged = modules;
+            }
+            Err(e) => self.status = format!("Import error: {e:#}"),
+        }
+    }
+
+    fn adopt_unmanaged(&mut self, i: usize) {
+        let module = self.unmanaged.remove(i);
+        let summary = import::adopt_modules(&mut self.cfg, std::slice::from_ref(&module));
+        self.status = match system::save_app_config(&self.cfg) {
+            Ok(()) if summary.skipped > 0 => {
+                format!("Module {} is already configured.", module.module_id)
+            }
+            Ok(()) => format!(
+                "Imported module {}, config saved. Unload it before applying to avoid a duplicate stream.",
+                module.module_id
+            ),
+            Err(e) => format!("Import error: {e:#}"),
+        };
     }
 
-    fn apply(&mut self, restart: bool) {
-        let result = (|| -> Result<system::AutoLinkSummary> {
-            self.save();
-            system::apply_pipewire_fragments(&self.cfg)?;
-            if restart {
-                system::restart_pipewire_user_services()?;
+    fn adopt_imports(&mut self) {
+        let fragments = std::mem::take(&mut self.imported);
+        let mut summary = import::adopt(&mut self.cfg, &fragments);
+        let result = (|| -> Result<()> {
+            system::save_app_config(&self.cfg)?;
+            if self.import_delete_originals {
+                import::delete_originals(&fragments, &mut summary)?;
             }
-            system::autolink_send_sources(&self.cfg)
+            Ok(())
         })();
 
         self.status = match result {
-            Ok(summary) => {
-                let mut status = if restart {
-                    "Fragments applied + pipewire restarted.".to_string()
-                } else {
-                    "Fragments applied.".to_string()
-                };
-
-                if summary.links_created > 0 {
+            Ok(()) => {
+                let mut status = format!(
+                    "Imported {} send(s) and {} recv(s), config saved.",
+                    summary.sends, summary.recvs
+                );
+                if summary.skipped > 0 {
+                    status.push_str(&format!(" {} already configured.", summary.skipped));
+                }
+                if !summary.deleted.is_empty() {
+                    status.push_str(&format!(
+                        " {} original file(s) deleted.",
+                        summary.deleted.len()
+                    ));
+                }
+                if !summary.kept.is_empty() {
                     status.push_str(&format!(
-                        " Auto-link: {} link(s) created.",
-                        summary.links_created
+                        " {} file(s) kept (other modules inside).",
+                        summary.kept.len()
                     ));
                 }
+                status.push_str(" Apply fragments to take over.");
+                status
+            }
+            Err(e) => format!("Import error: {e:#}"),
+        };
+    }
+
+    /// Saves and opens the preview dialog; nothing is written before it is confirmed.
+    fn apply(&mut self, mode: ApplyMode) {
+        if self.apply_job.is_some() {
+            self.status = "An apply is still running.".into();
+            return;
+        }
+        self.save();
+        match system::plan_pipewire_fragments(&self.cfg) {
+            Ok(plan) => self.pending_apply = Some((plan, mode)),
+            Err(e) => self.status = format!("Apply error: {e:#}"),
+        }
+    }
+
+    /// Runs the confirmed apply off the UI thread; restarts and auto-link waits take seconds.
+    fn confirm_apply(&mut self, ctx: &egui::Context) {
+        let Some((plan, mode)) = self.pending_apply.take() else {
+            return;
+        };
+        let (tx, rx) = mpsc::channel();
+        let cfg = self.cfg.clone();
+        let ctx = ctx.clone();
+        self.status = format!("{}...", mode.title());
+        self.apply_job = Some(rx);
+        thread::spawn(move || {
+            let result = Self::run_apply(&plan, mode, &cfg, |progress| {
+                let _ = tx.send(ApplyEvent::Progress(progress.to_string()));
+                ctx.request_repaint();
+            });
+            let _ = tx.send(ApplyEvent::Done(result));
+            ctx.request_repaint();
+        });
+    }
+
+    fn run_apply(
+        plan: &system::ApplyPlan,
+        mode: ApplyMode,
+        cfg: &AppConfig,
+        progress: impl FnMut(&str),
+    ) -> Result<(String, system::AutoLinkSummary)> {
+        let status = match mode {
+            ApplyMode::Fragments => {
+                plan.apply(false)?;
+                "Fragments applied.".to_string()
+            }
+            ApplyMode::Restart => {
+                plan.apply(true)?;
+                "Fragments applied + pipewire restarted.".to_string()
+            }
+            ApplyMode::Live => {
+                let summary = live::hot_apply(plan)?;
+                let mut status = format!(
+                    "Fragments applied live: {} module(s) unloaded, {} loaded.",
+                    summary.unloaded, summary.loaded
+                );
+                if !summary.issues.is_empty() {
+                    status.push_str(" Live warnings: ");
+                    status.push_str(&summary.issues.join(" | "));
+                }
+                status
+            }
+        };
+        let summary = match mode {
+            ApplyMode::Fragments => system::autolink_streams(cfg)?,
+            ApplyMode::Restart | ApplyMode::Live => {
+                let timeout = Duration::from_secs(cfg.autolink.wait_timeout_secs);
+                system::autolink_streams_when_ready(cfg, timeout, progress)?
+            }
+        };
+        Ok((status, summary))
+    }
+
+    fn poll_apply(&mut self) {
+        let Some(rx) = &self.apply_job else {
+            return;
+        };
+        let result = loop {
+            match rx.try_recv() {
+                Ok(ApplyEvent::Progress(progress)) => self.status = progress,
+                Ok(ApplyEvent::Done(result)) => break result,
+                Err(mpsc::TryRecvError::Empty) => return,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    break Err(anyhow::anyhow!("apply thread stopped"))
+                }
+            }
+        };
+        self.apply_job = None;
+
+        self.status = match result {
+            Ok((mut status, summary)) => {
+                if let Some(changes) = summary.changes() {
+                    status.push_str(&format!(" Auto-link: {changes}."));
+                }
                 if !summary.issues.is_empty() {
                     status.push_str(" Auto-link warnings: ");
                     status.push_str(&summary.issues.join(" | "));
@@ -100,19 +488,386 @@ impl App {
         Ok(())
     }
 
+    fn load_playback_sinks(&mut self) -> Result<()> {
+        self.playback_sinks = system::list_playback_sinks()?;
+        Ok(())
+    }
+
+    /// Sources and sinks are scanned independently; one failing keeps the other list.
     fn refresh_microphone_sources(&mut self) {
-        match self.load_microphone_sources() {
-            Ok(()) => {
-                self.status = format!(
-                    "Detected {} microphone source(s).",
-                    self.microphone_sources.len()
-                )
-            }
+        let mut status = match self.load_microphone_sources() {
+            Ok(()) => format!(
+                "Detected {} source(s): {}.",
+                self.microphone_sources.len(),
+                Self::source_counts(&self.microphone_sources)
+            ),
             Err(e) => {
-                self.status = format!("Microphone scan error: {e:#}");
                 self.microphone_sources.clear();
+                format!("Source scan error: {e:#}")
+            }
+        };
+        match self.load_playback_sinks() {
+            Ok(()) => status.push_str(&format!(" {} sink(s).", self.playback_sinks.len())),
+            Err(e) => {
+                self.playback_sinks.clear();
+                status.push_str(&format!(" Sink scan error: {e:#}"));
+            }
+        }
+        self.status = status;
+    }
+
+    fn start_stream_scan(&mut self, ctx: &egui::Context) {
+        let (tx, rx) = mpsc::channel();
+        let port = self.scan_port;
+        let duration = Duration::from_secs(self.scan_seconds);
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let _ = tx.send(discovery::scan_streams(port, duration));
+            ctx.request_repaint();
+        });
+
+        self.stream_scan = Some(rx);
+        self.status = format!("Scanning UDP port {port} for {}s...", self.scan_seconds);
+    }
+
+    fn poll_stream_scan(&mut self) {
+        let Some(rx) = &self.stream_scan else {
+            return;
+        };
+        let result = match rx.try_recv() {
+            Ok(result) => result,
+            Err(mpsc::TryRecvError::Empty) => return,
+            Err(mpsc::TryRecvError::Disconnected) => {
+                Err(anyhow::anyhow!("scan thread stopped unexpectedly"))
+            }
+        };
+
+        self.stream_scan = None;
+        match result {
+            Ok(streams) => {
+                self.status = format!("Scan finished: {} VBAN stream(s) found.", streams.len());
+                self.discovered_streams = streams;
+            }
+            Err(e) => {
+                self.status = format!("Scan error: {e:#}");
+                self.discovered_streams.clear();
+            }
+        }
+    }
+
+    fn start_host_discovery(&mut self, ctx: &egui::Context) {
+        let (tx, rx) = mpsc::channel();
+        let settings = self.cfg.host_discovery.clone();
+        let identity = service::Ping0::from_config(&self.cfg);
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let _ = tx.send(discovery::discover_hosts(
+                &settings,
+                &identity,
+                Duration::from_secs(2),
+            ));
+            ctx.request_repaint();
+        });
+
+        self.host_discovery = Some(rx);
+        self.status = "Sending VBAN PING0...".into();
+    }
+
+    fn poll_host_discovery(&mut self) {
+        let Some(rx) = &self.host_discovery else {
+            return;
+        };
+        let result = match rx.try_recv() {
+            Ok(result) => result,
+            Err(mpsc::TryRecvError::Empty) => return,
+            Err(mpsc::TryRecvError::Disconnected) => {
+                Err(anyhow::anyhow!("discovery thread stopped unexpectedly"))
+            }
+        };
+
+        self.host_discovery = None;
+        match result {
+            Ok(hosts) => {
+                self.status = format!("Discovery finished: {} VBAN host(s) replied.", hosts.len());
+                self.discovered_hosts = hosts;
+            }
+            Err(e) => {
+                self.status = format!("Discovery error: {e:#}");
+                self.discovered_hosts.clear();
+            }
+        }
+    }
+
+    fn poll_tone(&mut self, ctx: &egui::Context) {
+        let Some(tone) = &mut self.tone else {
+            return;
+        };
+        if let Some(e) = tone.take_error() {
+            self.status = format!("Test tone error: {e:#}");
+            self.tone = None;
+            return;
+        }
+        ctx.request_repaint_after(Duration::from_millis(250));
+    }
+
+    fn poll_stream_monitor(&mut self, ctx: &egui::Context) {
+        let Some(stream_monitor) = &mut self.stream_monitor else {
+            return;
+        };
+        if let Some(e) = stream_monitor.take_error() {
+            self.status = format!("Monitor error: {e:#}");
+            self.stream_monitor = None;
+            return;
+        }
+        ctx.request_repaint_after(Duration::from_millis(500));
+    }
+
+    fn poll_recorder(&mut self, ctx: &egui::Context) {
+        let Some(recorder) = &mut self.recorder else {
+            return;
+        };
+        match recorder.take_result() {
+            Some(result) => {
+                self.recorder = None;
+                self.status = Self::record_status(result);
+            }
+            None => ctx.request_repaint_after(Duration::from_millis(250)),
+        }
+    }
+
+    fn poll_player(&mut self, ctx: &egui::Context) {
+        let Some(player) = &mut self.player else {
+            return;
+        };
+        match player.take_result() {
+            Some(Ok(())) => {
+                self.status = format!(
+                    "Playback finished ({} packets sent).",
+                    player.packets_sent()
+                );
+                self.player = None;
+            }
+            Some(Err(e)) => {
+                self.status = format!("Playback error: {e:#}");
+                self.player = None;
+            }
+            None => ctx.request_repaint_after(Duration::from_millis(250)),
+        }
+    }
+
+    fn poll_relays(&mut self, ctx: &egui::Context) {
+        let mut errors = Vec::new();
+        self.relays.retain_mut(|relay| match relay.take_error() {
+            Some(e) => {
+                errors.push(format!("{e:#}"));
+                false
+            }
+            None => true,
+        });
+        if !errors.is_empty() {
+            self.status = format!("Relay error: {}", errors.join(" | "));
+        }
+        if self.tab == Tab::Relays && !self.relays.is_empty() {
+            ctx.request_repaint_after(Duration::from_secs(1));
+        }
+    }
+
+    /// Refreshes the node/fragment status in the background while a stream tab is shown.
+    fn poll_runtime(&mut self, ctx: &egui::Context) {
+        if let Some(rx) = &self.runtime_job {
+            match rx.try_recv() {
+                Ok(Ok(snapshot)) => self.runtime = snapshot,
+                Ok(Err(e)) => {
+                    self.runtime = runtime::RuntimeSnapshot {
+                        graph_error: Some(format!("{e:#}")),
+                        ..runtime::RuntimeSnapshot::default()
+                    }
+                }
+                Err(mpsc::TryRecvError::Empty) => return,
+                Err(mpsc::TryRecvError::Disconnected) => {}
+            }
+            self.runtime_job = None;
+        }
+
+        if !matches!(self.tab, Tab::Sends | Tab::Recvs) {
+            return;
+        }
+        if self
+            .runtime_refreshed_at
+            .is_none_or(|at| at.elapsed() >= RUNTIME_REFRESH)
+        {
+            let (tx, rx) = mpsc::channel();
+            let cfg = self.cfg.clone();
+            let ctx = ctx.clone();
+            thread::spawn(move || {
+                let _ = tx.send(runtime::collect_runtime(&cfg));
+                ctx.request_repaint();
+            });
+            self.runtime_job = Some(rx);
+            self.runtime_refreshed_at = Some(Instant::now());
+        }
+        ctx.request_repaint_after(RUNTIME_REFRESH);
+    }
+
+    fn ui_stream_runtime(
+        ui: &mut egui::Ui,
+        stream: Option<&runtime::StreamRuntime>,
+        graph_error: Option<&str>,
+        missing_label: &str,
+    ) {
+        let Some(stream) = stream else {
+            ui.label(RichText::new("Checking PipeWire...").color(Color32::from_rgb(150, 160, 176)));
+            return;
+        };
+        ui.horizontal_wrapped(|ui| {
+            match (&stream.node, graph_error) {
+                (Some(node), _) => {
+                    let color = match node.state.as_str() {
+                        "running" => Color32::from_rgb(61, 176, 136),
+                        "idle" => Color32::from_rgb(120, 170, 210),
+                        "error" => Color32::from_rgb(211, 84, 84),
+                        _ => Color32::from_rgb(205, 165, 103),
+                    };
+                    ui.label(
+                        RichText::new(format!("● {} (node {})", node.state, node.id))
+                            .strong()
+                            .color(color),
+                    );
+                    if let Some(error) = &node.error {
+                        ui.label(RichText::new(error).color(Color32::from_rgb(211, 84, 84)));
+                    }
+                    ui.separator();
+                    let links = if node.linked_nodes.is_empty() {
+                        "no links".to_string()
+                    } else {
+                        format!("linked: {}", node.linked_nodes.join(", "))
+                    };
+                    ui.label(RichText::new(links).color(Color32::from_rgb(175, 186, 204)));
+                }
+                (None, Some(error)) => {
+                    ui.label(
+                        RichText::new("● graph unavailable")
+                            .strong()
+                            .color(Color32::from_rgb(150, 160, 176)),
+                    )
+                    .on_hover_text(error);
+                }
+                (None, None) => {
+                    ui.label(
+                        RichText::new(format!("● {missing_label}"))
+                            .strong()
+                            .color(Color32::from_rgb(205, 165, 103)),
+                    );
+                }
+            }
+            ui.separator();
+            let color = if stream.fragment.is_pending() {
+                Color32::from_rgb(226, 170, 84)
+            } else {
+                Color32::from_rgb(175, 186, 204)
+            };
+            ui.label(RichText::new(stream.fragment.label()).color(color));
+        });
+    }
+
+    fn record_status(result: Result<recorder::RecordSummary>) -> String {
+        match result {
+            Ok(summary) => format!(
+                "Recording saved to {} ({}, {:.1}s).",
+                summary.path.display(),
+                summary.format,
+                summary.seconds
+            ),
+            Err(e) => format!("Recording error: {e:#}"),
+        }
+    }
+
+    fn start_stream_monitor(&mut self, i: usize) {
+        let recv = self.cfg.recvs[i].clone();
+        // Release the previous socket first, the user may reuse the same port.
+        self.stream_monitor = None;
+        match monitor::StreamMonitor::spawn(self.monitor_port, Some(recv)) {
+            Ok(stream_monitor) => {
+                self.status = format!(
+                    "Monitoring UDP port {} for recv {}.",
+                    self.monitor_port,
+                    i + 1
+                );
+                self.stream_monitor = Some(stream_monitor);
+            }
+            Err(e) => self.status = format!("Monitor error: {e:#}"),
+        }
+    }
+
+    fn ui_stream_monitor(
+        ui: &mut egui::Ui,
+        recv: &VbanRecv,
+        stream_monitor: Option<&monitor::StreamMonitor>,
+        monitor_port: &mut u16,
+    ) -> Option<bool> {
+        let mut action = None;
+        let active = stream_monitor.filter(|m| m.recv_id() == Some(recv.id));
+        ui.horizontal_wrapped(|ui| {
+            ui.add_sized(
+                egui::vec2(170.0, 22.0),
+                egui::Label::new(
+                    RichText::new("Monitor (mirror port)").color(Color32::from_rgb(202, 216, 236)),
+                ),
+            );
+            match active {
+                Some(stream_monitor) => {
+                    ui.label(format!("Listening on UDP {}", stream_monitor.port()));
+                    if Self::action_button(ui, "Stop", Color32::from_rgb(187, 72, 72)) {
+                        action = Some(false);
+                    }
+                }
+                None => {
+                    ui.add(
+                        egui::DragValue::new(monitor_port)
+                            .clamp_range(1..=u16::MAX)
+                            .speed(1.0),
+                    );
+                    if Self::action_button(ui, "Monitor", Color32::from_rgb(42, 122, 104)) {
+                        action = Some(true);
+                    }
+                }
+            }
+        });
+
+        let Some(stream_monitor) = active else {
+            return action;
+        };
+        let streams = stream_monitor.snapshot();
+        if streams.is_empty() {
+            ui.label(
+                RichText::new("No VBAN packet received yet.")
+                    .color(Color32::from_rgb(175, 186, 204)),
+            );
+        }
+        for stats in streams {
+            let healthy = stats.lost == 0 && stats.mismatches.is_empty();
+            ui.label(
+                RichText::new(format!(
+                    "{}  \"{}\"  {}  {}",
+                    stats.stream.source_ip,
+                    stats.stream.stream_name,
+                    stats.stream.format_label(),
+                    stats.summary()
+                ))
+                .color(if healthy {
+                    Color32::from_rgb(206, 220, 241)
+                } else {
+                    Color32::from_rgb(240, 190, 110)
+                }),
+            );
+            for mismatch in &stats.mismatches {
+                ui.label(
+                    RichText::new(format!("  ! {mismatch}"))
+                        .color(Color32::from_rgb(255, 140, 140)),
+                );
             }
         }
+        action
     }
 
     fn ui_header(&mut self, ui: &mut egui::Ui) {
@@ -189,6 +944,22 @@ impl App {
                     ) {
                         self.tab = Tab::Recvs;
                     }
+                    if Self::tab_button(
+                        ui,
+                        self.tab == Tab::Remote,
+                        "VBAN-TEXT",
+                        Color32::from_rgb(142, 98, 204),
+                    ) {
+                        self.tab = Tab::Remote;
+                    }
+                    if Self::tab_button(
+                        ui,
+                        self.tab == Tab::Relays,
+                        "Relays",
+                        Color32::from_rgb(206, 112, 64),
+                    ) {
+                        self.tab = Tab::Relays;
+                    }
                     if Self::tab_button(
                         ui,
                         self.tab == Tab::Settings,
@@ -203,17 +974,107 @@ impl App {
                     if Self::action_button(ui, "Save", Color32::from_rgb(68, 150, 110)) {
                         self.save();
                     }
-                    if Self::action_button(ui, "Refresh mics", Color32::from_rgb(69, 94, 155)) {
+                    if Self::action_button(ui, "Refresh sources", Color32::from_rgb(69, 94, 155)) {
                         self.refresh_microphone_sources();
                     }
                     if Self::action_button(ui, "Apply fragments", Color32::from_rgb(57, 111, 188)) {
-                        self.apply(false);
+                        self.apply(ApplyMode::Fragments);
+                    }
+                    if Self::action_button(ui, "Apply live", Color32::from_rgb(68, 150, 110)) {
+                        self.apply(ApplyMode::Live);
                     }
                     if Self::action_button(ui, "Apply + restart", Color32::from_rgb(179, 114, 48)) {
-                        self.apply(true);
+                        self.apply(ApplyMode::Restart);
+                    }
+                });
+            });
+    }
+
+    fn ui_apply_preview(&mut self, ctx: &egui::Context) {
+        let Some((plan, mode)) = &self.pending_apply else {
+            return;
+        };
+        let mut confirm = false;
+        let mut cancel = false;
+
+        egui::Window::new(mode.title())
+            .collapsible(false)
+            .resizable(true)
+            .default_width(640.0)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label(
+                    RichText::new(format!("{}: {}", plan.dir.display(), plan.summary()))
+                        .color(Color32::from_rgb(210, 218, 229)),
+                );
+                ui.add_space(4.0);
+                egui::ScrollArea::vertical()
+                    .max_height(420.0)
+                    .show(ui, |ui| {
+                        for change in &plan.changes {
+                            let color = match change.kind {
+                                system::FragmentChangeKind::Create => {
+                                    Color32::from_rgb(61, 176, 136)
+                                }
+                                system::FragmentChangeKind::Modify => {
+                                    Color32::from_rgb(226, 170, 84)
+                                }
+                                system::FragmentChangeKind::Unchanged => {
+                                    Color32::from_rgb(150, 160, 176)
+                                }
+                                system::FragmentChangeKind::Delete => {
+                                    Color32::from_rgb(211, 84, 84)
+                                }
+                            };
+                            ui.horizontal(|ui| {
+                                ui.label(RichText::new(change.kind.label()).strong().color(color));
+                                ui.label(
+                                    RichText::new(&change.file_name)
+                                        .monospace()
+                                        .color(Color32::from_rgb(206, 220, 241)),
+                                );
+                            });
+                            if !change.diff.is_empty() {
+                                egui::CollapsingHeader::new("diff")
+                                    .id_source(&change.file_name)
+                                    .default_open(change.kind == system::FragmentChangeKind::Modify)
+                                    .show(ui, |ui| {
+                                        for line in change.diff.lines() {
+                                            let color = if line.starts_with('+') {
+                                                Color32::from_rgb(120, 210, 150)
+                                            } else if line.starts_with('-') {
+                                                Color32::from_rgb(226, 120, 120)
+                                            } else {
+                                                Color32::from_rgb(175, 186, 204)
+                                            };
+                                            ui.label(RichText::new(line).monospace().color(color));
+                                        }
+                                    });
+                            }
+                        }
+                    });
+                ui.add_space(6.0);
+                ui.horizontal(|ui| {
+                    let label = if plan.has_changes() {
+                        "Write fragments"
+                    } else {
+                        "Nothing to write, continue"
+                    };
+                    if Self::action_button(ui, label, Color32::from_rgb(57, 111, 188)) {
+                        confirm = true;
+                    }
+                    if Self::action_button(ui, "Cancel", Color32::from_rgb(92, 96, 108)) {
+                        cancel = true;
                     }
                 });
             });
+
+        if confirm {
+            self.confirm_apply(ctx);
+        } else if cancel {
+            self.pending_apply = None;
+            self.status = "Apply cancelled, nothing written.".to_string();
+        }
     }
 
     fn ui_card_frame(fill: Color32, border: Color32) -> egui::Frame {
@@ -237,6 +1098,91 @@ impl App {
         });
     }
 
+    /// Auto/manual switch and, in manual mode, a source x send grid of routes.
+    fn ui_channel_map(ui: &mut egui::Ui, i: usize, send: &mut VbanSend) {
+        let label_color = Color32::from_rgb(202, 216, 236);
+        ui.horizontal(|ui| {
+            ui.add_sized(
+                egui::vec2(170.0, 22.0),
+                egui::Label::new(RichText::new("Channel map").color(label_color)),
+            );
+            let mut manual = send.channel_map.is_some();
+            if ui
+                .checkbox(&mut manual, "Manual (auto matches ports by channel)")
+                .changed()
+            {
+                send.channel_map =
+                    manual.then(|| ChannelMap::straight(send.audio_channels, send.audio_channels));
+            }
+        });
+
+        let send_channels = send.audio_channels;
+        let Some(map) = &mut send.channel_map else {
+            return;
+        };
+        ui.horizontal(|ui| {
+            ui.add_space(174.0);
+            ui.label(RichText::new("Source channels").color(label_color));
+            ui.add(
+                egui::DragValue::new(&mut map.source_channels)
+                    .clamp_range(1..=64)
+                    .speed(1.0),
+            );
+        });
+        let source_channels = map.source_channels;
+        // Routes outside the grid are kept (shrinking a count for a moment must not lose
+        // them) until removed on purpose.
+        let in_grid =
+            |route: &ChannelRoute| route.source <= source_channels && route.send <= send_channels;
+        let hidden: Vec<String> = map
+            .routes
+            .iter()
+            .filter(|route| !in_grid(route))
+            .map(|route| format!("{}:{}", route.source, route.send))
+            .collect();
+        if !hidden.is_empty() {
+            ui.horizontal(|ui| {
+                ui.add_space(174.0);
+                ui.label(
+                    RichText::new(format!("Outside the grid: {}", hidden.join(", ")))
+                        .color(Color32::from_rgb(205, 165, 103)),
+                );
+                if ui.small_button("Remove").clicked() {
+                    map.routes.retain(in_grid);
+                }
+            });
+        }
+
+        ui.horizontal(|ui| {
+            ui.add_space(174.0);
+            egui::Grid::new(format!("send-channel-map-{i}"))
+                .spacing(egui::vec2(6.0, 2.0))
+                .show(ui, |ui| {
+                    ui.label(RichText::new("src \\ send").color(label_color));
+                    for send_channel in 1..=send_channels {
+                        ui.label(RichText::new(send_channel.to_string()).color(label_color));
+                    }
+                    ui.end_row();
+                    for source in 1..=source_channels {
+                        ui.label(RichText::new(source.to_string()).color(label_color));
+                        for send_channel in 1..=send_channels {
+                            let mut on = map.contains(source, send_channel);
+                            if ui.checkbox(&mut on, "").changed() {
+                                map.set(source, send_channel, on);
+                            }
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+        if map.routes.is_empty() {
+            ui.label(
+                RichText::new("No route: the send will not be linked.")
+                    .color(Color32::from_rgb(205, 165, 103)),
+            );
+        }
+    }
+
     fn microphone_option_label(source: &system::AudioSourceDevice) -> String {
         if source.description == source.node_name {
             source.node_name.clone()
@@ -245,6 +1191,22 @@ impl App {
         }
     }
 
+    /// E.g. `2 inputs, 1 sink monitors, 0 applications`.
+    fn source_counts(sources: &[system::AudioSourceDevice]) -> String {
+        [
+            system::SourceKind::Input,
+            system::SourceKind::Monitor,
+            system::SourceKind::Application,
+        ]
+        .into_iter()
+        .map(|kind| {
+            let count = sources.iter().filter(|source| source.kind == kind).count();
+            format!("{count} {}", kind.label().to_lowercase())
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+    }
+
     fn selected_microphone_label(
         target_object: &str,
         sources: &[system::AudioSourceDevice],
@@ -262,7 +1224,11 @@ impl App {
     fn ui_sends(&mut self, ui: &mut egui::Ui) {
         if Self::action_button(ui, "+ Add send", Color32::from_rgb(43, 133, 219)) {
             let mut send = VbanSend::default();
-            if let Some(source) = self.microphone_sources.first() {
+            if let Some(source) = self
+                .microphone_sources
+                .iter()
+                .find(|source| source.kind == system::SourceKind::Input)
+            {
                 send.target_object = source.node_name.clone();
             }
             self.cfg.sends.push(send);
@@ -271,18 +1237,57 @@ impl App {
         ui.horizontal(|ui| {
             ui.label(
                 RichText::new(format!(
-                    "{} microphone source(s) detected.",
-                    self.microphone_sources.len()
+                    "{} source(s) detected: {}.",
+                    self.microphone_sources.len(),
+                    Self::source_counts(&self.microphone_sources)
                 ))
                 .color(Color32::from_rgb(175, 186, 204)),
             );
             if self.microphone_sources.is_empty() {
                 ui.label(
-                    RichText::new("Click `Refresh mics` in toolbar.")
+                    RichText::new("Click `Refresh sources` in toolbar.")
                         .color(Color32::from_rgb(205, 165, 103)),
                 );
             }
         });
+        let mut watch = self.autolink_watcher.is_some();
+        ui.horizontal(|ui| {
+            if ui
+                .checkbox(
+                    &mut watch,
+                    "Keep streams linked (re-link when a device or PipeWire comes back)",
+                )
+                .changed()
+            {
+                self.set_autolink_watcher(watch);
+            }
+            if let Some(watcher) = &self.autolink_watcher {
+                ui.label(
+                    RichText::new(format!("{} pass(es)", watcher.passes()))
+                        .color(Color32::from_rgb(175, 186, 204)),
+                );
+            }
+        });
+        ui.checkbox(
+            &mut self.cfg.autolink.unlink_manual_links,
+            "Also unlink hand-made links into sends / from recvs to sinks that do not match",
+        );
+        ui.horizontal(|ui| {
+            ui.label(
+                RichText::new("After restart/live apply, wait for nodes up to")
+                    .color(Color32::from_rgb(175, 186, 204)),
+            );
+            ui.add(
+                egui::DragValue::new(&mut self.cfg.autolink.wait_timeout_secs)
+                    .clamp_range(0..=120)
+                    .suffix(" s")
+                    .speed(1.0),
+            );
+        });
+        ui.add_space(8.0);
+        self.ui_host_discovery(ui);
+        ui.add_space(8.0);
+        self.ui_file_player(ui);
         ui.add_space(8.0);
 
         if self.cfg.sends.is_empty() {
@@ -291,6 +1296,8 @@ impl App {
         }
 
         let microphone_sources = self.microphone_sources.clone();
+        let discovered_hosts = self.discovered_hosts.clone();
+        let runtime = &self.runtime;
         let mut remove_index: Option<usize> = None;
         for (i, send) in self.cfg.sends.iter_mut().enumerate() {
             let accent = if send.enabled {
@@ -338,6 +1345,12 @@ impl App {
                     });
                 });
 
+                Self::ui_stream_runtime(
+                    ui,
+                    runtime.streams.get(&send.id),
+                    runtime.graph_error.as_deref(),
+                    "node not in graph",
+                );
                 ui.add_space(6.0);
                 ui.horizontal(|ui| {
                     ui.checkbox(&mut send.always_process, "Always process");
@@ -345,6 +1358,34 @@ impl App {
                 Self::ui_labeled_text(ui, "Stream name", &mut send.sess_name);
                 Self::ui_labeled_text(ui, "Sess media", &mut send.sess_media);
                 Self::ui_labeled_text(ui, "Destination IP", &mut send.destination_ip);
+                if !discovered_hosts.is_empty() {
+                    ui.horizontal(|ui| {
+                        ui.add_sized(
+                            egui::vec2(170.0, 22.0),
+                            egui::Label::new(
+                                RichText::new("Discovered host")
+                                    .color(Color32::from_rgb(202, 216, 236)),
+                            ),
+                        );
+                        let selected = discovered_hosts
+                            .iter()
+                            .find(|host| host.addr.ip().to_string() == send.destination_ip.trim())
+                            .map(|host| host.label())
+                            .unwrap_or_else(|| "Pick a host...".to_string());
+                        egui::ComboBox::from_id_source(format!("send-host-{}", i))
+                            .selected_text(selected)
+                            .width(ui.available_width())
+                            .show_ui(ui, |ui| {
+                                for host in &discovered_hosts {
+                                    ui.selectable_value(
+                                        &mut send.destination_ip,
+                                        host.addr.ip().to_string(),
+                                        format!("{}  {}", host.addr.ip(), host.label()),
+                                    );
+                                }
+                            });
+                    });
+                }
 
                 ui.horizontal(|ui| {
                     ui.add_sized(
@@ -361,32 +1402,616 @@ impl App {
                     );
                 });
 
-                Self::ui_labeled_text(ui, "Audio format", &mut send.audio_format);
+                Self::ui_labeled_text(ui, "Audio format", &mut send.audio_format);
+
+                ui.horizontal(|ui| {
+                    ui.add_sized(
+                        egui::vec2(170.0, 22.0),
+                        egui::Label::new(
+                            RichText::new("Audio rate").color(Color32::from_rgb(202, 216, 236)),
+                        ),
+                    );
+                    ui.add(
+                        egui::DragValue::new(&mut send.audio_rate)
+                            .clamp_range(8_000..=384_000)
+                            .speed(10.0),
+                    );
+                });
+
+                ui.horizontal(|ui| {
+                    ui.add_sized(
+                        egui::vec2(170.0, 22.0),
+                        egui::Label::new(
+                            RichText::new("Audio channels").color(Color32::from_rgb(202, 216, 236)),
+                        ),
+                    );
+                    ui.add(
+                        egui::DragValue::new(&mut send.audio_channels)
+                            .clamp_range(1..=32)
+                            .speed(1.0),
+                    );
+                });
+
+                ui.horizontal(|ui| {
+                    ui.add_sized(
+                        egui::vec2(170.0, 22.0),
+                        egui::Label::new(
+                            RichText::new("Source").color(Color32::from_rgb(202, 216, 236)),
+                        ),
+                    );
+                    egui::ComboBox::from_id_source(format!("send-source-{}", i))
+                        .selected_text(Self::selected_microphone_label(
+                            &send.target_object,
+                            &microphone_sources,
+                        ))
+                        .width(ui.available_width())
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(
+                                &mut send.target_object,
+                                String::new(),
+                                "None (manual patch in qpwgraph)",
+                            );
+                            let mut kind = None;
+                            // A send monitoring itself would feed its own output back in.
+                            for source in microphone_sources
+                                .iter()
+                                .filter(|source| source.node_name != send.node_name.trim())
+                            {
+                                if kind != Some(source.kind) {
+                                    kind = Some(source.kind);
+                                    ui.separator();
+                                    ui.label(
+                                        RichText::new(source.kind.label())
+                                            .strong()
+                                            .color(Color32::from_rgb(175, 186, 204)),
+                                    );
+                                }
+                                ui.selectable_value(
+                                    &mut send.target_object,
+                                    source.node_name.clone(),
+                                    Self::microphone_option_label(source),
+                                );
+                            }
+                        });
+                });
+
+                Self::ui_labeled_text(ui, "target.object", &mut send.target_object);
+                Self::ui_channel_map(ui, i, send);
+                Self::ui_labeled_text(ui, "node.name", &mut send.node_name);
+                Self::ui_labeled_text(ui, "node.description", &mut send.node_description);
+            });
+            ui.add_space(8.0);
+        }
+
+        if let Some(i) = remove_index {
+            self.cfg.sends.remove(i);
+            self.status = "Send removed. Save/apply to update.".into();
+        }
+    }
+
+    fn ui_file_player(&mut self, ui: &mut egui::Ui) {
+        let accent = Color32::from_rgb(96, 170, 230);
+        Self::ui_card_frame(Color32::from_rgb(27, 38, 50), accent.gamma_multiply(0.7)).show(
+            ui,
+            |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    ui.label(RichText::new("Play WAV file").strong().color(accent));
+                    ui.separator();
+
+                    if let Some(player) = &self.player {
+                        let settings = player.settings();
+                        ui.label(format!(
+                            "Sending \"{}\" to {}:{} ({} packets)",
+                            settings.stream_name,
+                            settings.destination_ip,
+                            settings.destination_port,
+                            player.packets_sent()
+                        ));
+                        if Self::action_button(ui, "Stop", Color32::from_rgb(187, 72, 72)) {
+                            self.player = None;
+                            self.status = "Playback stopped.".into();
+                        }
+                        return;
+                    }
+
+                    let selected = self
+                        .cfg
+                        .sends
+                        .iter()
+                        .position(|send| send.id == self.play_send);
+                    egui::ComboBox::from_id_source("play-send")
+                        .selected_text(match selected {
+                            Some(i) => format!("Send {} ({})", i + 1, self.cfg.sends[i].sess_name),
+                            None => "Use send...".to_string(),
+                        })
+                        .show_ui(ui, |ui| {
+                            for (i, send) in self.cfg.sends.iter().enumerate() {
+                                ui.selectable_value(
+                                    &mut self.play_send,
+                                    send.id,
+                                    format!(
+                                        "Send {} ({} -> {}:{})",
+                                        i + 1,
+                                        send.sess_name,
+                                        send.destination_ip,
+                                        send.destination_port
+                                    ),
+                                );
+                            }
+                        });
+                    ui.checkbox(&mut self.play_loop, "Loop");
+
+                    if let Some(i) = selected {
+                        if Self::action_button(ui, "Play", Color32::from_rgb(43, 133, 219)) {
+                            let settings = player::PlaySettings::from_send(
+                                &self.cfg.sends[i],
+                                self.play_path.trim().into(),
+                                self.play_loop,
+                            );
+                            self.status = format!(
+                                "Playing {} to {}:{}.",
+                                settings.path.display(),
+                                settings.destination_ip,
+                                settings.destination_port
+                            );
+                            self.player = Some(player::FilePlayer::spawn(settings));
+                        }
+                    }
+                });
+
+                if self.player.is_none() {
+                    Self::ui_labeled_text(ui, "WAV file", &mut self.play_path);
+                }
+            },
+        );
+    }
+
+    fn ui_host_discovery(&mut self, ui: &mut egui::Ui) {
+        Self::ui_card_frame(
+            Color32::from_rgb(27, 35, 50),
+            Color32::from_rgb(52, 96, 160),
+        )
+        .show(ui, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label(
+                    RichText::new("Discover hosts")
+                        .strong()
+                        .color(Color32::from_rgb(64, 164, 255)),
+                );
+                ui.separator();
+                ui.checkbox(&mut self.cfg.host_discovery.broadcast, "Broadcast");
+                ui.label("Port");
+                ui.add(
+                    egui::DragValue::new(&mut self.cfg.host_discovery.port)
+                        .clamp_range(1..=u16::MAX)
+                        .speed(1.0),
+                );
+                ui.label("Addresses");
+                let addresses = ui.add(
+                    egui::TextEdit::singleline(&mut self.discovery_addresses)
+                        .hint_text("10.0.0.5, studio-pc:6980"),
+                );
+                if addresses.changed() {
+                    self.cfg.host_discovery.addresses = self
+                        .discovery_addresses
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|address| !address.is_empty())
+                        .map(ToOwned::to_owned)
+                        .collect();
+                }
+
+                if self.host_discovery.is_some() {
+                    ui.spinner();
+                    ui.label("Waiting for replies...");
+                } else if Self::action_button(ui, "Send PING0", Color32::from_rgb(52, 96, 160)) {
+                    self.start_host_discovery(ui.ctx());
+                }
+            });
+
+            for host in &self.discovered_hosts {
+                ui.label(
+                    RichText::new(format!("{}  {}", host.addr.ip(), host.label()))
+                        .color(Color32::from_rgb(206, 220, 241)),
+                );
+            }
+        });
+    }
+
+    fn ui_stream_scan(&mut self, ui: &mut egui::Ui) {
+        Self::ui_card_frame(
+            Color32::from_rgb(26, 38, 44),
+            Color32::from_rgb(42, 122, 104),
+        )
+        .show(ui, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label(
+                    RichText::new("Scan network")
+                        .strong()
+                        .color(Color32::from_rgb(49, 204, 152)),
+                );
+                ui.separator();
+                ui.label("UDP port");
+                ui.add(
+                    egui::DragValue::new(&mut self.scan_port)
+                        .clamp_range(1..=u16::MAX)
+                        .speed(1.0),
+                );
+                ui.label("Seconds");
+                ui.add(
+                    egui::DragValue::new(&mut self.scan_seconds)
+                        .clamp_range(1..=30)
+                        .speed(1.0),
+                );
+
+                if self.stream_scan.is_some() {
+                    ui.spinner();
+                    ui.label("Listening...");
+                } else if Self::action_button(ui, "Scan", Color32::from_rgb(42, 122, 104)) {
+                    self.start_stream_scan(ui.ctx());
+                }
+            });
+
+            if self.discovered_streams.is_empty() {
+                return;
+            }
+
+            ui.add_space(4.0);
+            let mut create: Option<usize> = None;
+            for (i, stream) in self.discovered_streams.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.label(
+                        RichText::new(format!("{}  \"{}\"", stream.source_ip, stream.stream_name))
+                            .color(Color32::from_rgb(206, 220, 241)),
+                    );
+                    ui.label(
+                        RichText::new(format!(
+                            "{} ({} packets)",
+                            stream.format_label(),
+                            stream.packets
+                        ))
+                        .color(Color32::from_rgb(175, 186, 204)),
+                    );
+                    if Self::action_button(
+                        ui,
+                        "Create recv from this",
+                        Color32::from_rgb(23, 176, 127),
+                    ) {
+                        create = Some(i);
+                    }
+                });
+            }
+
+            if let Some(i) = create {
+                let stream = &self.discovered_streams[i];
+                self.cfg.recvs.push(VbanRecv {
+                    source_ip: stream.source_ip.to_string(),
+                    source_port: self.scan_port,
+                    stream_name: stream.stream_name.clone(),
+                    ..VbanRecv::default()
+                });
+                self.status = format!(
+                    "Recv created for `{}` from {}. Save/apply to update.",
+                    stream.stream_name, stream.source_ip
+                );
+            }
+        });
+    }
+
+    fn ui_tone_generator(&mut self, ui: &mut egui::Ui) {
+        let accent = Color32::from_rgb(206, 170, 70);
+        Self::ui_card_frame(Color32::from_rgb(40, 37, 28), accent.gamma_multiply(0.7)).show(
+            ui,
+            |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    ui.label(RichText::new("Test tone").strong().color(accent));
+                    ui.separator();
+
+                    if let Some(tone) = &self.tone {
+                        let settings = tone.settings();
+                        ui.label(format!(
+                            "Sending \"{}\" to {}:{} ({} packets)",
+                            settings.stream_name,
+                            settings.destination_ip,
+                            settings.destination_port,
+                            tone.packets_sent()
+                        ));
+                        if Self::action_button(ui, "Stop", Color32::from_rgb(187, 72, 72)) {
+                            self.tone = None;
+                            self.status = "Test tone stopped.".into();
+                        }
+                        return;
+                    }
+
+                    egui::ComboBox::from_id_source("tone-fill-from-recv")
+                        .selected_text("Fill from recv...")
+                        .show_ui(ui, |ui| {
+                            for recv in &self.cfg.recvs {
+                                let name = if recv.stream_name.trim().is_empty() {
+                                    "Stream1"
+                                } else {
+                                    recv.stream_name.trim()
+                                };
+                                if ui
+                                    .selectable_label(
+                                        false,
+                                        format!("{name} (port {})", recv.source_port),
+                                    )
+                                    .clicked()
+                                {
+                                    self.tone_settings.destination_ip = "127.0.0.1".into();
+                                    self.tone_settings.destination_port = recv.source_port;
+                                    self.tone_settings.stream_name = name.to_string();
+                                }
+                            }
+                        });
+                    if Self::action_button(ui, "Start", Color32::from_rgb(160, 128, 40)) {
+                        self.tone =
+                            Some(generator::ToneGenerator::spawn(self.tone_settings.clone()));
+                        self.status = format!(
+                            "Test tone started to {}:{}.",
+                            self.tone_settings.destination_ip, self.tone_settings.destination_port
+                        );
+                    }
+                });
+
+                if self.tone.is_some() {
+                    return;
+                }
+
+                let settings = &mut self.tone_settings;
+                Self::ui_labeled_text(ui, "Destination IP", &mut settings.destination_ip);
+                Self::ui_labeled_text(ui, "Stream name", &mut settings.stream_name);
+                Self::ui_labeled_text(ui, "Audio format", &mut settings.audio_format);
+                ui.horizontal_wrapped(|ui| {
+                    ui.label("Port");
+                    ui.add(
+                        egui::DragValue::new(&mut settings.destination_port)
+                            .clamp_range(1..=u16::MAX)
+                            .speed(1.0),
+                    );
+                    ui.label("Rate");
+                    egui::ComboBox::from_id_source("tone-rate")
+                        .selected_text(settings.audio_rate.to_string())
+                        .show_ui(ui, |ui| {
+                            for rate in vban::SAMPLE_RATES {
+                                ui.selectable_value(
+                                    &mut settings.audio_rate,
+                                    rate,
+                                    rate.to_string(),
+                                );
+                            }
+                        });
+                    ui.label("Channels");
+                    ui.add(
+                        egui::DragValue::new(&mut settings.audio_channels)
+                            .clamp_range(1..=32)
+                            .speed(1.0),
+                    );
+                    ui.label("Level");
+                    ui.add(egui::Slider::new(&mut settings.amplitude, 0.0..=1.0));
+                });
+                ui.horizontal(|ui| {
+                    let is_sine = matches!(settings.waveform, generator::Waveform::Sine { .. });
+                    if ui.radio(is_sine, "Sine").clicked() && !is_sine {
+                        settings.waveform = generator::Waveform::Sine { frequency: 440.0 };
+                    }
+                    if let generator::Waveform::Sine { frequency } = &mut settings.waveform {
+                        ui.add(
+                            egui::DragValue::new(frequency)
+                                .clamp_range(20.0..=20_000.0)
+                                .suffix(" Hz")
+                                .speed(1.0),
+                        );
+                    }
+                    if ui.radio(!is_sine, "Pink noise").clicked() {
+                        settings.waveform = generator::Waveform::PinkNoise;
+                    }
+                });
+            },
+        );
+    }
+
+    fn ui_recorder(&mut self, ui: &mut egui::Ui) {
+        let accent = Color32::from_rgb(219, 96, 96);
+        Self::ui_card_frame(Color32::from_rgb(44, 30, 32), accent.gamma_multiply(0.7)).show(
+            ui,
+            |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    ui.label(RichText::new("Record to WAV").strong().color(accent));
+                    ui.separator();
+
+                    if let Some(recorder) = &self.recorder {
+                        let settings = recorder.settings();
+                        ui.label(format!(
+                            "Recording \"{}\" from port {} ({:.1} MB)",
+                            settings.stream_name,
+                            settings.port,
+                            recorder.bytes_written() as f64 / (1024.0 * 1024.0)
+                        ));
+                        if Self::action_button(ui, "Stop", Color32::from_rgb(187, 72, 72)) {
+                            if let Some(recorder) = self.recorder.take() {
+                                self.status = Self::record_status(recorder.finish());
+                            }
+                        }
+                        return;
+                    }
+
+                    egui::ComboBox::from_id_source("record-fill-from-scan")
+                        .selected_text("Fill from scan...")
+                        .show_ui(ui, |ui| {
+                            for stream in &self.discovered_streams {
+                                if ui
+                                    .selectable_label(
+                                        false,
+                                        format!("{}  \"{}\"", stream.source_ip, stream.stream_name),
+                                    )
+                                    .clicked()
+                                {
+                                    self.record_settings.port = self.scan_port;
+                                    self.record_settings.stream_name = stream.stream_name.clone();
+                                }
+                            }
+                        });
+                    if Self::action_button(ui, "Record", Color32::from_rgb(170, 60, 60)) {
+                        self.recorder =
+                            Some(recorder::Recorder::spawn(self.record_settings.clone()));
+                        self.status = format!(
+                            "Recording `{}` to {}.",
+                            self.record_settings.stream_name,
+                            self.record_settings.path.display()
+                        );
+                    }
+                });
+
+                if self.recorder.is_some() {
+                    return;
+                }
+
+                let settings = &mut self.record_settings;
+                Self::ui_labeled_text(ui, "Stream name", &mut settings.stream_name);
+                let mut path = settings.path.display().to_string();
+                Self::ui_labeled_text(ui, "WAV file", &mut path);
+                if path != settings.path.display().to_string() {
+                    settings.path = path.into();
+                }
+                ui.horizontal_wrapped(|ui| {
+                    ui.label("UDP port");
+                    ui.add(
+                        egui::DragValue::new(&mut settings.port)
+                            .clamp_range(1..=u16::MAX)
+                            .speed(1.0),
+                    );
+
+                    let mut limit_seconds = settings.max_seconds.is_some();
+                    ui.checkbox(&mut limit_seconds, "Max seconds");
+                    let mut seconds = settings.max_seconds.unwrap_or(60);
+                    ui.add_enabled(
+                        limit_seconds,
+                        egui::DragValue::new(&mut seconds)
+                            .clamp_range(1..=86_400)
+                            .speed(1.0),
+                    );
+                    settings.max_seconds = limit_seconds.then_some(seconds);
+
+                    let mut limit_size = settings.max_bytes.is_some();
+                    ui.checkbox(&mut limit_size, "Max MB");
+                    let mut megabytes = settings.max_bytes.map_or(100, |b| b / (1024 * 1024));
+                    ui.add_enabled(
+                        limit_size,
+                        egui::DragValue::new(&mut megabytes)
+                            .clamp_range(1..=4_000)
+                            .speed(1.0),
+                    );
+                    settings.max_bytes = limit_size.then_some(megabytes * 1024 * 1024);
+                });
+            },
+        );
+    }
+
+    fn ui_recvs(&mut self, ui: &mut egui::Ui) {
+        if Self::action_button(ui, "+ Add recv", Color32::from_rgb(23, 176, 127)) {
+            self.cfg.recvs.push(VbanRecv::default());
+        }
+        ui.add_space(8.0);
+        self.ui_stream_scan(ui);
+        ui.add_space(8.0);
+        self.ui_tone_generator(ui);
+        ui.add_space(8.0);
+        self.ui_recorder(ui);
+        ui.add_space(8.0);
+
+        if self.cfg.recvs.is_empty() {
+            ui.label(RichText::new("No recv configured.").color(Color32::from_rgb(175, 186, 204)));
+            return;
+        }
+
+        let runtime = &self.runtime;
+        let playback_sinks = &self.playback_sinks;
+        let mut remove_index: Option<usize> = None;
+        let mut monitor_action: Option<(usize, bool)> = None;
+        for (i, recv) in self.cfg.recvs.iter_mut().enumerate() {
+            let accent = if recv.enabled {
+                Color32::from_rgb(49, 204, 152)
+            } else {
+                Color32::from_rgb(111, 120, 135)
+            };
+            let fill = if recv.enabled {
+                Color32::from_rgb(27, 44, 48)
+            } else {
+                Color32::from_rgb(34, 39, 48)
+            };
+
+            Self::ui_card_frame(fill, accent).show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut recv.enabled, "");
+                    ui.label(
+                        RichText::new(format!("Recv {}", i + 1))
+                            .strong()
+                            .size(17.0)
+                            .color(accent),
+                    );
+                    ui.separator();
+                    let title = if recv.stream_name.trim().is_empty() {
+                        "* (all streams)"
+                    } else {
+                        &recv.stream_name
+                    };
+                    ui.label(RichText::new(title).color(Color32::from_rgb(206, 220, 241)));
+
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui
+                            .add(
+                                egui::Button::new(
+                                    RichText::new("Delete").strong().color(Color32::WHITE),
+                                )
+                                .fill(Color32::from_rgb(187, 72, 72))
+                                .stroke(Stroke::new(1.0, Color32::from_rgb(219, 91, 91)))
+                                .rounding(egui::Rounding::same(8.0)),
+                            )
+                            .clicked()
+                        {
+                            remove_index = Some(i);
+                        }
+                    });
+                });
+
+                Self::ui_stream_runtime(
+                    ui,
+                    runtime.streams.get(&recv.id),
+                    runtime.graph_error.as_deref(),
+                    "node not in graph (created when a stream arrives)",
+                );
+                ui.add_space(6.0);
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut recv.always_process, "Always process");
+                });
+                Self::ui_labeled_text(ui, "Stream name (empty = all)", &mut recv.stream_name);
+                Self::ui_labeled_text(ui, "Source IP", &mut recv.source_ip);
 
                 ui.horizontal(|ui| {
                     ui.add_sized(
                         egui::vec2(170.0, 22.0),
                         egui::Label::new(
-                            RichText::new("Audio rate").color(Color32::from_rgb(202, 216, 236)),
+                            RichText::new("Source port").color(Color32::from_rgb(202, 216, 236)),
                         ),
                     );
                     ui.add(
-                        egui::DragValue::new(&mut send.audio_rate)
-                            .clamp_range(8_000..=384_000)
-                            .speed(10.0),
+                        egui::DragValue::new(&mut recv.source_port)
+                            .clamp_range(1..=u16::MAX)
+                            .speed(1.0),
                     );
                 });
-
                 ui.horizontal(|ui| {
                     ui.add_sized(
                         egui::vec2(170.0, 22.0),
                         egui::Label::new(
-                            RichText::new("Audio channels").color(Color32::from_rgb(202, 216, 236)),
+                            RichText::new("Latency ms").color(Color32::from_rgb(202, 216, 236)),
                         ),
                     );
                     ui.add(
-                        egui::DragValue::new(&mut send.audio_channels)
-                            .clamp_range(1..=32)
+                        egui::DragValue::new(&mut recv.latency_msec)
+                            .clamp_range(0..=5_000)
                             .speed(1.0),
                     );
                 });
@@ -395,85 +2020,309 @@ impl App {
                     ui.add_sized(
                         egui::vec2(170.0, 22.0),
                         egui::Label::new(
-                            RichText::new("Microphone source")
-                                .color(Color32::from_rgb(202, 216, 236)),
+                            RichText::new("Playback sink").color(Color32::from_rgb(202, 216, 236)),
                         ),
                     );
-                    egui::ComboBox::from_id_source(format!("send-source-{}", i))
-                        .selected_text(Self::selected_microphone_label(
-                            &send.target_object,
-                            &microphone_sources,
-                        ))
+                    let target = recv.target_sink.trim();
+                    let selected = if target.is_empty() {
+                        "None (manual patch in qpwgraph)".to_string()
+                    } else {
+                        playback_sinks
+                            .iter()
+                            .find(|sink| sink.node_name == target)
+                            .map(|sink| format!("{} ({})", sink.description, sink.node_name))
+                            .unwrap_or_else(|| format!("Custom: {target}"))
+                    };
+                    egui::ComboBox::from_id_source(format!("recv-sink-{}", i))
+                        .selected_text(selected)
                         .width(ui.available_width())
                         .show_ui(ui, |ui| {
                             ui.selectable_value(
-                                &mut send.target_object,
+                                &mut recv.target_sink,
                                 String::new(),
                                 "None (manual patch in qpwgraph)",
                             );
-                            for source in &microphone_sources {
+                            for sink in playback_sinks {
                                 ui.selectable_value(
-                                    &mut send.target_object,
-                                    source.node_name.clone(),
-                                    Self::microphone_option_label(source),
+                                    &mut recv.target_sink,
+                                    sink.node_name.clone(),
+                                    format!("{} ({})", sink.description, sink.node_name),
                                 );
                             }
                         });
                 });
+                Self::ui_labeled_text(ui, "target.object", &mut recv.target_sink);
+                Self::ui_labeled_text(ui, "node.name", &mut recv.node_name);
+                Self::ui_labeled_text(ui, "node.description", &mut recv.node_description);
 
-                Self::ui_labeled_text(ui, "target.object", &mut send.target_object);
-                Self::ui_labeled_text(ui, "node.name", &mut send.node_name);
-                Self::ui_labeled_text(ui, "node.description", &mut send.node_description);
+                ui.add_space(4.0);
+                if let Some(start) = Self::ui_stream_monitor(
+                    ui,
+                    recv,
+                    self.stream_monitor.as_ref(),
+                    &mut self.monitor_port,
+                ) {
+                    monitor_action = Some((i, start));
+                }
             });
             ui.add_space(8.0);
         }
 
+        match monitor_action {
+            Some((i, true)) => self.start_stream_monitor(i),
+            Some((_, false)) => {
+                self.stream_monitor = None;
+                self.status = "Monitor stopped.".into();
+            }
+            None => {}
+        }
+
         if let Some(i) = remove_index {
-            self.cfg.sends.remove(i);
-            self.status = "Send removed. Save/apply to update.".into();
+            if self
+                .stream_monitor
+                .as_ref()
+                .is_some_and(|m| m.recv_id() == Some(self.cfg.recvs[i].id))
+            {
+                self.stream_monitor = None;
+            }
+            self.cfg.recvs.remove(i);
+            self.status = "Recv removed. Save/apply to update.".into();
         }
     }
 
-    fn ui_recvs(&mut self, ui: &mut egui::Ui) {
-        if Self::action_button(ui, "+ Add recv", Color32::from_rgb(23, 176, 127)) {
-            self.cfg.recvs.push(VbanRecv::default());
+    fn remote_target_label(targets: &[TextTarget], id: uuid::Uuid) -> String {
+        targets
+            .iter()
+            .find(|target| target.id == id)
+            .map(|target| format!("{} ({}:{})", target.name, target.ip, target.port))
+            .unwrap_or_else(|| "Pick a target...".to_string())
+    }
+
+    fn ui_remote_target_combo(
+        ui: &mut egui::Ui,
+        id_source: String,
+        targets: &[TextTarget],
+        selected: &mut uuid::Uuid,
+    ) {
+        egui::ComboBox::from_id_source(id_source)
+            .selected_text(Self::remote_target_label(targets, *selected))
+            .show_ui(ui, |ui| {
+                for target in targets {
+                    ui.selectable_value(
+                        selected,
+                        target.id,
+                        format!("{} ({}:{})", target.name, target.ip, target.port),
+                    );
+                }
+            });
+    }
+
+    fn ui_remote(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if Self::action_button(ui, "+ Add target", Color32::from_rgb(142, 98, 204)) {
+                self.cfg.text_remote.targets.push(TextTarget::default());
+            }
+            if Self::action_button(ui, "+ Add macro", Color32::from_rgb(110, 82, 160)) {
+                let target_id = self
+                    .cfg
+                    .text_remote
+                    .targets
+                    .first()
+                    .map(|target| target.id)
+                    .unwrap_or_else(uuid::Uuid::nil);
+                self.cfg.text_remote.macros.push(TextMacro {
+                    target_id,
+                    ..TextMacro::default()
+                });
+            }
+        });
+        ui.add_space(8.0);
+
+        if self.cfg.text_remote.targets.is_empty() {
+            ui.label(
+                RichText::new("No VBAN-TEXT target configured.")
+                    .color(Color32::from_rgb(175, 186, 204)),
+            );
+            return;
+        }
+
+        let accent = Color32::from_rgb(171, 130, 235);
+        let mut remove_target: Option<usize> = None;
+        for (i, target) in self.cfg.text_remote.targets.iter_mut().enumerate() {
+            Self::ui_card_frame(Color32::from_rgb(38, 32, 52), accent).show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(
+                        RichText::new(format!("Target {}", i + 1))
+                            .strong()
+                            .size(17.0)
+                            .color(accent),
+                    );
+                    ui.separator();
+                    ui.label(RichText::new(&target.name).color(Color32::from_rgb(206, 220, 241)));
+
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if Self::action_button(ui, "Delete", Color32::from_rgb(187, 72, 72)) {
+                            remove_target = Some(i);
+                        }
+                    });
+                });
+
+                ui.add_space(6.0);
+                Self::ui_labeled_text(ui, "Name", &mut target.name);
+                Self::ui_labeled_text(ui, "IP", &mut target.ip);
+                ui.horizontal(|ui| {
+                    ui.add_sized(
+                        egui::vec2(170.0, 22.0),
+                        egui::Label::new(
+                            RichText::new("Port").color(Color32::from_rgb(202, 216, 236)),
+                        ),
+                    );
+                    ui.add(
+                        egui::DragValue::new(&mut target.port)
+                            .clamp_range(1..=u16::MAX)
+                            .speed(1.0),
+                    );
+                });
+                Self::ui_labeled_text(ui, "Stream name", &mut target.stream_name);
+            });
+            ui.add_space(8.0);
+        }
+
+        if let Some(i) = remove_target {
+            self.cfg.text_remote.targets.remove(i);
+            self.status = "Target removed. Save to keep the change.".into();
+            return;
+        }
+
+        let targets = self.cfg.text_remote.targets.clone();
+        Self::ui_card_frame(
+            Color32::from_rgb(32, 30, 44),
+            Color32::from_rgb(110, 82, 160),
+        )
+        .show(ui, |ui| {
+            ui.label(RichText::new("Command").strong().color(accent));
+            ui.horizontal(|ui| {
+                Self::ui_remote_target_combo(
+                    ui,
+                    "remote-command-target".into(),
+                    &targets,
+                    &mut self.remote_target,
+                );
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.remote_command)
+                        .hint_text("Strip[0].Gain=-6;"),
+                );
+                if Self::action_button(ui, "Send", Color32::from_rgb(142, 98, 204)) {
+                    let result = targets
+                        .iter()
+                        .find(|target| target.id == self.remote_target)
+                        .ok_or_else(|| anyhow::anyhow!("no target selected"))
+                        .and_then(|target| {
+                            text_remote::send_text_command(target, &self.remote_command)
+                        });
+                    self.status = match result {
+                        Ok(()) => format!("VBAN-TEXT sent: {}", self.remote_command.trim()),
+                        Err(e) => format!("VBAN-TEXT error: {e:#}"),
+                    };
+                }
+            });
+
+            if self.cfg.text_remote.macros.is_empty() {
+                return;
+            }
+
+            ui.add_space(6.0);
+            ui.label(RichText::new("Macros").strong().color(accent));
+            let mut send_macro: Option<usize> = None;
+            let mut remove_macro: Option<usize> = None;
+            for (i, text_macro) in self.cfg.text_remote.macros.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    if Self::action_button(ui, &text_macro.name, Color32::from_rgb(142, 98, 204)) {
+                        send_macro = Some(i);
+                    }
+                    ui.add(egui::TextEdit::singleline(&mut text_macro.name).desired_width(140.0));
+                    ui.add(
+                        egui::TextEdit::singleline(&mut text_macro.command).desired_width(260.0),
+                    );
+                    Self::ui_remote_target_combo(
+                        ui,
+                        format!("remote-macro-target-{i}"),
+                        &targets,
+                        &mut text_macro.target_id,
+                    );
+                    if Self::action_button(ui, "Delete", Color32::from_rgb(187, 72, 72)) {
+                        remove_macro = Some(i);
+                    }
+                });
+            }
+
+            if let Some(i) = send_macro {
+                let text_macro = &self.cfg.text_remote.macros[i];
+                self.status = match text_remote::send_macro(&self.cfg.text_remote, text_macro) {
+                    Ok(()) => format!("Macro `{}` sent.", text_macro.name),
+                    Err(e) => format!("VBAN-TEXT error: {e:#}"),
+                };
+            }
+            if let Some(i) = remove_macro {
+                self.cfg.text_remote.macros.remove(i);
+                self.status = "Macro removed. Save to keep the change.".into();
+            }
+        });
+    }
+
+    fn ui_relays(&mut self, ui: &mut egui::Ui) {
+        if Self::action_button(ui, "+ Add relay", Color32::from_rgb(206, 112, 64)) {
+            self.cfg.relays.push(VbanRelay {
+                destinations: vec![RelayDestination::default()],
+                ..VbanRelay::default()
+            });
         }
         ui.add_space(8.0);
 
-        if self.cfg.recvs.is_empty() {
-            ui.label(RichText::new("No recv configured.").color(Color32::from_rgb(175, 186, 204)));
+        if self.cfg.relays.is_empty() {
+            ui.label(RichText::new("No relay configured.").color(Color32::from_rgb(175, 186, 204)));
             return;
         }
 
         let mut remove_index: Option<usize> = None;
-        for (i, recv) in self.cfg.recvs.iter_mut().enumerate() {
-            let accent = if recv.enabled {
-                Color32::from_rgb(49, 204, 152)
+        for (i, relay) in self.cfg.relays.iter_mut().enumerate() {
+            let running = self.relays.iter().find(|running| running.id() == relay.id);
+            let accent = if relay.enabled {
+                Color32::from_rgb(230, 140, 84)
             } else {
                 Color32::from_rgb(111, 120, 135)
             };
-            let fill = if recv.enabled {
-                Color32::from_rgb(27, 44, 48)
+            let fill = if relay.enabled {
+                Color32::from_rgb(48, 36, 30)
             } else {
                 Color32::from_rgb(34, 39, 48)
             };
 
             Self::ui_card_frame(fill, accent).show(ui, |ui| {
                 ui.horizontal(|ui| {
-                    ui.checkbox(&mut recv.enabled, "");
+                    ui.checkbox(&mut relay.enabled, "");
                     ui.label(
-                        RichText::new(format!("Recv {}", i + 1))
+                        RichText::new(format!("Relay {}", i + 1))
                             .strong()
                             .size(17.0)
                             .color(accent),
                     );
                     ui.separator();
-                    let title = if recv.stream_name.trim().is_empty() {
-                        "* (all streams)"
-                    } else {
-                        &recv.stream_name
+                    ui.label(RichText::new(&relay.name).color(Color32::from_rgb(206, 220, 241)));
+                    ui.separator();
+                    match running {
+                        Some(running) if running.matches(relay) => ui.label(
+                            RichText::new(format!("Running, {} packet(s) in", running.received()))
+                                .color(Color32::from_rgb(49, 204, 152)),
+                        ),
+                        Some(_) => ui.label(
+                            RichText::new("Running, save to apply changes")
+                                .color(Color32::from_rgb(240, 190, 110)),
+                        ),
+                        None => ui.label(
+                            RichText::new("Stopped").color(Color32::from_rgb(175, 186, 204)),
+                        ),
                     };
-                    ui.label(RichText::new(title).color(Color32::from_rgb(206, 220, 241)));
 
                     ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                         if ui
@@ -493,48 +2342,79 @@ impl App {
                 });
 
                 ui.add_space(6.0);
-                ui.horizontal(|ui| {
-                    ui.checkbox(&mut recv.always_process, "Always process");
-                });
-                Self::ui_labeled_text(ui, "Stream name (empty = all)", &mut recv.stream_name);
-                Self::ui_labeled_text(ui, "Source IP", &mut recv.source_ip);
-
+                Self::ui_labeled_text(ui, "Name", &mut relay.name);
                 ui.horizontal(|ui| {
                     ui.add_sized(
                         egui::vec2(170.0, 22.0),
                         egui::Label::new(
-                            RichText::new("Source port").color(Color32::from_rgb(202, 216, 236)),
+                            RichText::new("Listen port").color(Color32::from_rgb(202, 216, 236)),
                         ),
                     );
                     ui.add(
-                        egui::DragValue::new(&mut recv.source_port)
+                        egui::DragValue::new(&mut relay.listen_port)
                             .clamp_range(1..=u16::MAX)
                             .speed(1.0),
                     );
                 });
-                ui.horizontal(|ui| {
-                    ui.add_sized(
-                        egui::vec2(170.0, 22.0),
-                        egui::Label::new(
-                            RichText::new("Latency ms").color(Color32::from_rgb(202, 216, 236)),
-                        ),
-                    );
-                    ui.add(
-                        egui::DragValue::new(&mut recv.latency_msec)
-                            .clamp_range(0..=5_000)
-                            .speed(1.0),
-                    );
-                });
+                Self::ui_labeled_text(ui, "Stream filter (empty = all)", &mut relay.stream_filter);
+                Self::ui_labeled_text(ui, "Rename to (empty = keep)", &mut relay.rename);
 
-                Self::ui_labeled_text(ui, "node.name", &mut recv.node_name);
-                Self::ui_labeled_text(ui, "node.description", &mut recv.node_description);
+                ui.add_space(4.0);
+                let stats = running
+                    .map(|running| running.destination_stats())
+                    .unwrap_or_default();
+                let mut remove_destination: Option<usize> = None;
+                for (d, destination) in relay.destinations.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.add_sized(
+                            egui::vec2(170.0, 22.0),
+                            egui::Label::new(
+                                RichText::new(format!("Destination {}", d + 1))
+                                    .color(Color32::from_rgb(202, 216, 236)),
+                            ),
+                        );
+                        ui.add_sized(
+                            egui::vec2(160.0, 24.0),
+                            egui::TextEdit::singleline(&mut destination.ip),
+                        );
+                        ui.add(
+                            egui::DragValue::new(&mut destination.port)
+                                .clamp_range(1..=u16::MAX)
+                                .speed(1.0),
+                        );
+                        if let Some(stats) = stats.get(d) {
+                            ui.label(
+                                RichText::new(format!(
+                                    "{} packet(s), {:.1} MB, {} error(s)",
+                                    stats.packets,
+                                    stats.bytes as f64 / (1024.0 * 1024.0),
+                                    stats.errors
+                                ))
+                                .color(if stats.errors == 0 {
+                                    Color32::from_rgb(175, 186, 204)
+                                } else {
+                                    Color32::from_rgb(255, 140, 140)
+                                }),
+                            );
+                        }
+                        if ui.small_button("Remove").clicked() {
+                            remove_destination = Some(d);
+                        }
+                    });
+                }
+                if let Some(d) = remove_destination {
+                    relay.destinations.remove(d);
+                }
+                if ui.small_button("+ Destination").clicked() {
+                    relay.destinations.push(RelayDestination::default());
+                }
             });
             ui.add_space(8.0);
         }
 
         if let Some(i) = remove_index {
-            self.cfg.recvs.remove(i);
-            self.status = "Recv removed. Save/apply to update.".into();
+            self.cfg.relays.remove(i);
+            self.status = "Relay removed. Save to stop it.".into();
         }
     }
 
@@ -574,6 +2454,25 @@ impl App {
                 &mut self.cfg.host_info_emulation.client_name,
             );
 
+            ui.add_space(6.0);
+            ui.horizontal(|ui| {
+                ui.checkbox(
+                    &mut self.cfg.host_info_emulation.ping_responder,
+                    "Answer VBAN PING0 on UDP port",
+                );
+                ui.add(
+                    egui::DragValue::new(&mut self.cfg.host_info_emulation.ping_port)
+                        .clamp_range(1..=u16::MAX)
+                        .speed(1.0),
+                );
+                if let Some(responder) = &self.ping_responder {
+                    ui.label(
+                        RichText::new(format!("Responding on {}", responder.port()))
+                            .color(Color32::from_rgb(61, 176, 136)),
+                    );
+                }
+            });
+
             ui.add_space(6.0);
             ui.label(
                 RichText::new(
@@ -581,11 +2480,167 @@ impl App {
                 )
                 .color(Color32::from_rgb(192, 202, 217)),
             );
+            ui.label(
+                RichText::new(
+                    "PING0 replies advertise Client name as device, App name as application, plus Host and User name. Save to (re)start the responder.",
+                )
+                .color(Color32::from_rgb(192, 202, 217)),
+            );
             ui.label(
                 RichText::new("Use Save + Apply fragments to regenerate PipeWire drop-ins.")
                     .color(Color32::from_rgb(193, 166, 122)),
             );
         });
+
+        ui.add_space(8.0);
+        self.ui_import(ui);
+        ui.add_space(8.0);
+        self.ui_unmanaged_modules(ui);
+    }
+
+    fn ui_unmanaged_modules(&mut self, ui: &mut egui::Ui) {
+        let accent = Color32::from_rgb(120, 170, 210);
+        Self::ui_card_frame(Color32::from_rgb(28, 36, 46), accent.gamma_multiply(0.7)).show(
+            ui,
+            |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(
+                        RichText::new("Unmanaged running modules")
+                            .strong()
+                            .size(18.0)
+                            .color(accent),
+                    );
+                    if Self::action_button(ui, "Scan PipeWire", Color32::from_rgb(69, 94, 155)) {
+                        self.scan_unmanaged();
+                    }
+                });
+                ui.add_space(4.0);
+                ui.label(
+                    RichText::new(
+                        "vban modules loaded by other tools (e.g. pw-cli load-module) whose node is not in this config.",
+                    )
+                    .color(Color32::from_rgb(210, 218, 229)),
+                );
+
+                let mut adopt: Option<usize> = None;
+                for (i, module) in self.unmanaged.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.label(
+                            RichText::new("unmanaged")
+                                .small()
+                                .color(Color32::from_rgb(226, 170, 84)),
+                        );
+                        ui.label(
+                            RichText::new(module.label()).color(Color32::from_rgb(206, 220, 241)),
+                        );
+                        if ui.small_button("Import").clicked() {
+                            adopt = Some(i);
+                        }
+                    });
+                }
+                if let Some(i) = adopt {
+                    self.adopt_unmanaged(i);
+                }
+            },
+        );
+    }
+
+    fn ui_import(&mut self, ui: &mut egui::Ui) {
+        let accent = Color32::from_rgb(120, 170, 210);
+        Self::ui_card_frame(Color32::from_rgb(28, 36, 46), accent.gamma_multiply(0.7)).show(
+            ui,
+            |ui| {
+                ui.label(
+                    RichText::new("Import PipeWire fragments")
+                        .strong()
+                        .size(18.0)
+                        .color(accent),
+                );
+                ui.add_space(4.0);
+                ui.label(
+                    RichText::new(
+                        "Adopt hand-written libpipewire-module-vban-send/recv blocks so this config becomes the single source of truth.",
+                    )
+                    .color(Color32::from_rgb(210, 218, 229)),
+                );
+                ui.add_space(6.0);
+
+                ui.horizontal(|ui| {
+                    if Self::action_button(ui, "Scan pipewire.conf.d", Color32::from_rgb(69, 94, 155))
+                    {
+                        self.scan_imports();
+                    }
+                    ui.separator();
+                    ui.add_sized(
+                        egui::vec2(320.0, 24.0),
+                        egui::TextEdit::singleline(&mut self.import_path)
+                            .hint_text("/path/to/file.conf"),
+                    );
+                    if Self::action_button(ui, "Read file", Color32::from_rgb(69, 94, 155)) {
+                        self.add_import_file();
+                    }
+                });
+
+                for error in &self.import_errors {
+                    ui.label(
+                        RichText::new(format!("Skipped {error}"))
+                            .color(Color32::from_rgb(205, 165, 103)),
+                    );
+                }
+                if self.imported.is_empty() {
+                    return;
+                }
+
+                ui.add_space(4.0);
+                let mut remove: Option<usize> = None;
+                for (i, fragment) in self.imported.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.label(
+                            RichText::new(fragment.label())
+                                .color(Color32::from_rgb(206, 220, 241)),
+                        );
+                        if ui.small_button("Skip").clicked() {
+                            remove = Some(i);
+                        }
+                    });
+                    for send in &fragment.sends {
+                        ui.label(
+                            RichText::new(format!(
+                                "    send \"{}\" -> {}:{}  node={}",
+                                send.sess_name,
+                                send.destination_ip,
+                                send.destination_port,
+                                send.node_name
+                            ))
+                            .color(Color32::from_rgb(175, 186, 204)),
+                        );
+                    }
+                    for recv in &fragment.recvs {
+                        ui.label(
+                            RichText::new(format!(
+                                "    recv <- {}:{}  node={}",
+                                recv.source_ip, recv.source_port, recv.node_name
+                            ))
+                            .color(Color32::from_rgb(175, 186, 204)),
+                        );
+                    }
+                }
+                if let Some(i) = remove {
+                    self.imported.remove(i);
+                }
+
+                ui.add_space(4.0);
+                ui.horizontal(|ui| {
+                    ui.checkbox(
+                        &mut self.import_delete_originals,
+                        "Delete the original files (files with other modules are kept)",
+                    );
+                    if Self::action_button(ui, "Adopt", Color32::from_rgb(68, 150, 110)) {
+                        self.adopt_imports();
+                    }
+                });
+            },
+        );
     }
 
     fn status_style(status: &str) -> (Color32, Color32) {
@@ -615,6 +2670,18 @@ impl eframe::App for App {
             apply_visual_theme(ctx);
             self.theme_applied = true;
         }
+        self.poll_stream_scan();
+        self.poll_host_discovery();
+        self.poll_tone(ctx);
+        self.poll_stream_monitor(ctx);
+        self.poll_recorder(ctx);
+        self.poll_player(ctx);
+        self.poll_relays(ctx);
+        self.poll_runtime(ctx);
+        self.poll_autolink_watcher();
+        self.poll_apply();
+
+        self.ui_apply_preview(ctx);
 
         egui::CentralPanel::default().show(ctx, |ui| {
             self.ui_header(ui);
@@ -627,6 +2694,8 @@ impl eframe::App for App {
                 .show(ui, |ui| match self.tab {
                     Tab::Sends => self.ui_sends(ui),
                     Tab::Recvs => self.ui_recvs(ui),
+                    Tab::Remote => self.ui_remote(ui),
+                    Tab::Relays => self.ui_relays(ui),
                     Tab::Settings => self.ui_settings(ui),
                 });
 
@@ -676,6 +2745,15 @@ fn load_app_icon() -> Option<Arc<egui::IconData>> {
 }
 
 fn main() -> eframe::Result<()> {
+    let cli = cli::Cli::parse();
+    if let Some(command) = cli.command {
+        if let Err(e) = cli::run(command) {
+            eprintln!("Error: {e:#}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     let mut viewport = egui::ViewportBuilder::default().with_app_id(APP_ID);
     if let Some(icon) = load_app_icon() {
         viewport = viewport.with_icon(icon);
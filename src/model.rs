@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -7,6 +8,30 @@ pub struct AppConfig {
     pub sends: Vec<VbanSend>,
     pub recvs: Vec<VbanRecv>,
     pub host_info_emulation: HostInfoEmulation,
+    pub host_discovery: HostDiscovery,
+    pub text_remote: TextRemote,
+    pub relays: Vec<VbanRelay>,
+    pub autolink: AutoLinkOptions,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoLinkOptions {
+    /// Also remove hand-made links (e.g. from qpwgraph) into send nodes, and from recv nodes
+    /// into playback sinks, when they do not match the configured source or sink; otherwise
+    /// only links RustBAN created are removed. Apps recording from a recv are never unlinked.
+    pub unlink_manual_links: bool,
+    /// How long auto-linking after a restart or live apply waits for the nodes to appear.
+    pub wait_timeout_secs: u64,
+}
+
+impl Default for AutoLinkOptions {
+    fn default() -> Self {
+        Self {
+            unlink_manual_links: false,
+            wait_timeout_secs: 15,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,6 +42,9 @@ pub struct HostInfoEmulation {
     pub host_name: String,
     pub user_name: String,
     pub client_name: String,
+    /// Answer VBAN service PING0 requests with the identity above.
+    pub ping_responder: bool,
+    pub ping_port: u16,
 }
 
 impl Default for HostInfoEmulation {
@@ -27,6 +55,28 @@ impl Default for HostInfoEmulation {
             host_name: "vban-host".into(),
             user_name: "vban".into(),
             client_name: "VBAN Remote".into(),
+            ping_responder: false,
+            ping_port: 6980,
+        }
+    }
+}
+
+/// Where VBAN PING0 discovery requests are sent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HostDiscovery {
+    pub broadcast: bool,
+    pub port: u16,
+    /// Extra unicast targets, `host` or `host:port`.
+    pub addresses: Vec<String>,
+}
+
+impl Default for HostDiscovery {
+    fn default() -> Self {
+        Self {
+            broadcast: true,
+            port: 6980,
+            addresses: Vec::new(),
         }
     }
 }
@@ -47,6 +97,8 @@ pub struct VbanSend {
     pub node_name: String,
     pub node_description: String,
     pub target_object: String,
+    /// Manual routing from the source into the send; `None` matches ports by channel.
+    pub channel_map: Option<ChannelMap>,
 }
 
 impl Default for VbanSend {
@@ -66,7 +118,109 @@ impl Default for VbanSend {
             node_name: format!("vban-send-{}", id.simple()),
             node_description: "VBAN Send".into(),
             target_object: String::new(),
+            channel_map: None,
+        }
+    }
+}
+
+/// One source channel feeding one send channel, both 1-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChannelRoute {
+    pub source: u8,
+    pub send: u8,
+}
+
+/// Which output channels of the source feed which send channels; a source channel may feed
+/// several send channels (mono upmix).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChannelMap {
+    /// Columns shown in the grid; routes may not exceed it.
+    pub source_channels: u8,
+    pub routes: Vec<ChannelRoute>,
+}
+
+impl Default for ChannelMap {
+    fn default() -> Self {
+        Self {
+            source_channels: 2,
+            routes: Vec::new(),
+        }
+    }
+}
+
+impl ChannelMap {
+    /// Channel N to channel N; a mono source feeds every send channel.
+    pub fn straight(source_channels: u8, send_channels: u8) -> Self {
+        let routes = (1..=send_channels)
+            .map(|send| ChannelRoute {
+                source: if source_channels == 1 { 1 } else { send },
+                send,
+            })
+            .filter(|route| route.source <= source_channels)
+            .collect();
+        Self {
+            source_channels,
+            routes,
+        }
+    }
+
+    pub fn contains(&self, source: u8, send: u8) -> bool {
+        self.routes.contains(&ChannelRoute { source, send })
+    }
+
+    pub fn set(&mut self, source: u8, send: u8, on: bool) {
+        let route = ChannelRoute { source, send };
+        self.routes.retain(|existing| *existing != route);
+        if on {
+            self.routes.push(route);
+            self.routes.sort_by_key(|route| (route.send, route.source));
+        }
+    }
+}
+
+/// `1:1,1:2` (source:send pairs).
+impl fmt::Display for ChannelMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pairs: Vec<String> = self
+            .routes
+            .iter()
+            .map(|route| format!("{}:{}", route.source, route.send))
+            .collect();
+        f.write_str(&pairs.join(","))
+    }
+}
+
+impl FromStr for ChannelMap {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut map = Self {
+            source_channels: 1,
+            routes: Vec::new(),
+        };
+        for pair in value
+            .split(',')
+            .map(str::trim)
+            .filter(|pair| !pair.is_empty())
+        {
+            let route = pair
+                .split_once(':')
+                .and_then(|(source, send)| {
+                    Some(ChannelRoute {
+                        source: source.trim().parse().ok()?,
+                        send: send.trim().parse().ok()?,
+                    })
+                })
+                .filter(|route| route.source > 0 && route.send > 0)
+                .ok_or_else(|| format!("`{pair}` is not a source:send channel pair"))?;
+            map.source_channels = map.source_channels.max(route.source);
+            map.set(route.source, route.send, true);
         }
+        if map.routes.is_empty() {
+            return Err("the channel map has no source:send pair".into());
+        }
+        Ok(map)
     }
 }
 
@@ -82,6 +236,8 @@ pub struct VbanRecv {
     pub stream_name: String,
     pub node_name: String,
     pub node_description: String,
+    /// Playback sink the recv is linked into (`target.object`); empty to patch by hand.
+    pub target_sink: String,
 }
 
 impl Default for VbanRecv {
@@ -97,6 +253,103 @@ impl Default for VbanRecv {
             stream_name: String::new(),
             node_name: format!("vban-recv-{}", id.simple()),
             node_description: "VBAN Recv".into(),
+            target_sink: String::new(),
+        }
+    }
+}
+
+/// VBAN-TEXT command targets and saved macros (Voicemeeter remote control).
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct TextRemote {
+    pub targets: Vec<TextTarget>,
+    pub macros: Vec<TextMacro>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TextTarget {
+    pub id: Uuid,
+    pub name: String,
+    pub ip: String,
+    pub port: u16,
+    pub stream_name: String,
+}
+
+impl Default for TextTarget {
+    fn default() -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: "Voicemeeter".into(),
+            ip: "127.0.0.1".into(),
+            port: 6980,
+            stream_name: "Command1".into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TextMacro {
+    pub id: Uuid,
+    pub name: String,
+    pub command: String,
+    /// `TextTarget::id` the macro is sent to.
+    pub target_id: Uuid,
+}
+
+impl Default for TextMacro {
+    fn default() -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: "Mute strip 1".into(),
+            command: "Strip[0].Mute=1;".into(),
+            target_id: Uuid::nil(),
+        }
+    }
+}
+
+/// Userspace forwarder: VBAN packets received on one port are copied to every destination.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VbanRelay {
+    pub id: Uuid,
+    pub enabled: bool,
+    pub name: String,
+    pub listen_port: u16,
+    /// Only forward this stream name (empty = all streams).
+    pub stream_filter: String,
+    /// Rewrite the stream name of forwarded packets (empty = keep).
+    pub rename: String,
+    pub destinations: Vec<RelayDestination>,
+}
+
+impl Default for VbanRelay {
+    fn default() -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            enabled: true,
+            name: "Relay".into(),
+            listen_port: 6990,
+            stream_filter: String::new(),
+            rename: String::new(),
+            destinations: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RelayDestination {
+    pub ip: String,
+    pub port: u16,
+}
+
+impl Default for RelayDestination {
+    fn default() -> Self {
+        Self {
+            ip: "127.0.0.1".into(),
+            port: 6980,
         }
     }
 }
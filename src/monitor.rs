@@ -0,0 +1,411 @@
+use anyhow::{Context, Result};
+use std::{
+    io::ErrorKind,
+    net::{IpAddr, UdpSocket},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    discovery::DiscoveredStream,
+    model::VbanRecv,
+    vban::{Codec, VbanHeader, MAX_PACKET_SIZE},
+};
+
+/// A frame-counter jump larger than this is treated as a sender restart, not as loss.
+const RESTART_THRESHOLD: i64 = 10_000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamStats {
+    pub stream: DiscoveredStream,
+    /// Average packets per second since the first packet.
+    pub packet_rate: f64,
+    pub lost: u64,
+    pub out_of_order: u64,
+    /// Packets repeating the frame counter of the packet before them.
+    pub duplicates: u64,
+    /// RFC 3550 style inter-arrival jitter, in milliseconds.
+    pub jitter_ms: f64,
+    /// Differences between the packet headers and the configured recv.
+    pub mismatches: Vec<String>,
+}
+
+impl StreamStats {
+    pub fn summary(&self) -> String {
+        format!(
+            "{:.1} pkt/s, lost {}, out-of-order {}, duplicates {}, jitter {:.2} ms",
+            self.packet_rate, self.lost, self.out_of_order, self.duplicates, self.jitter_ms
+        )
+    }
+}
+
+struct Tracker {
+    stats: StreamStats,
+    first_arrival: Instant,
+    last_arrival: Instant,
+    last_counter: u32,
+    /// Audio duration of the last in-sequence packet, in seconds.
+    last_duration: Option<f64>,
+}
+
+impl Tracker {
+    fn new(source_ip: IpAddr, header: &VbanHeader, codec: Codec, at: Instant) -> Self {
+        Self {
+            stats: StreamStats {
+                stream: DiscoveredStream {
+                    source_ip,
+                    stream_name: header.stream_name.clone(),
+                    sample_rate: header.sample_rate(),
+                    channels: header.channels,
+                    data_type: header.data_type,
+                    codec,
+                    packets: 1,
+                },
+                packet_rate: 0.0,
+                lost: 0,
+                out_of_order: 0,
+                duplicates: 0,
+                jitter_ms: 0.0,
+                mismatches: Vec::new(),
+            },
+            first_arrival: at,
+            last_arrival: at,
+            last_counter: header.frame_counter,
+            last_duration: packet_duration(header),
+        }
+    }
+
+    fn record(&mut self, header: &VbanHeader, codec: Codec, at: Instant) {
+        let stats = &mut self.stats;
+        stats.stream.packets += 1;
+
+        let format_before = stats.stream.format_label();
+        stats.stream.sample_rate = header.sample_rate();
+        stats.stream.channels = header.channels;
+        stats.stream.data_type = header.data_type;
+        stats.stream.codec = codec;
+        let format_after = stats.stream.format_label();
+        if format_before != format_after {
+            add_mismatch(
+                &mut stats.mismatches,
+                format!("format changed from {format_before} to {format_after}"),
+            );
+        }
+
+        let delta = i64::from(header.frame_counter.wrapping_sub(self.last_counter) as i32) - 1;
+        if delta.abs() > RESTART_THRESHOLD {
+            self.last_counter = header.frame_counter;
+            self.last_arrival = at;
+            self.last_duration = packet_duration(header);
+            return;
+        }
+        if delta == -1 {
+            // Same counter as the last packet: sent twice, nothing was lost or reordered.
+            stats.duplicates += 1;
+            return;
+        }
+        if delta < 0 {
+            // A late packet that was counted as lost when the gap opened.
+            stats.out_of_order += 1;
+            stats.lost = stats.lost.saturating_sub(1);
+            return;
+        }
+        stats.lost += delta as u64;
+
+        if delta == 0 {
+            if let Some(expected) = self.last_duration {
+                let actual = at.duration_since(self.last_arrival).as_secs_f64();
+                let deviation_ms = (actual - expected).abs() * 1_000.0;
+                stats.jitter_ms += (deviation_ms - stats.jitter_ms) / 16.0;
+            }
+        }
+        self.last_counter = header.frame_counter;
+        self.last_arrival = at;
+        self.last_duration = packet_duration(header);
+    }
+
+    fn snapshot(&self, now: Instant) -> StreamStats {
+        let mut stats = self.stats.clone();
+        let elapsed = now.duration_since(self.first_arrival).as_secs_f64();
+        if elapsed > 0.0 {
+            stats.packet_rate = stats.stream.packets as f64 / elapsed;
+        }
+        stats
+    }
+}
+
+fn packet_duration(header: &VbanHeader) -> Option<f64> {
+    let rate = header.sample_rate()?;
+    Some(f64::from(header.samples_per_frame) / f64::from(rate))
+}
+
+fn add_mismatch(mismatches: &mut Vec<String>, mismatch: String) {
+    if !mismatches.contains(&mismatch) {
+        mismatches.push(mismatch);
+    }
+}
+
+/// Header fields that the configured recv would not accept.
+fn recv_mismatches(recv: &VbanRecv, source_ip: IpAddr, header: &VbanHeader) -> Vec<String> {
+    let mut mismatches = Vec::new();
+    if let Ok(expected) = recv.source_ip.trim().parse::<IpAddr>() {
+        if !expected.is_unspecified() && expected != source_ip {
+            mismatches.push(format!(
+                "source IP {source_ip} is not the configured {expected}"
+            ));
+        }
+    }
+    let stream_name = recv.stream_name.trim();
+    if !stream_name.is_empty() && stream_name != header.stream_name {
+        mismatches.push(format!(
+            "stream name `{}` is not the configured `{stream_name}`",
+            header.stream_name
+        ));
+    }
+    if let Some(codec) = header.codec().filter(|&codec| codec != Codec::Pcm) {
+        mismatches.push(format!(
+            "codec {codec:?} is not PCM, PipeWire cannot decode it"
+        ));
+    }
+    if header.data_type.sample_size().is_none() {
+        mismatches.push(format!(
+            "{} samples are not supported",
+            header.data_type.label()
+        ));
+    }
+    mismatches
+}
+
+/// Per-stream statistics for VBAN audio packets, keyed by (source IP, stream name).
+#[derive(Default)]
+struct MonitorState {
+    recv: Option<VbanRecv>,
+    trackers: Vec<Tracker>,
+}
+
+impl MonitorState {
+    fn record(&mut self, source_ip: IpAddr, packet: &[u8], at: Instant) {
+        let Ok(header) = VbanHeader::parse(packet) else {
+            return;
+        };
+        let Some(codec) = header.codec() else {
+            return;
+        };
+
+        let index = match self.trackers.iter().position(|t| {
+            t.stats.stream.source_ip == source_ip
+                && t.stats.stream.stream_name == header.stream_name
+        }) {
+            Some(index) => {
+                self.trackers[index].record(&header, codec, at);
+                index
+            }
+            None => {
+                self.trackers
+                    .push(Tracker::new(source_ip, &header, codec, at));
+                self.trackers.len() - 1
+            }
+        };
+
+        if let Some(recv) = &self.recv {
+            let mismatches = &mut self.trackers[index].stats.mismatches;
+            for mismatch in recv_mismatches(recv, source_ip, &header) {
+                add_mismatch(mismatches, mismatch);
+            }
+        }
+    }
+
+    fn snapshot(&self, now: Instant) -> Vec<StreamStats> {
+        let mut streams: Vec<_> = self.trackers.iter().map(|t| t.snapshot(now)).collect();
+        streams.sort_by(|a, b| {
+            a.stream
+                .source_ip
+                .cmp(&b.stream.source_ip)
+                .then_with(|| a.stream.stream_name.cmp(&b.stream.stream_name))
+        });
+        streams
+    }
+}
+
+fn monitor_packets(
+    socket: &UdpSocket,
+    state: &Mutex<MonitorState>,
+    stop: &AtomicBool,
+) -> Result<()> {
+    let mut buf = [0u8; MAX_PACKET_SIZE];
+    while !stop.load(Ordering::Relaxed) {
+        match socket.recv_from(&mut buf) {
+            Ok((len, addr)) => {
+                let at = Instant::now();
+                if let Ok(mut state) = state.lock() {
+                    state.record(addr.ip(), &buf[..len], at);
+                }
+            }
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(e) => return Err(e).context("Could not receive VBAN packet"),
+        }
+    }
+    Ok(())
+}
+
+/// Background listener collecting stream statistics on a mirror port, stopped when dropped.
+pub struct StreamMonitor {
+    port: u16,
+    recv_id: Option<uuid::Uuid>,
+    state: Arc<Mutex<MonitorState>>,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<Result<()>>>,
+}
+
+impl StreamMonitor {
+    /// Listens on `port`; when `recv` is given, headers are checked against it.
+    pub fn spawn(port: u16, recv: Option<VbanRecv>) -> Result<Self> {
+        let socket = UdpSocket::bind(("0.0.0.0", port)).with_context(|| {
+            format!("Could not listen on UDP port {port} (is a PipeWire vban-recv using it?)")
+        })?;
+        socket.set_read_timeout(Some(Duration::from_millis(200)))?;
+
+        let recv_id = recv.as_ref().map(|recv| recv.id);
+        let state = Arc::new(Mutex::new(MonitorState {
+            recv,
+            trackers: Vec::new(),
+        }));
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let state = Arc::clone(&state);
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || monitor_packets(&socket, &state, &stop))
+        };
+
+        Ok(Self {
+            port,
+            recv_id,
+            state,
+            stop,
+            handle: Some(handle),
+        })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn recv_id(&self) -> Option<uuid::Uuid> {
+        self.recv_id
+    }
+
+    pub fn snapshot(&self) -> Vec<StreamStats> {
+        self.state
+            .lock()
+            .map(|state| state.snapshot(Instant::now()))
+            .unwrap_or_default()
+    }
+
+    /// Returns the thread error once the monitor has stopped on its own.
+    pub fn take_error(&mut self) -> Option<anyhow::Error> {
+        if !self.handle.as_ref()?.is_finished() {
+            return None;
+        }
+        match self.handle.take()?.join() {
+            Ok(Ok(())) => None,
+            Ok(Err(e)) => Some(e),
+            Err(_) => Some(anyhow::anyhow!("monitor thread panicked")),
+        }
+    }
+}
+
+impl Drop for StreamMonitor {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vban::DataType;
+    use std::net::Ipv4Addr;
+
+    fn audio_packet(name: &str, counter: u32) -> Vec<u8> {
+        let mut header = VbanHeader::audio(name, 48_000, 2, 240, DataType::Int16).unwrap();
+        header.frame_counter = counter;
+        let mut packet = header.to_bytes().unwrap().to_vec();
+        packet.extend_from_slice(&[0; 240 * 2 * 2]);
+        packet
+    }
+
+    #[test]
+    fn counts_gaps_reordering_and_jitter() {
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let start = Instant::now();
+        let mut state = MonitorState::default();
+
+        // 240 samples at 48 kHz = one packet every 5 ms.
+        for (counter, ms) in [(0, 0), (1, 5), (3, 15), (2, 16), (4, 20), (5, 27)] {
+            state.record(
+                ip,
+                &audio_packet("Stream1", counter),
+                start + Duration::from_millis(ms),
+            );
+        }
+
+        let stats = state.snapshot(start + Duration::from_millis(30));
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].stream.packets, 6);
+        assert_eq!(stats[0].lost, 0);
+        assert_eq!(stats[0].out_of_order, 1);
+        assert!((stats[0].packet_rate - 200.0).abs() < 1e-6);
+        assert!(stats[0].jitter_ms > 0.0 && stats[0].jitter_ms < 1.0);
+        assert!(stats[0].mismatches.is_empty());
+    }
+
+    #[test]
+    fn counts_duplicates_without_hiding_loss() {
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let start = Instant::now();
+        let mut state = MonitorState::default();
+        for (counter, ms) in [(0, 0), (1, 5), (1, 6), (3, 15), (3, 16)] {
+            state.record(
+                ip,
+                &audio_packet("Stream1", counter),
+                start + Duration::from_millis(ms),
+            );
+        }
+
+        let stats = state.snapshot(start + Duration::from_millis(20));
+        assert_eq!(stats[0].lost, 1);
+        assert_eq!(stats[0].out_of_order, 0);
+        assert_eq!(stats[0].duplicates, 2);
+    }
+
+    #[test]
+    fn reports_headers_the_recv_would_reject() {
+        let mut state = MonitorState {
+            recv: Some(VbanRecv {
+                source_ip: "10.0.0.7".into(),
+                stream_name: "Desk".into(),
+                ..VbanRecv::default()
+            }),
+            ..MonitorState::default()
+        };
+        let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 8));
+        state.record(ip, &audio_packet("Mic1", 0), Instant::now());
+        state.record(ip, &audio_packet("Mic1", 1), Instant::now());
+
+        let stats = state.snapshot(Instant::now());
+        assert_eq!(
+            stats[0].mismatches,
+            vec![
+                "source IP 10.0.0.8 is not the configured 10.0.0.7".to_string(),
+                "stream name `Mic1` is not the configured `Desk`".to_string(),
+            ]
+        );
+    }
+}
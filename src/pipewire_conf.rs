@@ -1,3 +1,5 @@
+use anyhow::{bail, Context, Result};
+
 use crate::model::{HostInfoEmulation, VbanRecv, VbanSend};
 
 pub fn filename_send(id_simple: &str) -> String {
@@ -8,57 +10,456 @@ pub fn filename_recv(id_simple: &str) -> String {
     format!("99-rustban-recv-{id_simple}.conf")
 }
 
-pub fn render_send(s: &VbanSend, emulation: &HostInfoEmulation) -> String {
-    let target_object = s.target_object.trim();
-    let target_object_line = if target_object.is_empty() {
-        String::new()
+/// A value in PipeWire's SPA-JSON config dialect.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpaValue {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    Array(Vec<SpaValue>),
+    Object(SpaObject),
+}
+
+/// Ordered `key = value` pairs, rendered as `{ ... }`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SpaObject(Vec<(String, SpaValue)>);
+
+impl SpaObject {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, key: &str, value: impl Into<SpaValue>) -> Self {
+        self.push(key, value);
+        self
+    }
+
+    pub fn push(&mut self, key: &str, value: impl Into<SpaValue>) {
+        self.0.push((key.to_string(), value.into()));
+    }
+
+    pub fn get(&self, key: &str) -> Option<&SpaValue> {
+        self.0
+            .iter()
+            .find(|(entry_key, _)| entry_key == key)
+            .map(|(_, value)| value)
+    }
+
+    /// Adds a trimmed string property, skipping it when empty.
+    pub fn push_non_empty(&mut self, key: &str, value: &str) {
+        let value = value.trim();
+        if !value.is_empty() {
+            self.push(key, value);
+        }
+    }
+}
+
+impl From<bool> for SpaValue {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<&str> for SpaValue {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+impl From<String> for SpaValue {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+macro_rules! spa_int_from {
+    ($($ty:ty),*) => {
+        $(impl From<$ty> for SpaValue {
+            fn from(value: $ty) -> Self {
+                Self::Int(i64::from(value))
+            }
+        })*
+    };
+}
+
+spa_int_from!(u8, u16, u32, i64);
+
+impl From<SpaObject> for SpaValue {
+    fn from(value: SpaObject) -> Self {
+        Self::Object(value)
+    }
+}
+
+impl From<Vec<SpaValue>> for SpaValue {
+    fn from(value: Vec<SpaValue>) -> Self {
+        Self::Array(value)
+    }
+}
+
+impl SpaValue {
+    /// Strings and bare words; numbers and booleans are given back as text too.
+    pub fn as_text(&self) -> Option<String> {
+        match self {
+            Self::String(value) => Some(value.clone()),
+            Self::Bool(value) => Some(value.to_string()),
+            Self::Int(value) => Some(value.to_string()),
+            Self::Float(value) => Some(value.to_string()),
+            Self::Null | Self::Array(_) | Self::Object(_) => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Self::Int(value) => Some(*value),
+            Self::String(value) => value.trim().parse().ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(value) => Some(*value),
+            Self::Int(value) => Some(*value != 0),
+            Self::String(value) => match value.trim() {
+                "true" => Some(true),
+                "false" => Some(false),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&SpaObject> {
+        match self {
+            Self::Object(object) => Some(object),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[SpaValue]> {
+        match self {
+            Self::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+const INDENT: &str = "  ";
+
+impl SpaValue {
+    fn write(&self, out: &mut String, depth: usize) {
+        match self {
+            Self::Null => out.push_str("null"),
+            Self::Bool(value) => out.push_str(if *value { "true" } else { "false" }),
+            Self::Int(value) => out.push_str(&value.to_string()),
+            Self::Float(value) => out.push_str(&format!("{value:?}")),
+            Self::String(value) => write_string(out, value),
+            Self::Array(items) => {
+                if items.is_empty() {
+                    out.push_str("[ ]");
+                    return;
+                }
+                out.push_str("[\n");
+                for item in items {
+                    out.push_str(&INDENT.repeat(depth + 1));
+                    item.write(out, depth + 1);
+                    out.push('\n');
+                }
+                out.push_str(&INDENT.repeat(depth));
+                out.push(']');
+            }
+            Self::Object(object) => {
+                if object.0.is_empty() {
+                    out.push_str("{ }");
+                    return;
+                }
+                out.push_str("{\n");
+                object.write_entries(out, depth + 1);
+                out.push_str(&INDENT.repeat(depth));
+                out.push('}');
+            }
+        }
+    }
+}
+
+impl SpaObject {
+    fn write_entries(&self, out: &mut String, depth: usize) {
+        for (key, value) in &self.0 {
+            out.push_str(&INDENT.repeat(depth));
+            write_key(out, key);
+            out.push_str(" = ");
+            value.write(out, depth);
+            out.push('\n');
+        }
+    }
+}
+
+/// Bare keys cannot contain whitespace or SPA-JSON structural characters.
+fn write_key(out: &mut String, key: &str) {
+    let bare = !key.is_empty()
+        && !key
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || "{}[]\"=:,#\\".contains(c));
+    if bare {
+        out.push_str(key);
     } else {
-        format!(
-            "        target.object = \"{}\"\n",
-            escape_str(target_object)
-        )
+        write_string(out, key);
+    }
+}
+
+fn write_string(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Characters that end a bare (unquoted) word.
+const BARE_WORD_END: &str = "{}[]=:,\"#";
+
+struct SpaParser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl SpaParser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn line(&self) -> usize {
+        self.text[..self.pos].matches('\n').count() + 1
+    }
+
+    /// Skips whitespace, comments and the optional `,` separators.
+    fn skip_blank(&mut self) {
+        while let Some(c) = self.peek() {
+            if c == '#' {
+                while self.peek().is_some_and(|c| c != '\n') {
+                    self.bump();
+                }
+            } else if c.is_whitespace() || c == ',' {
+                self.bump();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn parse_entries(&mut self, close: Option<char>) -> Result<SpaObject> {
+        let mut object = SpaObject::new();
+        loop {
+            self.skip_blank();
+            match self.peek() {
+                None if close.is_none() => return Ok(object),
+                None => bail!("Unterminated object at end of file"),
+                Some(c) if Some(c) == close => {
+                    self.bump();
+                    return Ok(object);
+                }
+                Some(_) => {}
+            }
+
+            let key = match self.peek() {
+                Some('"') => self.parse_string()?,
+                _ => self.parse_bare_word()?,
+            };
+            self.skip_blank();
+            if matches!(self.peek(), Some('=' | ':')) {
+                self.bump();
+            }
+            self.skip_blank();
+            let value = self.parse_value()?;
+            object.0.push((key, value));
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<SpaValue> {
+        match self.peek() {
+            Some('{') => {
+                self.bump();
+                Ok(SpaValue::Object(self.parse_entries(Some('}'))?))
+            }
+            Some('[') => {
+                self.bump();
+                let mut items = Vec::new();
+                loop {
+                    self.skip_blank();
+                    match self.peek() {
+                        Some(']') => {
+                            self.bump();
+                            return Ok(SpaValue::Array(items));
+                        }
+                        None => bail!("Unterminated array at end of file"),
+                        Some(_) => items.push(self.parse_value()?),
+                    }
+                }
+            }
+            Some('"') => Ok(SpaValue::String(self.parse_string()?)),
+            _ => {
+                let word = self.parse_bare_word()?;
+                Ok(match word.as_str() {
+                    "null" => SpaValue::Null,
+                    "true" => SpaValue::Bool(true),
+                    "false" => SpaValue::Bool(false),
+                    _ => match (word.parse::<i64>(), word.parse::<f64>()) {
+                        (Ok(value), _) => SpaValue::Int(value),
+                        (_, Ok(value)) if !word.chars().any(char::is_alphabetic) => {
+                            SpaValue::Float(value)
+                        }
+                        _ => SpaValue::String(word),
+                    },
+                })
+            }
+        }
+    }
+
+    fn parse_bare_word(&mut self) -> Result<String> {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| !c.is_whitespace() && !BARE_WORD_END.contains(c))
+        {
+            self.bump();
+        }
+        if self.pos == start {
+            match self.peek() {
+                Some(c) => bail!("Unexpected `{c}` on line {}", self.line()),
+                None => bail!("Unexpected end of file"),
+            }
+        }
+        Ok(self.text[start..self.pos].to_string())
+    }
+
+    fn parse_string(&mut self) -> Result<String> {
+        let line = self.line();
+        self.bump();
+        let mut out = String::new();
+        loop {
+            match self.bump() {
+                None => bail!("Unterminated string starting on line {line}"),
+                Some('"') => return Ok(out),
+                Some('\\') => match self.bump() {
+                    Some('n') => out.push('\n'),
+                    Some('r') => out.push('\r'),
+                    Some('t') => out.push('\t'),
+                    Some('b') => out.push('\u{8}'),
+                    Some('f') => out.push('\u{c}'),
+                    Some('u') => {
+                        let hex = self.text.get(self.pos..self.pos + 4).unwrap_or_default();
+                        let c = u32::from_str_radix(hex, 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .with_context(|| format!("Bad \\u escape on line {}", self.line()))?;
+                        self.pos += 4;
+                        out.push(c);
+                    }
+                    Some(c) => out.push(c),
+                    None => bail!("Unterminated string starting on line {line}"),
+                },
+                Some(c) => out.push(c),
+            }
+        }
+    }
+}
+
+/// Parses a config file; the root object may be written with or without braces.
+pub fn parse_document(text: &str) -> Result<SpaObject> {
+    let mut parser = SpaParser { text, pos: 0 };
+    parser.skip_blank();
+    let root = if parser.peek() == Some('{') {
+        parser.bump();
+        parser.parse_entries(Some('}'))?
+    } else {
+        parser.parse_entries(None)?
     };
-    let host_info_lines = render_host_info_lines(emulation, &s.sess_name);
+    parser.skip_blank();
+    if let Some(c) = parser.peek() {
+        bail!(
+            "Unexpected `{c}` after the document on line {}",
+            parser.line()
+        );
+    }
+    Ok(root)
+}
 
-    format!(
-        r#"# Generated by RustBAN
-context.modules = [
-  {{ name = libpipewire-module-vban-send
-    args = {{
-      destination.ip = "{dest_ip}"
-      destination.port = {dest_port}
-      sess.name = "{sess_name}"
-      sess.media = "{sess_media}"
-
-      audio.format = "{fmt}"
-      audio.rate = {rate}
-      audio.channels = {ch}
-
-      stream.props = {{
-        node.name = "{node_name}"
-        node.description = "{node_desc}"
-        node.always-process = {always_process}
-        media.class = "Audio/Sink"
-        media.name = "{media_name}"
-{host_info_lines}{target_object_line}      }}
-    }}
-  }}
-]
-"#,
-        dest_ip = s.destination_ip,
-        dest_port = s.destination_port,
-        sess_name = escape_str(&s.sess_name),
-        sess_media = escape_str(&s.sess_media),
-        fmt = escape_str(&s.audio_format),
-        rate = s.audio_rate,
-        ch = s.audio_channels,
-        node_name = escape_str(&s.node_name),
-        node_desc = escape_str(&s.node_description),
-        always_process = if s.always_process { "true" } else { "false" },
-        media_name = escape_str(&s.sess_name),
-        host_info_lines = host_info_lines,
-        target_object_line = target_object_line,
-    )
+/// Renders a top-level config fragment (the root object has no braces).
+pub fn render_document(root: &SpaObject) -> String {
+    let mut out = String::from("# Generated by RustBAN\n");
+    root.write_entries(&mut out, 0);
+    out
+}
+
+fn module_document(name: &str, args: SpaObject) -> String {
+    let module = SpaObject::new().with("name", name).with("args", args);
+    render_document(&SpaObject::new().with("context.modules", vec![module.into()]))
+}
+
+/// Standalone `pipewire -c` config that runs the modules of `fragment` as a client of the
+/// running daemon, the way `filter-chain.conf` does.
+pub fn render_live_host(fragment: &str) -> Result<String> {
+    let root = parse_document(fragment)?;
+    let mut modules: Vec<SpaValue> = [
+        "libpipewire-module-protocol-native",
+        "libpipewire-module-client-node",
+        "libpipewire-module-adapter",
+    ]
+    .into_iter()
+    .map(|name| SpaObject::new().with("name", name).into())
+    .collect();
+    modules.extend_from_slice(
+        root.get("context.modules")
+            .and_then(SpaValue::as_array)
+            .context("Fragment has no context.modules")?,
+    );
+
+    let spa_libs = SpaObject::new()
+        .with("audio.convert.*", "audioconvert/libspa-audioconvert")
+        .with("support.*", "support/libspa-support");
+    Ok(render_document(
+        &SpaObject::new()
+            .with("context.spa-libs", spa_libs)
+            .with("context.modules", modules),
+    ))
+}
+
+pub fn render_send(s: &VbanSend, emulation: &HostInfoEmulation) -> String {
+    let mut props = SpaObject::new()
+        .with("node.name", s.node_name.trim())
+        .with("node.description", s.node_description.as_str())
+        .with("node.always-process", s.always_process)
+        .with("media.class", "Audio/Sink")
+        .with("media.name", s.sess_name.as_str());
+    push_host_info_props(&mut props, emulation, &s.sess_name);
+    props.push_non_empty("target.object", &s.target_object);
+
+    let args = SpaObject::new()
+        .with("destination.ip", s.destination_ip.trim())
+        .with("destination.port", s.destination_port)
+        .with("sess.name", s.sess_name.as_str())
+        .with("sess.media", s.sess_media.as_str())
+        .with("audio.format", s.audio_format.as_str())
+        .with("audio.rate", s.audio_rate)
+        .with("audio.channels", s.audio_channels)
+        .with("stream.props", props);
+    module_document("libpipewire-module-vban-send", args)
 }
 
 pub fn render_recv(r: &VbanRecv, emulation: &HostInfoEmulation) -> String {
@@ -73,63 +474,46 @@ pub fn render_recv(r: &VbanRecv, emulation: &HostInfoEmulation) -> String {
     } else {
         stream_name
     };
-    let host_info_lines = render_host_info_lines(emulation, media_name);
 
-    format!(
-        r#"# Generated by RustBAN
-context.modules = [
-  {{ name = libpipewire-module-vban-recv
-    args = {{
-      source.ip = "{src_ip}"
-      source.port = {src_port}
-      node.always-process = {always_process}
-      sess.latency.msec = {latency}
-
-      stream.rules = [
-        {{
-          matches = [
-            {{ sess.name = "{sess_match}" }}
-          ]
-          actions = {{
-            create-stream = {{
-              stream.props = {{
-                node.name = "{node_name}"
-                node.description = "{node_desc}"
-                media.class = "Audio/Source"
-                media.name = "{media_name}"
-{host_info_lines}
-              }}
-            }}
-          }}
-        }}
-      ]
-    }}
-  }}
-]
-"#,
-        src_ip = r.source_ip,
-        src_port = r.source_port,
-        always_process = if r.always_process { "true" } else { "false" },
-        latency = r.latency_msec,
-        sess_match = escape_str(sess_match),
-        node_name = escape_str(&r.node_name),
-        node_desc = escape_str(&r.node_description),
-        media_name = escape_str(media_name),
-        host_info_lines = host_info_lines,
-    )
-}
-
-fn render_host_info_lines(emulation: &HostInfoEmulation, stream_label: &str) -> String {
+    let mut props = SpaObject::new()
+        .with("node.name", r.node_name.trim())
+        .with("node.description", r.node_description.as_str())
+        .with("media.class", "Audio/Source")
+        .with("media.name", media_name);
+    push_host_info_props(&mut props, emulation, media_name);
+    props.push_non_empty("target.object", &r.target_sink);
+
+    let rule = SpaObject::new()
+        .with(
+            "matches",
+            vec![SpaObject::new().with("sess.name", sess_match).into()],
+        )
+        .with(
+            "actions",
+            SpaObject::new().with(
+                "create-stream",
+                SpaObject::new().with("stream.props", props),
+            ),
+        );
+    let args = SpaObject::new()
+        .with("source.ip", r.source_ip.trim())
+        .with("source.port", r.source_port)
+        .with("node.always-process", r.always_process)
+        .with("sess.latency.msec", r.latency_msec)
+        .with("stream.rules", vec![rule.into()]);
+    module_document("libpipewire-module-vban-recv", args)
+}
+
+fn push_host_info_props(props: &mut SpaObject, emulation: &HostInfoEmulation, stream_label: &str) {
     if !emulation.enabled {
-        return String::new();
+        return;
     }
 
-    let mut out = String::new();
-    push_prop_line(&mut out, "application.name", &emulation.app_name);
-    push_prop_line(&mut out, "application.process.binary", &emulation.app_name);
-    push_prop_line(&mut out, "application.process.host", &emulation.host_name);
-    push_prop_line(&mut out, "application.process.user", &emulation.user_name);
-    push_prop_line(&mut out, "client.name", &emulation.client_name);
+    props.push_non_empty("application.name", &emulation.app_name);
+    props.push_non_empty("application.process.binary", &emulation.app_name);
+    props.push_non_empty("application.process.host", &emulation.host_name);
+    props.push_non_empty("application.process.user", &emulation.user_name);
+    props.push_non_empty("client.name", &emulation.client_name);
 
     let stream_label = stream_label.trim();
     let host_name = emulation.host_name.trim();
@@ -140,19 +524,121 @@ fn render_host_info_lines(emulation: &HostInfoEmulation, stream_label: &str) ->
     } else {
         host_name.to_string()
     };
-    push_prop_line(&mut out, "node.nick", &node_nick);
-
-    out
+    props.push_non_empty("node.nick", &node_nick);
 }
 
-fn push_prop_line(out: &mut String, key: &str, value: &str) {
-    let value = value.trim();
-    if value.is_empty() {
-        return;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn send_fragment_quotes_every_string() {
+        let send = VbanSend {
+            destination_ip: "10.0.0.5\" bad".into(),
+            sess_name: "Mic \\ 1".into(),
+            node_name: " vban-send-test ".into(),
+            target_object: "alsa_input.usb".into(),
+            ..VbanSend::default()
+        };
+
+        assert_eq!(
+            render_send(&send, &HostInfoEmulation::default()),
+            r#"# Generated by RustBAN
+context.modules = [
+  {
+    name = "libpipewire-module-vban-send"
+    args = {
+      destination.ip = "10.0.0.5\" bad"
+      destination.port = 6980
+      sess.name = "Mic \\ 1"
+      sess.media = "audio"
+      audio.format = "S16LE"
+      audio.rate = 48000
+      audio.channels = 2
+      stream.props = {
+        node.name = "vban-send-test"
+        node.description = "VBAN Send"
+        node.always-process = false
+        media.class = "Audio/Sink"
+        media.name = "Mic \\ 1"
+        target.object = "alsa_input.usb"
+      }
     }
-    out.push_str(&format!("        {key} = \"{}\"\n", escape_str(value)));
-}
+  }
+]
+"#
+        );
+    }
+
+    #[test]
+    fn writer_escapes_keys_and_control_characters() {
+        let root = SpaObject::new()
+            .with("plain.key", "tab\there\nnew\u{1}")
+            .with("needs quotes", SpaObject::new())
+            .with("list", Vec::<SpaValue>::new());
+
+        assert_eq!(
+            render_document(&root),
+            "# Generated by RustBAN\n\
+             plain.key = \"tab\\there\\nnew\\u0001\"\n\
+             \"needs quotes\" = { }\n\
+             list = [ ]\n"
+        );
+    }
+
+    #[test]
+    fn parser_reads_hand_written_fragments() {
+        let root = parse_document(
+            r#"
+# old mic send
+context.modules = [
+    {   name = libpipewire-module-vban-send   # trailing comment
+        args = {
+            destination.ip = 192.168.1.20
+            destination.port = 6980,
+            "sess.name": "Mic \"A\""
+            audio.rate = 48000
+            latency = 0.5
+            flags = [ ifexists nofail ]
+        }
+    }
+]
+"#,
+        )
+        .unwrap();
+
+        let modules = root.get("context.modules").unwrap().as_array().unwrap();
+        let module = modules[0].as_object().unwrap();
+        let args = module.get("args").unwrap().as_object().unwrap();
+        assert_eq!(
+            module.get("name").unwrap().as_text().unwrap(),
+            "libpipewire-module-vban-send"
+        );
+        assert_eq!(
+            args.get("destination.ip").unwrap().as_text().unwrap(),
+            "192.168.1.20"
+        );
+        assert_eq!(args.get("destination.port").unwrap().as_int(), Some(6980));
+        assert_eq!(
+            args.get("sess.name").unwrap().as_text().unwrap(),
+            "Mic \"A\""
+        );
+        assert_eq!(args.get("latency"), Some(&SpaValue::Float(0.5)));
+        assert_eq!(args.get("flags").unwrap().as_array().unwrap().len(), 2);
 
-fn escape_str(s: &str) -> String {
-    s.replace('\\', "\\\\").replace('"', "\\\"")
+        assert!(parse_document("a = { b = 1").is_err());
+        assert!(parse_document("a = \"open").is_err());
+    }
+
+    #[test]
+    fn rendered_fragments_parse_back() {
+        let send = VbanSend {
+            sess_name: "tab\tand \"quotes\"".into(),
+            ..VbanSend::default()
+        };
+        let rendered = render_send(&send, &HostInfoEmulation::default());
+        let root = parse_document(&rendered).unwrap();
+
+        assert_eq!(render_document(&root), rendered);
+    }
 }
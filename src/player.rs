@@ -0,0 +1,219 @@
+use anyhow::{bail, Result};
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+};
+
+use crate::{
+    model::VbanSend,
+    stream_out::{samples_per_frame, AudioStreamOut},
+    vban::{DataType, VbanHeader, STREAM_NAME_SIZE},
+    wav::{WavFormat, WavReader},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaySettings {
+    pub path: PathBuf,
+    pub destination_ip: String,
+    pub destination_port: u16,
+    pub stream_name: String,
+    /// PipeWire-style format name, e.g. `S16LE`.
+    pub audio_format: String,
+    pub audio_rate: u32,
+    pub audio_channels: u16,
+    pub looping: bool,
+}
+
+impl PlaySettings {
+    /// Sends the file the way `send` would: same destination, stream name and format.
+    pub fn from_send(send: &VbanSend, path: PathBuf, looping: bool) -> Self {
+        Self {
+            path,
+            destination_ip: send.destination_ip.clone(),
+            destination_port: send.destination_port,
+            stream_name: truncate_stream_name(send.sess_name.trim()),
+            audio_format: send.audio_format.clone(),
+            audio_rate: send.audio_rate,
+            audio_channels: u16::from(send.audio_channels),
+            looping,
+        }
+    }
+}
+
+/// `sess.name` can be longer than the 16-byte VBAN stream name field, which is ASCII only;
+/// other characters are dropped.
+fn truncate_stream_name(name: &str) -> String {
+    name.chars()
+        .filter(char::is_ascii)
+        .take(STREAM_NAME_SIZE)
+        .collect()
+}
+
+/// Re-encodes interleaved frames to `to`; extra output channels repeat the input ones.
+fn convert_frames(input: &[u8], from: WavFormat, to: WavFormat, out: &mut Vec<u8>) -> Result<()> {
+    out.clear();
+    if from.data_type == to.data_type && from.channels == to.channels {
+        out.extend_from_slice(input);
+        return Ok(());
+    }
+
+    let sample_size = from.data_type.sample_size().unwrap_or(1);
+    for frame in input.chunks_exact(from.frame_size()) {
+        for channel in 0..usize::from(to.channels) {
+            let offset = (channel % usize::from(from.channels)) * sample_size;
+            let sample = from.data_type.decode_sample(&frame[offset..])?;
+            to.data_type.encode_sample(sample, out)?;
+        }
+    }
+    Ok(())
+}
+
+/// Streams the WAV file until it ends (or forever when looping) or `stop` is set.
+pub fn play_file(settings: &PlaySettings, stop: &AtomicBool, packets: &AtomicU32) -> Result<()> {
+    let Some(data_type) = DataType::from_audio_format(&settings.audio_format) else {
+        bail!("Unsupported audio format `{}`", settings.audio_format);
+    };
+    let mut reader = WavReader::open(&settings.path)?;
+    let from = reader.format();
+    let to = WavFormat {
+        rate: settings.audio_rate,
+        channels: settings.audio_channels,
+        data_type,
+    };
+    if from.rate != to.rate {
+        bail!(
+            "`{}` is {} Hz but the stream is {} Hz",
+            settings.path.display(),
+            from.rate,
+            to.rate
+        );
+    }
+
+    let samples = samples_per_frame(to.channels, data_type)?;
+    let header = VbanHeader::audio(
+        &settings.stream_name,
+        to.rate,
+        to.channels,
+        samples,
+        data_type,
+    )?;
+    let mut out =
+        AudioStreamOut::connect(&settings.destination_ip, settings.destination_port, header)?;
+
+    let mut frames = Vec::new();
+    let mut payload = Vec::new();
+    while !stop.load(Ordering::Relaxed) {
+        if reader.read_frames(usize::from(samples), &mut frames)? == 0 {
+            if !settings.looping || out.frames_sent() == 0 {
+                break;
+            }
+            reader.rewind()?;
+            continue;
+        }
+        convert_frames(&frames, from, to, &mut payload)?;
+        out.send(&payload)?;
+        packets.store(out.frames_sent(), Ordering::Relaxed);
+    }
+    Ok(())
+}
+
+/// Background file playback, stopped when dropped.
+pub struct FilePlayer {
+    settings: PlaySettings,
+    stop: Arc<AtomicBool>,
+    packets: Arc<AtomicU32>,
+    handle: Option<JoinHandle<Result<()>>>,
+}
+
+impl FilePlayer {
+    pub fn spawn(settings: PlaySettings) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let packets = Arc::new(AtomicU32::new(0));
+        let handle = {
+            let settings = settings.clone();
+            let stop = Arc::clone(&stop);
+            let packets = Arc::clone(&packets);
+            thread::spawn(move || play_file(&settings, &stop, &packets))
+        };
+
+        Self {
+            settings,
+            stop,
+            packets,
+            handle: Some(handle),
+        }
+    }
+
+    pub fn settings(&self) -> &PlaySettings {
+        &self.settings
+    }
+
+    pub fn packets_sent(&self) -> u32 {
+        self.packets.load(Ordering::Relaxed)
+    }
+
+    /// Returns the outcome once playback has ended on its own.
+    pub fn take_result(&mut self) -> Option<Result<()>> {
+        if !self.handle.as_ref()?.is_finished() {
+            return None;
+        }
+        Some(
+            self.handle
+                .take()?
+                .join()
+                .unwrap_or_else(|_| Err(anyhow::anyhow!("player thread panicked"))),
+        )
+    }
+}
+
+impl Drop for FilePlayer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vban::encode_stream_name;
+
+    #[test]
+    fn converts_mono_16_bit_to_stereo_float() {
+        let from = WavFormat {
+            rate: 48_000,
+            channels: 1,
+            data_type: DataType::Int16,
+        };
+        let to = WavFormat {
+            channels: 2,
+            data_type: DataType::Float32,
+            ..from
+        };
+        let mut out = Vec::new();
+        convert_frames(&[0x00, 0x40, 0x00, 0xC0], from, to, &mut out).unwrap();
+
+        let samples: Vec<f32> = out
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+        assert_eq!(samples, [0.5, 0.5, -0.5, -0.5]);
+    }
+
+    #[test]
+    fn stream_name_is_cut_to_the_vban_field() {
+        let send = VbanSend::default();
+        let settings = PlaySettings::from_send(&send, "test.wav".into(), false);
+
+        assert_eq!(settings.stream_name, "PipeWire VBAN st");
+        let name = truncate_stream_name("Küche Mikrofon 1234");
+        assert_eq!(name, "Kche Mikrofon 12");
+        assert!(encode_stream_name(&name).is_ok());
+    }
+}
@@ -0,0 +1,268 @@
+use anyhow::{bail, Context, Result};
+use std::{
+    io::ErrorKind,
+    net::UdpSocket,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    vban::{SubProtocol, VbanHeader, HEADER_SIZE, MAX_PACKET_SIZE},
+    wav::{WavFormat, WavWriter, MAX_WAV_DATA},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordSettings {
+    pub port: u16,
+    pub stream_name: String,
+    pub path: PathBuf,
+    pub max_seconds: Option<u64>,
+    pub max_bytes: Option<u64>,
+    /// Ends the recording once no audio of the stream was written for this long, counted from
+    /// the first packet (before it, the recording waits until stopped).
+    pub idle_timeout: Option<Duration>,
+}
+
+impl Default for RecordSettings {
+    fn default() -> Self {
+        Self {
+            port: 6980,
+            stream_name: "Stream1".into(),
+            path: directories::UserDirs::new()
+                .map(|dirs| {
+                    dirs.audio_dir()
+                        .unwrap_or(dirs.home_dir())
+                        .join("rustban-capture.wav")
+                })
+                .unwrap_or_else(|| PathBuf::from("rustban-capture.wav")),
+            max_seconds: Some(60),
+            max_bytes: None,
+            idle_timeout: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordSummary {
+    pub path: PathBuf,
+    pub format: String,
+    pub data_bytes: u64,
+    pub seconds: f64,
+    /// Packets of the stream that were not written (format change or non-PCM codec).
+    pub skipped: u64,
+}
+
+/// Largest number of data bytes the limits allow, rounded down to whole frames.
+fn data_limit(settings: &RecordSettings, format: WavFormat) -> u64 {
+    let frame_size = format.frame_size() as u64;
+    let mut limit = MAX_WAV_DATA;
+    if let Some(seconds) = settings.max_seconds {
+        limit = limit.min(seconds * u64::from(format.rate) * frame_size);
+    }
+    if let Some(bytes) = settings.max_bytes {
+        limit = limit.min(bytes);
+    }
+    limit - limit % frame_size
+}
+
+/// Writes the audio of `settings.stream_name` received on `settings.port` to a WAV file.
+pub fn record_stream(
+    settings: &RecordSettings,
+    stop: &AtomicBool,
+    progress: &AtomicU64,
+) -> Result<RecordSummary> {
+    let stream_name = settings.stream_name.trim();
+    if stream_name.is_empty() {
+        bail!("Stream name is empty");
+    }
+    let socket = UdpSocket::bind(("0.0.0.0", settings.port)).with_context(|| {
+        format!(
+            "Could not listen on UDP port {} (is a PipeWire vban-recv using it?)",
+            settings.port
+        )
+    })?;
+    socket.set_read_timeout(Some(Duration::from_millis(200)))?;
+
+    let mut wav: Option<(WavWriter, u64)> = None;
+    let mut skipped = 0;
+    let mut buf = [0u8; MAX_PACKET_SIZE];
+    let mut last_audio: Option<Instant> = None;
+
+    while !stop.load(Ordering::Relaxed) {
+        if let (Some(timeout), Some(at)) = (settings.idle_timeout, last_audio) {
+            if at.elapsed() >= timeout {
+                break;
+            }
+        }
+        let len = match socket.recv_from(&mut buf) {
+            Ok((len, _)) => len,
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => continue,
+            Err(e) => return Err(e).context("Could not receive VBAN packet"),
+        };
+        let Ok(header) = VbanHeader::parse(&buf[..len]) else {
+            continue;
+        };
+        if header.sub_protocol != SubProtocol::Audio || header.stream_name != stream_name {
+            continue;
+        }
+        let Ok(format) = WavFormat::from_header(&header) else {
+            skipped += 1;
+            continue;
+        };
+
+        let (file, limit) = match &mut wav {
+            Some((file, _)) if file.format() != format => {
+                skipped += 1;
+                continue;
+            }
+            Some((file, limit)) => (file, *limit),
+            None => {
+                let file = WavWriter::create(&settings.path, format)?;
+                let (file, limit) = wav.insert((file, data_limit(settings, format)));
+                (file, *limit)
+            }
+        };
+
+        let payload = &buf[HEADER_SIZE..len];
+        let payload = &payload[..payload.len() - payload.len() % format.frame_size()];
+        let room = (limit - file.data_bytes()).min(payload.len() as u64) as usize;
+        file.append(&payload[..room])?;
+        last_audio = Some(Instant::now());
+        progress.store(file.data_bytes(), Ordering::Relaxed);
+        if file.data_bytes() >= limit {
+            break;
+        }
+    }
+
+    let Some((file, _)) = wav else {
+        bail!("No VBAN packet for stream `{stream_name}` was received");
+    };
+    let format = file.format();
+    let data_bytes = file.finish()?;
+    Ok(RecordSummary {
+        path: settings.path.clone(),
+        format: format.label(),
+        data_bytes,
+        seconds: data_bytes as f64 / (format.frame_size() as f64 * f64::from(format.rate)),
+        skipped,
+    })
+}
+
+/// Background recording, finalized when stopped or dropped.
+pub struct Recorder {
+    settings: RecordSettings,
+    stop: Arc<AtomicBool>,
+    progress: Arc<AtomicU64>,
+    handle: Option<JoinHandle<Result<RecordSummary>>>,
+}
+
+impl Recorder {
+    pub fn spawn(settings: RecordSettings) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let progress = Arc::new(AtomicU64::new(0));
+        let handle = {
+            let settings = settings.clone();
+            let stop = Arc::clone(&stop);
+            let progress = Arc::clone(&progress);
+            thread::spawn(move || record_stream(&settings, &stop, &progress))
+        };
+
+        Self {
+            settings,
+            stop,
+            progress,
+            handle: Some(handle),
+        }
+    }
+
+    pub fn settings(&self) -> &RecordSettings {
+        &self.settings
+    }
+
+    pub fn bytes_written(&self) -> u64 {
+        self.progress.load(Ordering::Relaxed)
+    }
+
+    /// Returns the outcome once the recording has ended on its own (limit reached or error).
+    pub fn take_result(&mut self) -> Option<Result<RecordSummary>> {
+        if !self.handle.as_ref()?.is_finished() {
+            return None;
+        }
+        Some(self.join())
+    }
+
+    /// Stops the recording and waits for the WAV file to be finalized.
+    pub fn finish(mut self) -> Result<RecordSummary> {
+        self.stop.store(true, Ordering::Relaxed);
+        self.join()
+    }
+
+    fn join(&mut self) -> Result<RecordSummary> {
+        match self.handle.take() {
+            Some(handle) => handle
+                .join()
+                .unwrap_or_else(|_| Err(anyhow::anyhow!("recorder thread panicked"))),
+            None => bail!("Recording already finished"),
+        }
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vban::DataType;
+
+    #[test]
+    fn limits_round_down_to_whole_frames() {
+        let format = WavFormat {
+            rate: 48_000,
+            channels: 2,
+            data_type: DataType::Int16,
+        };
+        let mut settings = RecordSettings {
+            max_seconds: Some(2),
+            max_bytes: None,
+            ..RecordSettings::default()
+        };
+        assert_eq!(data_limit(&settings, format), 2 * 48_000 * 4);
+
+        settings.max_bytes = Some(1_001);
+        assert_eq!(data_limit(&settings, format), 1_000);
+
+        settings.max_seconds = None;
+        settings.max_bytes = None;
+        assert_eq!(
+            data_limit(&settings, format),
+            MAX_WAV_DATA - MAX_WAV_DATA % 4
+        );
+    }
+
+    #[test]
+    fn idle_timeout_waits_for_the_first_packet() {
+        let mut recorder = Recorder::spawn(RecordSettings {
+            port: 0,
+            path: std::env::temp_dir().join("rustban-idle-test.wav"),
+            idle_timeout: Some(Duration::from_millis(50)),
+            ..RecordSettings::default()
+        });
+        thread::sleep(Duration::from_millis(400));
+        assert!(recorder.take_result().is_none());
+
+        let error = recorder.finish().unwrap_err().to_string();
+        assert!(error.starts_with("No VBAN packet"), "{error}");
+    }
+}
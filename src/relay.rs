@@ -0,0 +1,306 @@
+use anyhow::{bail, Context, Result};
+use std::{
+    io::ErrorKind,
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::{
+    model::VbanRelay,
+    vban::{encode_stream_name, VbanHeader, MAX_PACKET_SIZE, STREAM_NAME_SIZE},
+};
+
+/// Offset of the stream name in the VBAN header (after magic and the four format bytes).
+const STREAM_NAME_OFFSET: usize = 8;
+
+#[derive(Debug, Default)]
+struct DestinationCounters {
+    packets: AtomicU64,
+    bytes: AtomicU64,
+    errors: AtomicU64,
+}
+
+struct Destination {
+    label: String,
+    addr: SocketAddr,
+    counters: DestinationCounters,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DestinationStats {
+    pub label: String,
+    pub packets: u64,
+    pub bytes: u64,
+    pub errors: u64,
+}
+
+/// Which packets a relay forwards and how it rewrites them.
+struct RelayFilter {
+    stream_filter: Option<String>,
+    rename: Option<[u8; STREAM_NAME_SIZE]>,
+}
+
+impl RelayFilter {
+    fn new(relay: &VbanRelay) -> Result<Self> {
+        let stream_filter = relay.stream_filter.trim();
+        let rename = relay.rename.trim();
+        Ok(Self {
+            stream_filter: (!stream_filter.is_empty()).then(|| stream_filter.to_string()),
+            rename: if rename.is_empty() {
+                None
+            } else {
+                Some(encode_stream_name(rename)?)
+            },
+        })
+    }
+
+    /// Returns whether `packet` should be forwarded, renaming it in place.
+    fn apply(&self, packet: &mut [u8]) -> bool {
+        let Ok(header) = VbanHeader::parse(packet) else {
+            return false;
+        };
+        if self
+            .stream_filter
+            .as_ref()
+            .is_some_and(|name| *name != header.stream_name)
+        {
+            return false;
+        }
+        if let Some(rename) = &self.rename {
+            packet[STREAM_NAME_OFFSET..STREAM_NAME_OFFSET + STREAM_NAME_SIZE]
+                .copy_from_slice(rename);
+        }
+        true
+    }
+}
+
+fn resolve_destinations(relay: &VbanRelay) -> Result<Vec<Destination>> {
+    let mut destinations = Vec::new();
+    for destination in &relay.destinations {
+        let ip = destination.ip.trim();
+        let addr = (ip, destination.port)
+            .to_socket_addrs()
+            .with_context(|| format!("Could not resolve `{ip}`"))?
+            .find(SocketAddr::is_ipv4)
+            .with_context(|| format!("No IPv4 address for `{ip}`"))?;
+        if addr.port() == relay.listen_port
+            && (addr.ip().is_loopback() || addr.ip().is_unspecified())
+        {
+            bail!("Destination {addr} would send packets back into the relay");
+        }
+        destinations.push(Destination {
+            label: format!("{ip}:{}", destination.port),
+            addr,
+            counters: DestinationCounters::default(),
+        });
+    }
+    if destinations.is_empty() {
+        bail!("Relay `{}` has no destination", relay.name);
+    }
+    Ok(destinations)
+}
+
+fn forward_packets(
+    socket: &UdpSocket,
+    filter: &RelayFilter,
+    destinations: &[Destination],
+    received: &AtomicU64,
+    stop: &AtomicBool,
+) -> Result<()> {
+    let mut buf = [0u8; MAX_PACKET_SIZE];
+    while !stop.load(Ordering::Relaxed) {
+        let len = match socket.recv_from(&mut buf) {
+            Ok((len, _)) => len,
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => continue,
+            Err(e) => return Err(e).context("Could not receive VBAN packet"),
+        };
+        let packet = &mut buf[..len];
+        if !filter.apply(packet) {
+            continue;
+        }
+        received.fetch_add(1, Ordering::Relaxed);
+
+        for destination in destinations {
+            let counters = &destination.counters;
+            match socket.send_to(packet, destination.addr) {
+                Ok(sent) => {
+                    counters.packets.fetch_add(1, Ordering::Relaxed);
+                    counters.bytes.fetch_add(sent as u64, Ordering::Relaxed);
+                }
+                Err(_) => {
+                    counters.errors.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Running relay for one `VbanRelay` entry, stopped when dropped.
+pub struct Relay {
+    config: VbanRelay,
+    received: Arc<AtomicU64>,
+    destinations: Arc<Vec<Destination>>,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<Result<()>>>,
+}
+
+impl Relay {
+    pub fn spawn(config: &VbanRelay) -> Result<Self> {
+        let filter = RelayFilter::new(config)?;
+        let destinations = Arc::new(resolve_destinations(config)?);
+        let port = config.listen_port;
+        let socket = UdpSocket::bind(("0.0.0.0", port)).with_context(|| {
+            format!("Could not listen on UDP port {port} (is a PipeWire vban-recv using it?)")
+        })?;
+        socket.set_read_timeout(Some(Duration::from_millis(200)))?;
+
+        let received = Arc::new(AtomicU64::new(0));
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let destinations = Arc::clone(&destinations);
+            let received = Arc::clone(&received);
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || {
+                forward_packets(&socket, &filter, &destinations, &received, &stop)
+            })
+        };
+
+        Ok(Self {
+            config: config.clone(),
+            received,
+            destinations,
+            stop,
+            handle: Some(handle),
+        })
+    }
+
+    pub fn id(&self) -> uuid::Uuid {
+        self.config.id
+    }
+
+    /// Whether this relay already runs with exactly `config`.
+    pub fn matches(&self, config: &VbanRelay) -> bool {
+        self.config == *config
+    }
+
+    pub fn received(&self) -> u64 {
+        self.received.load(Ordering::Relaxed)
+    }
+
+    pub fn destination_stats(&self) -> Vec<DestinationStats> {
+        self.destinations
+            .iter()
+            .map(|destination| DestinationStats {
+                label: destination.label.clone(),
+                packets: destination.counters.packets.load(Ordering::Relaxed),
+                bytes: destination.counters.bytes.load(Ordering::Relaxed),
+                errors: destination.counters.errors.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+
+    /// Returns the thread error once the relay has stopped on its own.
+    pub fn take_error(&mut self) -> Option<anyhow::Error> {
+        if !self.handle.as_ref()?.is_finished() {
+            return None;
+        }
+        match self.handle.take()?.join() {
+            Ok(Ok(())) => None,
+            Ok(Err(e)) => Some(e),
+            Err(_) => Some(anyhow::anyhow!("relay thread panicked")),
+        }
+    }
+}
+
+impl Drop for Relay {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{model::RelayDestination, vban::DataType};
+
+    fn audio_packet(name: &str) -> Vec<u8> {
+        let header = VbanHeader::audio(name, 48_000, 2, 256, DataType::Int16).unwrap();
+        let mut packet = header.to_bytes().unwrap().to_vec();
+        packet.extend_from_slice(&[0; 256 * 4]);
+        packet
+    }
+
+    #[test]
+    fn filter_keeps_matching_streams_and_renames_them() {
+        let filter = RelayFilter::new(&VbanRelay {
+            stream_filter: "Mic1".into(),
+            rename: "Studio".into(),
+            ..VbanRelay::default()
+        })
+        .unwrap();
+
+        let mut other = audio_packet("Desk");
+        assert!(!filter.apply(&mut other));
+        assert!(!filter.apply(&mut b"definitely not a VBAN packet....".to_vec()));
+
+        let mut packet = audio_packet("Mic1");
+        assert!(filter.apply(&mut packet));
+        let header = VbanHeader::parse(&packet).unwrap();
+        assert_eq!(header.stream_name, "Studio");
+        assert_eq!(header.sample_rate(), Some(48_000));
+        assert_eq!(packet.len(), 28 + 256 * 4);
+    }
+
+    #[test]
+    fn relay_fans_out_to_every_destination() {
+        let a = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let b = UdpSocket::bind("127.0.0.1:0").unwrap();
+        for socket in [&a, &b] {
+            socket
+                .set_read_timeout(Some(Duration::from_secs(2)))
+                .unwrap();
+        }
+        let listen_port = {
+            let probe = UdpSocket::bind("127.0.0.1:0").unwrap();
+            probe.local_addr().unwrap().port()
+        };
+        let config = VbanRelay {
+            listen_port,
+            destinations: [&a, &b]
+                .iter()
+                .map(|socket| RelayDestination {
+                    ip: "127.0.0.1".into(),
+                    port: socket.local_addr().unwrap().port(),
+                })
+                .collect(),
+            ..VbanRelay::default()
+        };
+        let relay = Relay::spawn(&config).unwrap();
+
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let packet = audio_packet("Stream1");
+        sender.send_to(&packet, ("127.0.0.1", listen_port)).unwrap();
+
+        let mut buf = [0u8; MAX_PACKET_SIZE];
+        for socket in [&a, &b] {
+            let (len, _) = socket.recv_from(&mut buf).unwrap();
+            assert_eq!(&buf[..len], &packet[..]);
+        }
+        // Counters are bumped right after each send.
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(relay.received(), 1);
+        assert!(relay
+            .destination_stats()
+            .iter()
+            .all(|stats| stats.packets == 1 && stats.bytes == packet.len() as u64));
+    }
+}
@@ -0,0 +1,166 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::{
+    model::AppConfig,
+    pipewire_conf::{filename_recv, filename_send},
+    system::{self, ApplyPlan, FragmentChangeKind, NodeRuntime},
+};
+
+/// How the fragment on disk compares to the config entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FragmentSync {
+    UpToDate,
+    /// Written, but the config changed since the last apply.
+    Outdated,
+    /// Enabled, but not written yet.
+    Missing,
+    /// Disabled, but still on disk.
+    Leftover,
+    /// Disabled and not on disk.
+    Absent,
+}
+
+impl FragmentSync {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::UpToDate => "fragment up to date",
+            Self::Outdated => "fragment differs from config",
+            Self::Missing => "fragment not written",
+            Self::Leftover => "fragment still on disk",
+            Self::Absent => "no fragment",
+        }
+    }
+
+    /// Whether an apply is needed for this entry.
+    pub fn is_pending(self) -> bool {
+        matches!(self, Self::Outdated | Self::Missing | Self::Leftover)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamRuntime {
+    /// `None` when no node with the entry's node.name exists in the graph.
+    pub node: Option<NodeRuntime>,
+    pub fragment: FragmentSync,
+}
+
+/// Runtime state of every configured send and recv, keyed by entry id.
+#[derive(Debug, Clone, Default)]
+pub struct RuntimeSnapshot {
+    pub streams: HashMap<Uuid, StreamRuntime>,
+    /// Why the graph could not be read (e.g. PipeWire is not running).
+    pub graph_error: Option<String>,
+}
+
+fn fragment_sync(plan: &ApplyPlan, file_name: &str) -> FragmentSync {
+    match plan
+        .changes
+        .iter()
+        .find(|change| change.file_name == file_name)
+        .map(|change| change.kind)
+    {
+        Some(FragmentChangeKind::Unchanged) => FragmentSync::UpToDate,
+        Some(FragmentChangeKind::Modify) => FragmentSync::Outdated,
+        Some(FragmentChangeKind::Create) => FragmentSync::Missing,
+        Some(FragmentChangeKind::Delete) => FragmentSync::Leftover,
+        None => FragmentSync::Absent,
+    }
+}
+
+fn build_snapshot(
+    cfg: &AppConfig,
+    plan: &ApplyPlan,
+    nodes: Result<HashMap<String, NodeRuntime>>,
+) -> RuntimeSnapshot {
+    let (nodes, graph_error) = match nodes {
+        Ok(nodes) => (nodes, None),
+        Err(e) => (HashMap::new(), Some(format!("{e:#}"))),
+    };
+    let node = |name: &str| nodes.get(name.trim()).cloned();
+
+    let sends = cfg.sends.iter().map(|send| {
+        let file_name = filename_send(&send.id.simple().to_string());
+        (
+            send.id,
+            StreamRuntime {
+                node: node(&send.node_name),
+                fragment: fragment_sync(plan, &file_name),
+            },
+        )
+    });
+    let recvs = cfg.recvs.iter().map(|recv| {
+        let file_name = filename_recv(&recv.id.simple().to_string());
+        (
+            recv.id,
+            StreamRuntime {
+                node: node(&recv.node_name),
+                fragment: fragment_sync(plan, &file_name),
+            },
+        )
+    });
+
+    RuntimeSnapshot {
+        streams: sends.chain(recvs).collect(),
+        graph_error,
+    }
+}
+
+/// Reads the graph and the drop-in directory; meant to run off the UI thread.
+pub fn collect_runtime(cfg: &AppConfig) -> Result<RuntimeSnapshot> {
+    let plan = system::plan_pipewire_fragments(cfg)?;
+    let names: Vec<&str> = cfg
+        .sends
+        .iter()
+        .map(|send| send.node_name.trim())
+        .chain(cfg.recvs.iter().map(|recv| recv.node_name.trim()))
+        .collect();
+    Ok(build_snapshot(
+        cfg,
+        &plan,
+        system::load_node_runtimes(&names),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{VbanRecv, VbanSend};
+
+    #[test]
+    fn snapshot_matches_nodes_and_fragments_to_entries() {
+        let dir = std::env::temp_dir().join(format!("rustban-runtime-{}", Uuid::new_v4()));
+        let cfg = AppConfig {
+            sends: vec![VbanSend::default()],
+            recvs: vec![VbanRecv {
+                enabled: false,
+                ..VbanRecv::default()
+            }],
+            ..AppConfig::default()
+        };
+        let plan = system::plan_fragments_in(&dir, &cfg).unwrap();
+        let node = NodeRuntime {
+            id: 40,
+            state: "running".into(),
+            error: None,
+            linked_nodes: vec!["alsa_input.usb".into()],
+        };
+        let nodes = HashMap::from([(cfg.sends[0].node_name.clone(), node.clone())]);
+
+        let snapshot = build_snapshot(&cfg, &plan, Ok(nodes));
+        let send = &snapshot.streams[&cfg.sends[0].id];
+        assert_eq!(send.node, Some(node));
+        assert_eq!(send.fragment, FragmentSync::Missing);
+        let recv = &snapshot.streams[&cfg.recvs[0].id];
+        assert_eq!(recv.node, None);
+        assert_eq!(recv.fragment, FragmentSync::Absent);
+
+        let snapshot = build_snapshot(&cfg, &plan, Err(anyhow::anyhow!("no pw-dump")));
+        assert_eq!(snapshot.graph_error.as_deref(), Some("no pw-dump"));
+        assert!(snapshot
+            .streams
+            .values()
+            .all(|stream| stream.node.is_none()));
+    }
+}
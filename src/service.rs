@@ -0,0 +1,450 @@
+//! VBAN service sub-protocol: PING0 identification requests and replies.
+
+use anyhow::{bail, Context, Result};
+use std::{
+    io::ErrorKind,
+    net::UdpSocket,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::{
+    model::{AppConfig, HostInfoEmulation},
+    vban::{
+        decode_stream_name, encode_stream_name, SubProtocol, HEADER_SIZE, MAGIC, MAX_PACKET_SIZE,
+    },
+};
+
+pub const SERVICE_IDENTIFICATION: u8 = 0x00;
+pub const FUNCTION_PING0: u8 = 0x00;
+pub const FUNCTION_REPLY: u8 = 0x80;
+pub const PING0_SIZE: usize = 676;
+pub const SERVICE_STREAM_NAME: &str = "VBAN Service";
+
+pub const DEVICE_TYPE_RECEPTOR: u32 = 0x0000_0001;
+pub const DEVICE_TYPE_TRANSMITTER: u32 = 0x0000_0002;
+pub const DEVICE_TYPE_RECEPTOR_SPOT: u32 = 0x0000_0004;
+pub const DEVICE_TYPE_TRANSMITTER_SPOT: u32 = 0x0000_0008;
+pub const DEVICE_TYPE_VIRTUAL_DEVICE: u32 = 0x0000_0010;
+pub const DEVICE_TYPE_VIRTUAL_MIXER: u32 = 0x0000_0020;
+pub const DEVICE_TYPE_MATRIX: u32 = 0x0000_0040;
+pub const DEVICE_TYPE_DAW: u32 = 0x0000_0080;
+pub const DEVICE_TYPE_SERVER: u32 = 0x0100_0000;
+pub const FEATURE_AUDIO: u32 = 0x0000_0001;
+
+/// Decoded `T_VBAN_PING0` payload (676 bytes, little endian).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Ping0 {
+    pub device_type: u32,
+    pub features: u32,
+    pub features_ex: u32,
+    pub preferred_rate: u32,
+    pub min_rate: u32,
+    pub max_rate: u32,
+    pub color_rgb: u32,
+    pub version: [u8; 4],
+    pub lang_code: String,
+    pub device_name: String,
+    pub manufacturer_name: String,
+    pub application_name: String,
+    pub host_name: String,
+    pub user_name: String,
+    pub user_comment: String,
+}
+
+// Field offsets inside the PING0 payload.
+const LANG_CODE: (usize, usize) = (48, 8);
+const DEVICE_NAME: (usize, usize) = (164, 64);
+const MANUFACTURER_NAME: (usize, usize) = (228, 64);
+const APPLICATION_NAME: (usize, usize) = (292, 64);
+const HOST_NAME: (usize, usize) = (356, 64);
+const USER_NAME: (usize, usize) = (420, 128);
+const USER_COMMENT: (usize, usize) = (548, 128);
+
+impl Ping0 {
+    /// Identity advertised for this machine, taken from the host info emulation settings.
+    pub fn from_host_info(emulation: &HostInfoEmulation, device_type: u32) -> Self {
+        Self {
+            device_type,
+            features: FEATURE_AUDIO,
+            preferred_rate: 48_000,
+            min_rate: 8_000,
+            max_rate: 384_000,
+            color_rgb: 0x0091_C9FF,
+            version: crate_version(),
+            lang_code: "EN".into(),
+            device_name: emulation.client_name.trim().to_string(),
+            manufacturer_name: "RustBAN".into(),
+            application_name: emulation.app_name.trim().to_string(),
+            host_name: emulation.host_name.trim().to_string(),
+            user_name: emulation.user_name.trim().to_string(),
+            user_comment: String::new(),
+            ..Self::default()
+        }
+    }
+
+    pub fn from_config(cfg: &AppConfig) -> Self {
+        let mut device_type = DEVICE_TYPE_VIRTUAL_DEVICE;
+        if cfg.sends.iter().any(|send| send.enabled) {
+            device_type |= DEVICE_TYPE_TRANSMITTER;
+        }
+        if cfg.recvs.iter().any(|recv| recv.enabled) {
+            device_type |= DEVICE_TYPE_RECEPTOR;
+        }
+        Self::from_host_info(&cfg.host_info_emulation, device_type)
+    }
+
+    pub fn parse(payload: &[u8]) -> Result<Self> {
+        if payload.len() < PING0_SIZE {
+            bail!(
+                "PING0 payload too short ({} < {PING0_SIZE} bytes)",
+                payload.len()
+            );
+        }
+
+        let word = |offset: usize| {
+            u32::from_le_bytes([
+                payload[offset],
+                payload[offset + 1],
+                payload[offset + 2],
+                payload[offset + 3],
+            ])
+        };
+        let text =
+            |(offset, size): (usize, usize)| decode_stream_name(&payload[offset..offset + size]);
+
+        Ok(Self {
+            device_type: word(0),
+            features: word(4),
+            features_ex: word(8),
+            preferred_rate: word(12),
+            min_rate: word(16),
+            max_rate: word(20),
+            color_rgb: word(24),
+            version: [payload[28], payload[29], payload[30], payload[31]],
+            lang_code: text(LANG_CODE),
+            device_name: text(DEVICE_NAME),
+            manufacturer_name: text(MANUFACTURER_NAME),
+            application_name: text(APPLICATION_NAME),
+            host_name: text(HOST_NAME),
+            user_name: text(USER_NAME),
+            user_comment: text(USER_COMMENT),
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; PING0_SIZE] {
+        let mut out = [0u8; PING0_SIZE];
+        let words = [
+            self.device_type,
+            self.features,
+            self.features_ex,
+            self.preferred_rate,
+            self.min_rate,
+            self.max_rate,
+            self.color_rgb,
+        ];
+        for (i, word) in words.iter().enumerate() {
+            out[i * 4..i * 4 + 4].copy_from_slice(&word.to_le_bytes());
+        }
+        out[28..32].copy_from_slice(&self.version);
+
+        write_text(&mut out, LANG_CODE, &self.lang_code);
+        write_text(&mut out, DEVICE_NAME, &self.device_name);
+        write_text(&mut out, MANUFACTURER_NAME, &self.manufacturer_name);
+        write_text(&mut out, APPLICATION_NAME, &self.application_name);
+        write_text(&mut out, HOST_NAME, &self.host_name);
+        write_text(&mut out, USER_NAME, &self.user_name);
+        write_text(&mut out, USER_COMMENT, &self.user_comment);
+        out
+    }
+}
+
+/// Human readable list of the `T_VBAN_PING0::bitType` flags.
+pub fn device_type_label(device_type: u32) -> String {
+    let names = [
+        (DEVICE_TYPE_RECEPTOR, "receptor"),
+        (DEVICE_TYPE_TRANSMITTER, "transmitter"),
+        (DEVICE_TYPE_RECEPTOR_SPOT, "receptor spot"),
+        (DEVICE_TYPE_TRANSMITTER_SPOT, "transmitter spot"),
+        (DEVICE_TYPE_VIRTUAL_DEVICE, "virtual device"),
+        (DEVICE_TYPE_VIRTUAL_MIXER, "virtual mixer"),
+        (DEVICE_TYPE_MATRIX, "matrix"),
+        (DEVICE_TYPE_DAW, "DAW"),
+        (DEVICE_TYPE_SERVER, "server"),
+    ];
+    let labels: Vec<_> = names
+        .iter()
+        .filter(|(bit, _)| device_type & bit != 0)
+        .map(|(_, name)| *name)
+        .collect();
+
+    if labels.is_empty() {
+        format!("unknown ({device_type:#x})")
+    } else {
+        labels.join(", ")
+    }
+}
+
+/// Copies `value` into a zero-terminated fixed-size field, truncating on a char boundary.
+fn write_text(out: &mut [u8], (offset, size): (usize, usize), value: &str) {
+    let mut end = value.len().min(size - 1);
+    while !value.is_char_boundary(end) {
+        end -= 1;
+    }
+    out[offset..offset + end].copy_from_slice(&value.as_bytes()[..end]);
+}
+
+fn crate_version() -> [u8; 4] {
+    let mut version = [0u8; 4];
+    for (slot, part) in version.iter_mut().zip(env!("CARGO_PKG_VERSION").split('.')) {
+        *slot = part.parse().unwrap_or_default();
+    }
+    version
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServicePacket {
+    pub function: u8,
+    pub service_type: u8,
+    pub stream_name: String,
+    pub frame_counter: u32,
+    pub payload: Vec<u8>,
+}
+
+impl ServicePacket {
+    pub fn parse(packet: &[u8]) -> Result<Self> {
+        if packet.len() < HEADER_SIZE || packet[..4] != MAGIC {
+            bail!("Not a VBAN packet");
+        }
+        if SubProtocol::from_bits(packet[4]) != SubProtocol::Service {
+            bail!("Not a VBAN service packet");
+        }
+
+        Ok(Self {
+            function: packet[5],
+            service_type: packet[6],
+            stream_name: decode_stream_name(&packet[8..24]),
+            frame_counter: u32::from_le_bytes([packet[24], packet[25], packet[26], packet[27]]),
+            payload: packet[HEADER_SIZE..].to_vec(),
+        })
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut out = Vec::with_capacity(HEADER_SIZE + self.payload.len());
+        out.extend_from_slice(&MAGIC);
+        out.push(SubProtocol::Service.bits());
+        out.push(self.function);
+        out.push(self.service_type);
+        out.push(0);
+        out.extend_from_slice(&encode_stream_name(&self.stream_name)?);
+        out.extend_from_slice(&self.frame_counter.to_le_bytes());
+        out.extend_from_slice(&self.payload);
+        Ok(out)
+    }
+
+    pub fn ping(frame_counter: u32, identity: &Ping0) -> Self {
+        Self {
+            function: FUNCTION_PING0,
+            service_type: SERVICE_IDENTIFICATION,
+            stream_name: SERVICE_STREAM_NAME.into(),
+            frame_counter,
+            payload: identity.to_bytes().to_vec(),
+        }
+    }
+
+    pub fn is_ping_request(&self) -> bool {
+        self.service_type == SERVICE_IDENTIFICATION && self.function == FUNCTION_PING0
+    }
+
+    pub fn is_ping_reply(&self) -> bool {
+        self.service_type == SERVICE_IDENTIFICATION && self.function == FUNCTION_REPLY
+    }
+
+    /// Reply to a PING0 request, echoing its frame counter.
+    pub fn reply_to(&self, identity: &Ping0) -> Self {
+        Self {
+            function: FUNCTION_REPLY,
+            service_type: SERVICE_IDENTIFICATION,
+            stream_name: self.stream_name.clone(),
+            frame_counter: self.frame_counter,
+            payload: identity.to_bytes().to_vec(),
+        }
+    }
+}
+
+/// Background thread answering PING0 requests on a UDP port.
+pub struct PingResponder {
+    port: u16,
+    identity: Ping0,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl PingResponder {
+    pub fn spawn(port: u16, identity: Ping0) -> Result<Self> {
+        let socket = UdpSocket::bind(("0.0.0.0", port)).with_context(|| {
+            format!("Could not listen on UDP port {port} for VBAN PING0 (is a vban-recv using it?)")
+        })?;
+        socket.set_read_timeout(Some(Duration::from_millis(200)))?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let stop = Arc::clone(&stop);
+            let identity = identity.clone();
+            thread::spawn(move || {
+                let _ = serve_pings(&socket, &identity, &stop);
+            })
+        };
+
+        Ok(Self {
+            port,
+            identity,
+            stop,
+            handle: Some(handle),
+        })
+    }
+
+    pub fn matches(&self, port: u16, identity: &Ping0) -> bool {
+        self.port == port && &self.identity == identity
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+}
+
+impl Drop for PingResponder {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Answers PING0 requests on `socket` until `stop` is set.
+pub fn serve_pings(socket: &UdpSocket, identity: &Ping0, stop: &AtomicBool) -> Result<()> {
+    let mut buf = [0u8; MAX_PACKET_SIZE];
+    while !stop.load(Ordering::Relaxed) {
+        let (len, addr) = match socket.recv_from(&mut buf) {
+            Ok(received) => received,
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => continue,
+            Err(e) => return Err(e).context("Could not receive VBAN service packet"),
+        };
+
+        let Ok(request) = ServicePacket::parse(&buf[..len]) else {
+            continue;
+        };
+        if !request.is_ping_request() {
+            continue;
+        }
+
+        let reply = request.reply_to(identity).to_bytes()?;
+        // A vanished requester must not stop the responder.
+        let _ = socket.send_to(&reply, addr);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identity() -> Ping0 {
+        Ping0::from_host_info(&HostInfoEmulation::default(), DEVICE_TYPE_RECEPTOR)
+    }
+
+    #[test]
+    fn ping0_layout_round_trips() {
+        let ping = Ping0 {
+            user_comment: "é".repeat(80),
+            ..identity()
+        };
+        let bytes = ping.to_bytes();
+
+        assert_eq!(&bytes[0..4], &DEVICE_TYPE_RECEPTOR.to_le_bytes());
+        assert_eq!(&bytes[12..16], &48_000u32.to_le_bytes());
+        assert_eq!(&bytes[DEVICE_NAME.0..DEVICE_NAME.0 + 11], b"VBAN Remote");
+        assert_eq!(
+            &bytes[APPLICATION_NAME.0..APPLICATION_NAME.0 + 5],
+            b"VBAN\0"
+        );
+        assert_eq!(&bytes[HOST_NAME.0..HOST_NAME.0 + 9], b"vban-host");
+        assert_eq!(bytes[PING0_SIZE - 1], 0);
+
+        let parsed = Ping0::parse(&bytes).unwrap();
+        assert_eq!(parsed.user_comment, "é".repeat(63));
+        assert_eq!(
+            parsed,
+            Ping0 {
+                user_comment: "é".repeat(63),
+                ..ping
+            }
+        );
+    }
+
+    #[test]
+    fn reply_echoes_frame_counter_and_sets_reply_function() {
+        let request = ServicePacket::ping(42, &Ping0::default());
+        let bytes = request.to_bytes().unwrap();
+        assert_eq!(
+            &bytes[..8],
+            &[b'V', b'B', b'A', b'N', 0x60, 0x00, 0x00, 0x00]
+        );
+        assert_eq!(bytes.len(), HEADER_SIZE + PING0_SIZE);
+
+        let parsed = ServicePacket::parse(&bytes).unwrap();
+        assert!(parsed.is_ping_request());
+
+        let reply = parsed.reply_to(&identity());
+        assert!(reply.is_ping_reply());
+        assert_eq!(reply.frame_counter, 42);
+        assert_eq!(reply.to_bytes().unwrap()[5], FUNCTION_REPLY);
+    }
+
+    #[test]
+    fn labels_device_type_flags() {
+        assert_eq!(
+            device_type_label(DEVICE_TYPE_TRANSMITTER | DEVICE_TYPE_VIRTUAL_MIXER),
+            "transmitter, virtual mixer"
+        );
+        assert_eq!(device_type_label(0x200), "unknown (0x200)");
+    }
+
+    #[test]
+    fn responder_answers_ping_on_loopback() {
+        let responder_socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        responder_socket
+            .set_read_timeout(Some(Duration::from_millis(50)))
+            .unwrap();
+        let responder_addr = responder_socket.local_addr().unwrap();
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || serve_pings(&responder_socket, &identity(), &stop))
+        };
+
+        let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_secs(2)))
+            .unwrap();
+        let request = ServicePacket::ping(7, &Ping0::default())
+            .to_bytes()
+            .unwrap();
+        client.send_to(&request, responder_addr).unwrap();
+
+        let mut buf = [0u8; MAX_PACKET_SIZE];
+        let (len, _) = client.recv_from(&mut buf).unwrap();
+        stop.store(true, Ordering::Relaxed);
+        handle.join().unwrap().unwrap();
+
+        let reply = ServicePacket::parse(&buf[..len]).unwrap();
+        assert!(reply.is_ping_reply());
+        assert_eq!(reply.frame_counter, 7);
+        assert_eq!(Ping0::parse(&reply.payload).unwrap(), identity());
+    }
+}
@@ -0,0 +1,121 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::path::PathBuf;
+
+use crate::{
+    model::AppConfig,
+    pipewire_conf::{filename_recv, filename_send},
+    system::{self, AutoLinkRecord},
+};
+
+/// Bump whenever a field of [`StatusReport`] is renamed, removed or changes meaning.
+pub const STATUS_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize)]
+pub struct StatusReport {
+    pub schema_version: u32,
+    pub config: AppConfig,
+    pub fragments: FragmentStatus,
+    pub autolink: Option<AutoLinkRecord>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FragmentStatus {
+    pub dir: PathBuf,
+    pub files: Vec<FragmentFile>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FragmentFile {
+    pub file_name: String,
+    pub kind: FragmentKind,
+    /// `true` when an enabled send/recv in the config owns this file.
+    pub configured: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FragmentKind {
+    Send,
+    Recv,
+}
+
+pub fn collect_status() -> Result<StatusReport> {
+    let config = system::load_app_config()?;
+    let dir = system::pipewire_dropin_dir()?;
+    let files = describe_fragments(system::list_rustban_fragments()?, &config);
+    let autolink = system::load_last_autolink_record()?;
+
+    Ok(StatusReport {
+        schema_version: STATUS_SCHEMA_VERSION,
+        config,
+        fragments: FragmentStatus { dir, files },
+        autolink,
+    })
+}
+
+fn describe_fragments(names: Vec<String>, cfg: &AppConfig) -> Vec<FragmentFile> {
+    names
+        .into_iter()
+        .map(|file_name| {
+            let kind = if file_name.starts_with("99-rustban-send-") {
+                FragmentKind::Send
+            } else {
+                FragmentKind::Recv
+            };
+            let configured = match kind {
+                FragmentKind::Send => cfg.sends.iter().any(|send| {
+                    send.enabled && filename_send(&send.id.simple().to_string()) == file_name
+                }),
+                FragmentKind::Recv => cfg.recvs.iter().any(|recv| {
+                    recv.enabled && filename_recv(&recv.id.simple().to_string()) == file_name
+                }),
+            };
+
+            FragmentFile {
+                file_name,
+                kind,
+                configured,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{VbanRecv, VbanSend};
+
+    #[test]
+    fn flags_fragments_without_enabled_config_entry() {
+        let send = VbanSend::default();
+        let recv = VbanRecv {
+            enabled: false,
+            ..VbanRecv::default()
+        };
+        let cfg = AppConfig {
+            sends: vec![send.clone()],
+            recvs: vec![recv.clone()],
+            ..AppConfig::default()
+        };
+        let names = vec![
+            filename_send(&send.id.simple().to_string()),
+            filename_recv(&recv.id.simple().to_string()),
+            filename_send("0000"),
+        ];
+
+        let files = describe_fragments(names, &cfg);
+        let summary: Vec<_> = files
+            .iter()
+            .map(|file| (file.kind, file.configured))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (FragmentKind::Send, true),
+                (FragmentKind::Recv, false),
+                (FragmentKind::Send, false),
+            ]
+        );
+    }
+}
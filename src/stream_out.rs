@@ -0,0 +1,112 @@
+use anyhow::{bail, Context, Result};
+use std::{
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::vban::{DataType, VbanHeader, MAX_DATA_SIZE, MAX_SAMPLES_PER_FRAME};
+
+/// Largest frame (in samples per channel) that fits in one VBAN packet.
+pub fn samples_per_frame(channels: u16, data_type: DataType) -> Result<u16> {
+    let Some(sample_size) = data_type.sample_size() else {
+        bail!("{} samples are not supported", data_type.label());
+    };
+    let frame_size = usize::from(channels) * sample_size;
+    let fit = MAX_DATA_SIZE / frame_size.max(1);
+    if fit == 0 {
+        bail!(
+            "{channels} channels of {} do not fit in a VBAN packet",
+            data_type.label()
+        );
+    }
+    Ok(fit.min(usize::from(MAX_SAMPLES_PER_FRAME)) as u16)
+}
+
+/// Sends VBAN audio packets to one destination at real-time pace.
+pub struct AudioStreamOut {
+    socket: UdpSocket,
+    addr: SocketAddr,
+    header: VbanHeader,
+    rate: u32,
+    frame_size: usize,
+    started: Instant,
+    sent_samples: u64,
+}
+
+impl AudioStreamOut {
+    pub fn connect(ip: &str, port: u16, header: VbanHeader) -> Result<Self> {
+        let Some(rate) = header.sample_rate() else {
+            bail!("Header has no valid sample rate");
+        };
+        let Some(sample_size) = header.data_type.sample_size() else {
+            bail!("{} samples are not supported", header.data_type.label());
+        };
+
+        let addr = (ip.trim(), port)
+            .to_socket_addrs()
+            .with_context(|| format!("Could not resolve `{ip}`"))?
+            .next()
+            .with_context(|| format!("No address for `{ip}`"))?;
+        let socket = UdpSocket::bind(("0.0.0.0", 0)).context("Could not open UDP socket")?;
+
+        Ok(Self {
+            socket,
+            addr,
+            frame_size: usize::from(header.channels) * sample_size,
+            header,
+            rate,
+            started: Instant::now(),
+            sent_samples: 0,
+        })
+    }
+
+    pub fn frames_sent(&self) -> u32 {
+        self.header.frame_counter
+    }
+
+    /// Sends interleaved PCM `payload`, then sleeps so the stream does not run ahead of real time.
+    pub fn send(&mut self, payload: &[u8]) -> Result<()> {
+        let samples = payload.len() / self.frame_size;
+        if samples == 0 || payload.len() % self.frame_size != 0 {
+            bail!("Payload of {} bytes is not whole frames", payload.len());
+        }
+        if samples > usize::from(MAX_SAMPLES_PER_FRAME) || payload.len() > MAX_DATA_SIZE {
+            bail!(
+                "Payload of {} bytes does not fit in one VBAN packet",
+                payload.len()
+            );
+        }
+
+        self.header.samples_per_frame = samples as u16;
+        let mut packet = self.header.to_bytes()?.to_vec();
+        packet.extend_from_slice(payload);
+        self.socket
+            .send_to(&packet, self.addr)
+            .with_context(|| format!("Could not send VBAN packet to {}", self.addr))?;
+
+        self.header.frame_counter = self.header.frame_counter.wrapping_add(1);
+        self.sent_samples += samples as u64;
+
+        let due =
+            self.started + Duration::from_secs_f64(self.sent_samples as f64 / f64::from(self.rate));
+        if let Some(wait) = due.checked_duration_since(Instant::now()) {
+            thread::sleep(wait);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_size_respects_packet_limits() {
+        assert_eq!(samples_per_frame(2, DataType::Int16).unwrap(), 256);
+        assert_eq!(samples_per_frame(8, DataType::Int24).unwrap(), 59);
+        assert_eq!(samples_per_frame(1, DataType::Float64).unwrap(), 179);
+        assert!(samples_per_frame(256, DataType::Float64).is_err());
+        assert!(samples_per_frame(2, DataType::Bits12).is_err());
+    }
+}
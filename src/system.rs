@@ -1,41 +1,245 @@
 use anyhow::{Context, Result};
 use directories::BaseDirs;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::{
     collections::{HashMap, HashSet},
     fs,
     path::{Path, PathBuf},
     process::Command,
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
 use crate::{
-    model::AppConfig,
+    diff::unified_diff,
+    live,
+    model::{AppConfig, ChannelMap},
     pipewire_conf::{filename_recv, filename_send, render_recv, render_send},
 };
 
+/// An `Audio/Sink` a recv can play into.
+#[derive(Debug, Clone)]
+pub struct AudioSinkDevice {
+    pub node_name: String,
+    pub description: String,
+}
+
+/// First and longest pause between graph polls while waiting for auto-link nodes.
+const AUTOLINK_POLL_START: Duration = Duration::from_millis(250);
+const AUTOLINK_POLL_MAX: Duration = Duration::from_secs(2);
+
+/// What a send can take its audio from; all of them expose output ports to link.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SourceKind {
+    /// Microphones and other `Audio/Source` nodes.
+    Input,
+    /// The monitor ports of an `Audio/Sink` ("what I hear").
+    Monitor,
+    /// A `Stream/Output/Audio` playback stream of an application.
+    Application,
+}
+
+impl SourceKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Input => "Inputs",
+            Self::Monitor => "Sink monitors",
+            Self::Application => "Applications",
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct AudioSourceDevice {
+    /// `node.name` to link from; for a monitor, the sink itself.
     pub node_name: String,
     pub description: String,
+    pub kind: SourceKind,
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AutoLinkSummary {
     pub links_created: usize,
+    /// Links into send nodes, or from recv nodes into sinks, that no longer match their peer or
+    /// channel map.
+    pub links_removed: usize,
     pub issues: Vec<String>,
 }
 
+impl AutoLinkSummary {
+    /// `2 link(s) created, 1 removed`, or `None` when the graph was left as is.
+    pub fn changes(&self) -> Option<String> {
+        match (self.links_created, self.links_removed) {
+            (0, 0) => None,
+            (created, 0) => Some(format!("{created} link(s) created")),
+            (0, removed) => Some(format!("{removed} link(s) removed")),
+            (created, removed) => Some(format!("{created} link(s) created, {removed} removed")),
+        }
+    }
+}
+
+/// Last auto-link result, persisted so headless tools can report it later.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutoLinkRecord {
+    pub finished_at_unix: u64,
+    #[serde(flatten)]
+    pub summary: AutoLinkSummary,
+}
+
+/// A module loaded in the running PipeWire daemon, as reported by `pw-dump Module`.
+#[derive(Debug, Clone)]
+pub struct PipewireModule {
+    pub id: u32,
+    pub name: String,
+    /// SPA-JSON arguments the module was loaded with (may be empty).
+    pub args: String,
+}
+
 #[derive(Debug, Clone)]
 struct PipewirePort {
+    id: Option<u32>,
     port_name: String,
     is_input: bool,
     channel: Option<String>,
+    /// `port.id`: position of the port among the node's ports of the same direction.
+    index: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PipewireLink {
+    output_node: u32,
+    output_port: Option<u32>,
+    input_node: u32,
+    input_port: Option<u32>,
+}
+
+/// A link by `node:port` names, as `pw-link` takes them.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+struct LinkEnds {
+    output: String,
+    input: String,
+}
+
+impl LinkEnds {
+    fn output_node(&self) -> &str {
+        self.output
+            .rsplit_once(':')
+            .map_or(self.output.as_str(), |(node, _)| node)
+    }
+
+    fn input_node(&self) -> &str {
+        self.input
+            .rsplit_once(':')
+            .map_or(self.input.as_str(), |(node, _)| node)
+    }
+}
+
+impl std::fmt::Display for LinkEnds {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} -> {}", self.output, self.input)
+    }
 }
 
 #[derive(Debug, Clone, Default)]
 struct PipewireTopology {
     nodes_by_name: HashMap<String, u32>,
+    /// `info.state` and `info.error` by node id.
+    node_states: HashMap<u32, (String, Option<String>)>,
     ports_by_node: HashMap<u32, Vec<PipewirePort>>,
+    links: Vec<PipewireLink>,
+    /// Nodes with `media.class = Audio/Sink`.
+    sink_nodes: HashSet<u32>,
+}
+
+/// Live state of a node in the PipeWire graph.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeRuntime {
+    pub id: u32,
+    /// `creating`, `suspended`, `idle`, `running` or `error`.
+    pub state: String,
+    pub error: Option<String>,
+    /// Nodes linked to this one, in either direction.
+    pub linked_nodes: Vec<String>,
+}
+
+impl PipewireTopology {
+    fn node_name(&self, id: u32) -> Option<&str> {
+        self.nodes_by_name
+            .iter()
+            .find(|(_, &node_id)| node_id == id)
+            .map(|(name, _)| name.as_str())
+    }
+
+    fn port_name(&self, node: u32, port: Option<u32>) -> Option<&str> {
+        let port = port?;
+        self.ports_by_node
+            .get(&node)?
+            .iter()
+            .find(|candidate| candidate.id == Some(port))
+            .map(|candidate| candidate.port_name.as_str())
+    }
+
+    /// Links ending at `node`, skipping those whose ports are unknown.
+    fn links_into(&self, node: u32) -> Vec<LinkEnds> {
+        self.named_links(|link| link.input_node == node)
+    }
+
+    /// Links from `node` into playback sinks; other consumers of a recv (apps recording from
+    /// it) are left out so auto-linking never touches them.
+    fn links_into_sinks(&self, node: u32) -> Vec<LinkEnds> {
+        self.named_links(|link| {
+            link.output_node == node && self.sink_nodes.contains(&link.input_node)
+        })
+    }
+
+    fn named_links(&self, keep: impl Fn(&PipewireLink) -> bool) -> Vec<LinkEnds> {
+        self.links
+            .iter()
+            .filter(|link| keep(link))
+            .filter_map(|link| {
+                Some(LinkEnds {
+                    output: format!(
+                        "{}:{}",
+                        self.node_name(link.output_node)?,
+                        self.port_name(link.output_node, link.output_port)?
+                    ),
+                    input: format!(
+                        "{}:{}",
+                        self.node_name(link.input_node)?,
+                        self.port_name(link.input_node, link.input_port)?
+                    ),
+                })
+            })
+            .collect()
+    }
+
+    fn node_runtime(&self, node_name: &str) -> Option<NodeRuntime> {
+        let id = *self.nodes_by_name.get(node_name.trim())?;
+        let (state, error) = self
+            .node_states
+            .get(&id)
+            .cloned()
+            .unwrap_or_else(|| ("unknown".to_string(), None));
+        let mut linked_nodes: Vec<String> = self
+            .links
+            .iter()
+            .filter_map(|link| match (link.output_node, link.input_node) {
+                (out, peer) | (peer, out) if out == id => Some(peer),
+                _ => None,
+            })
+            .filter_map(|peer| self.node_name(peer).map(ToOwned::to_owned))
+            .collect();
+        linked_nodes.sort();
+        linked_nodes.dedup();
+        Some(NodeRuntime {
+            id,
+            state,
+            error,
+            linked_nodes,
+        })
+    }
 }
 
 pub fn config_dir() -> Result<PathBuf> {
@@ -72,142 +276,722 @@ pub fn save_app_config(cfg: &AppConfig) -> Result<()> {
     Ok(())
 }
 
-pub fn apply_pipewire_fragments(cfg: &AppConfig) -> Result<()> {
-    let dir = pipewire_dropin_dir()?;
-    fs::create_dir_all(&dir)?;
+/// What applying the config does to one fragment in `pipewire.conf.d`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FragmentChangeKind {
+    Create,
+    Modify,
+    Unchanged,
+    Delete,
+}
+
+impl FragmentChangeKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Create => "create",
+            Self::Modify => "modify",
+            Self::Unchanged => "unchanged",
+            Self::Delete => "delete",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FragmentChange {
+    pub file_name: String,
+    pub kind: FragmentChangeKind,
+    /// Unified diff against the file on disk, empty for unchanged and deleted fragments.
+    pub diff: String,
+    contents: Option<String>,
+}
+
+impl FragmentChange {
+    /// New contents for created, modified and unchanged fragments.
+    pub fn contents(&self) -> Option<&str> {
+        self.contents.as_deref()
+    }
+}
+
+/// Fragment writes and deletions computed before touching the drop-in directory.
+#[derive(Debug, Clone)]
+pub struct ApplyPlan {
+    pub dir: PathBuf,
+    pub changes: Vec<FragmentChange>,
+    /// Nodes that must exist after a restart; recv nodes only appear once a stream arrives.
+    expected_nodes: Vec<String>,
+}
+
+/// How long PipeWire gets to come back with the expected nodes after a restart.
+const PIPEWIRE_HEALTH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Contents of the RustBAN fragments before an apply, to roll back to.
+struct FragmentSnapshot {
+    dir: PathBuf,
+    files: Vec<(String, String)>,
+}
+
+impl FragmentSnapshot {
+    fn take(dir: &Path) -> Result<Self> {
+        let mut files = Vec::new();
+        for name in rustban_fragments_in(dir)? {
+            let path = dir.join(&name);
+            let raw = fs::read_to_string(&path)
+                .with_context(|| format!("Could not read {}", path.display()))?;
+            files.push((name, raw));
+        }
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            files,
+        })
+    }
+
+    fn restore(&self) -> Result<()> {
+        for name in rustban_fragments_in(&self.dir)? {
+            if !self.files.iter().any(|(kept, _)| *kept == name) {
+                fs::remove_file(self.dir.join(&name))?;
+            }
+        }
+        for (name, raw) in &self.files {
+            write_atomically(&self.dir.join(name), raw)?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes through a temporary file and a rename so PipeWire never reads half a fragment.
+fn write_atomically(path: &Path, contents: &str) -> Result<()> {
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .context("Fragment path has no file name")?;
+    // Not ending in `.conf`, so PipeWire ignores it if we crash before the rename.
+    let tmp = path.with_file_name(format!(".{file_name}.tmp"));
+    fs::write(&tmp, contents).with_context(|| format!("Could not write {}", tmp.display()))?;
+    fs::rename(&tmp, path).with_context(|| format!("Could not replace {}", path.display()))
+}
+
+/// Waits until `pw-dump` answers and lists every node of `expected`.
+pub fn wait_for_pipewire(expected: &[String], timeout: Duration) -> Result<()> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let missing = match pw_dump("Node") {
+            Ok(nodes) => {
+                let names: HashSet<&str> = nodes
+                    .iter()
+                    .filter_map(|node| node.get("info")?.get("props")?.get("node.name")?.as_str())
+                    .collect();
+                let missing: Vec<&str> = expected
+                    .iter()
+                    .map(String::as_str)
+                    .filter(|name| !names.contains(name))
+                    .collect();
+                if missing.is_empty() {
+                    return Ok(());
+                }
+                format!("missing node(s) {}", missing.join(", "))
+            }
+            Err(e) => format!("{e:#}"),
+        };
+        if Instant::now() >= deadline {
+            anyhow::bail!(
+                "PipeWire was not ready within {}s: {missing}",
+                timeout.as_secs()
+            );
+        }
+        thread::sleep(Duration::from_millis(500));
+    }
+}
+
+impl ApplyPlan {
+    pub fn has_changes(&self) -> bool {
+        self.changes
+            .iter()
+            .any(|change| change.kind != FragmentChangeKind::Unchanged)
+    }
+
+    /// E.g. `1 create, 2 unchanged`.
+    pub fn summary(&self) -> String {
+        let parts: Vec<String> = [
+            FragmentChangeKind::Create,
+            FragmentChangeKind::Modify,
+            FragmentChangeKind::Unchanged,
+            FragmentChangeKind::Delete,
+        ]
+        .into_iter()
+        .filter_map(|kind| {
+            let count = self.changes.iter().filter(|c| c.kind == kind).count();
+            (count > 0).then(|| format!("{count} {}", kind.label()))
+        })
+        .collect();
+        if parts.is_empty() {
+            "no fragment".to_string()
+        } else {
+            parts.join(", ")
+        }
+    }
+
+    /// Writes the plan; on failure, or when PipeWire does not come back after `restart`,
+    /// the previous fragments are restored (and PipeWire restarted again).
+    pub fn apply(&self, restart: bool) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let snapshot = FragmentSnapshot::take(&self.dir)?;
+
+        let result = self.write().and_then(|()| {
+            if restart {
+                // The daemon loads every fragment itself; hot-loaded copies would duplicate them.
+                live::stop_all()?;
+                restart_pipewire_user_services()?;
+                wait_for_pipewire(&self.expected_nodes, PIPEWIRE_HEALTH_TIMEOUT)?;
+            }
+            Ok(())
+        });
+        let Err(error) = result else {
+            return Ok(());
+        };
+
+        let rollback = snapshot.restore().and_then(|()| {
+            if restart {
+                restart_pipewire_user_services()?;
+                wait_for_pipewire(&[], PIPEWIRE_HEALTH_TIMEOUT)?;
+            }
+            Ok(())
+        });
+        match rollback {
+            Ok(()) => Err(error.context("Apply failed, previous fragments restored")),
+            Err(rollback_error) => Err(error.context(format!(
+                "Apply failed and restoring the previous fragments failed too ({rollback_error:#})"
+            ))),
+        }
+    }
+
+    fn write(&self) -> Result<()> {
+        for change in &self.changes {
+            let path = self.dir.join(&change.file_name);
+            match (&change.contents, change.kind) {
+                (Some(contents), FragmentChangeKind::Create | FragmentChangeKind::Modify) => {
+                    write_atomically(&path, contents)?;
+                }
+                (_, FragmentChangeKind::Delete) if path.exists() => {
+                    fs::remove_file(&path)
+                        .with_context(|| format!("Could not delete {}", path.display()))?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+fn plan_fragment(
+    dir: &Path,
+    file_name: String,
+    rendered: Option<String>,
+) -> Result<FragmentChange> {
+    let path = dir.join(&file_name);
+    let on_disk = if path.is_file() {
+        Some(
+            fs::read_to_string(&path)
+                .with_context(|| format!("Could not read {}", path.display()))?,
+        )
+    } else {
+        None
+    };
+
+    let (kind, diff) = match (&on_disk, &rendered) {
+        (None, None) => (FragmentChangeKind::Unchanged, String::new()),
+        (Some(_), None) => (FragmentChangeKind::Delete, String::new()),
+        (None, Some(new)) => (
+            FragmentChangeKind::Create,
+            unified_diff("", new, "/dev/null", &format!("b/{file_name}")),
+        ),
+        (Some(old), Some(new)) if old == new => (FragmentChangeKind::Unchanged, String::new()),
+        (Some(old), Some(new)) => (
+            FragmentChangeKind::Modify,
+            unified_diff(
+                old,
+                new,
+                &format!("a/{file_name}"),
+                &format!("b/{file_name}"),
+            ),
+        ),
+    };
+    Ok(FragmentChange {
+        file_name,
+        kind,
+        diff,
+        contents: rendered,
+    })
+}
 
+/// Plans against an explicit drop-in directory.
+pub fn plan_fragments_in(dir: &Path, cfg: &AppConfig) -> Result<ApplyPlan> {
     let mut keep: HashSet<String> = HashSet::new();
+    let mut changes = Vec::new();
+    let expected_nodes = cfg
+        .sends
+        .iter()
+        .filter(|send| send.enabled)
+        .map(|send| send.node_name.trim())
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect();
 
     for send in &cfg.sends {
-        let id = send.id.simple().to_string();
-        let file_name = filename_send(&id);
+        let file_name = filename_send(&send.id.simple().to_string());
         keep.insert(file_name.clone());
-        let path = dir.join(&file_name);
-
-        if send.enabled {
-            fs::write(path, render_send(send, &cfg.host_info_emulation))?;
-        } else if path.exists() {
-            fs::remove_file(path)?;
-        }
+        let rendered = send
+            .enabled
+            .then(|| render_send(send, &cfg.host_info_emulation));
+        changes.push(plan_fragment(dir, file_name, rendered)?);
     }
 
     for recv in &cfg.recvs {
-        let id = recv.id.simple().to_string();
-        let file_name = filename_recv(&id);
+        let file_name = filename_recv(&recv.id.simple().to_string());
         keep.insert(file_name.clone());
-        let path = dir.join(&file_name);
+        let rendered = recv
+            .enabled
+            .then(|| render_recv(recv, &cfg.host_info_emulation));
+        changes.push(plan_fragment(dir, file_name, rendered)?);
+    }
+
+    // Fragments of entries that were removed from the config.
+    for name in rustban_fragments_in(dir)? {
+        if !keep.contains(&name) {
+            changes.push(plan_fragment(dir, name, None)?);
+        }
+    }
+
+    // Disabled entries without a file on disk are not worth showing.
+    changes
+        .retain(|change| change.kind != FragmentChangeKind::Unchanged || change.contents.is_some());
+    Ok(ApplyPlan {
+        dir: dir.to_path_buf(),
+        changes,
+        expected_nodes,
+    })
+}
+
+/// Computes the fragment writes and deletions for `cfg`; `ApplyPlan::write` performs them.
+pub fn plan_pipewire_fragments(cfg: &AppConfig) -> Result<ApplyPlan> {
+    plan_fragments_in(&pipewire_dropin_dir()?, cfg)
+}
+
+pub fn list_rustban_fragments() -> Result<Vec<String>> {
+    rustban_fragments_in(&pipewire_dropin_dir()?)
+}
 
-        if recv.enabled {
-            fs::write(path, render_recv(recv, &cfg.host_info_emulation))?;
-        } else if path.exists() {
-            fs::remove_file(path)?;
+fn rustban_fragments_in(dir: &Path) -> Result<Vec<String>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut names = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let Some(name) = entry.file_name().to_str().map(ToOwned::to_owned) else {
+            continue;
+        };
+        if is_rustban_fragment(&name) && entry.path().is_file() {
+            names.push(name);
         }
     }
 
-    cleanup_removed_entries(&dir, &keep)?;
+    names.sort();
+    Ok(names)
+}
+
+pub fn load_last_autolink_record() -> Result<Option<AutoLinkRecord>> {
+    let path = config_dir()?.join("last-autolink.json");
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let raw = fs::read_to_string(&path)?;
+    let record = serde_json::from_str(&raw)
+        .with_context(|| format!("Could not parse {}", path.display()))?;
+    Ok(Some(record))
+}
+
+fn save_last_autolink_record(summary: &AutoLinkSummary) -> Result<()> {
+    let dir = config_dir()?;
+    fs::create_dir_all(&dir)?;
+    let record = AutoLinkRecord {
+        finished_at_unix: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default(),
+        summary: summary.clone(),
+    };
+    fs::write(
+        dir.join("last-autolink.json"),
+        serde_json::to_string_pretty(&record)?,
+    )?;
     Ok(())
 }
 
-pub fn autolink_send_sources(cfg: &AppConfig) -> Result<AutoLinkSummary> {
-    let sends_to_link: Vec<_> = cfg
+/// A stream auto-linking keeps wired: a send fed by its source, or a recv feeding its sink.
+struct LinkJob<'a> {
+    /// The send or recv node; only its own links are reconciled.
+    node: &'a str,
+    /// The source of a send or the sink of a recv; empty when only old links are removed.
+    peer: &'a str,
+    is_send: bool,
+    channel_map: Option<&'a ChannelMap>,
+}
+
+impl LinkJob<'_> {
+    fn output_node(&self) -> &str {
+        if self.is_send {
+            self.peer
+        } else {
+            self.node
+        }
+    }
+
+    fn input_node(&self) -> &str {
+        if self.is_send {
+            self.node
+        } else {
+            self.peer
+        }
+    }
+
+    fn output_role(&self) -> &'static str {
+        if self.is_send {
+            "Source"
+        } else {
+            "Recv"
+        }
+    }
+
+    fn input_role(&self) -> &'static str {
+        if self.is_send {
+            "Send"
+        } else {
+            "Sink"
+        }
+    }
+
+    /// Whether `link` is one of this stream's links, on the side auto-linking manages.
+    fn owns(&self, link: &LinkEnds) -> bool {
+        if self.is_send {
+            link.input_node() == self.node
+        } else {
+            link.output_node() == self.node
+        }
+    }
+}
+
+/// Enabled sends with a source and recvs with a sink; with `managed`, also those whose peer
+/// was cleared but still have links to remove.
+fn link_jobs<'a>(cfg: &'a AppConfig, managed: &HashSet<LinkEnds>) -> Vec<LinkJob<'a>> {
+    let sends = cfg
         .sends
         .iter()
-        .filter(|send| send.enabled && !send.target_object.trim().is_empty())
+        .filter(|send| send.enabled)
+        .map(|send| LinkJob {
+            node: send.node_name.trim(),
+            peer: send.target_object.trim(),
+            is_send: true,
+            channel_map: send.channel_map.as_ref(),
+        });
+    let recvs = cfg
+        .recvs
+        .iter()
+        .filter(|recv| recv.enabled)
+        .map(|recv| LinkJob {
+            node: recv.node_name.trim(),
+            peer: recv.target_sink.trim(),
+            is_send: false,
+            channel_map: None,
+        });
+    sends
+        .chain(recvs)
+        .filter(|job| !job.peer.is_empty() || managed.iter().any(|link| job.owns(link)))
+        .collect()
+}
+
+/// Stream and peer nodes that auto-linking connects.
+pub fn autolinked_nodes(cfg: &AppConfig) -> Vec<String> {
+    let mut names: Vec<String> = link_jobs(cfg, &HashSet::new())
+        .iter()
+        .flat_map(|job| [job.node, job.peer])
+        .map(ToOwned::to_owned)
         .collect();
-    if sends_to_link.is_empty() {
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Nodes auto-linking needs that are not in the graph yet; an unreadable graph counts as
+/// everything missing. Recv nodes only exist while a stream arrives, so only their sinks are
+/// waited for.
+fn missing_autolink_nodes(cfg: &AppConfig) -> Vec<String> {
+    let topology = load_pipewire_topology().ok();
+    let mut missing: Vec<String> = link_jobs(cfg, &HashSet::new())
+        .iter()
+        .flat_map(|job| [job.is_send.then_some(job.node), Some(job.peer)])
+        .flatten()
+        .filter(|name| {
+            topology
+                .as_ref()
+                .is_none_or(|topology| !topology.nodes_by_name.contains_key(*name))
+        })
+        .map(ToOwned::to_owned)
+        .collect();
+    missing.sort();
+    missing.dedup();
+    missing
+}
+
+/// [`autolink_streams`] once the stream and peer nodes exist, e.g. right after a restart.
+/// Polls the graph with backoff for up to `timeout`, reporting each wait through `progress`;
+/// nodes still missing then end up in the summary's issues.
+pub fn autolink_streams_when_ready(
+    cfg: &AppConfig,
+    timeout: Duration,
+    progress: impl FnMut(&str),
+) -> Result<AutoLinkSummary> {
+    let started = Instant::now();
+    wait_for_nodes(
+        timeout,
+        || missing_autolink_nodes(cfg),
+        || started.elapsed(),
+        thread::sleep,
+        progress,
+    );
+    autolink_streams(cfg)
+}
+
+/// Polls `missing` until it reports no node or `elapsed` reaches `timeout`, sleeping between
+/// polls from [`AUTOLINK_POLL_START`], doubling up to [`AUTOLINK_POLL_MAX`].
+fn wait_for_nodes(
+    timeout: Duration,
+    mut missing: impl FnMut() -> Vec<String>,
+    elapsed: impl Fn() -> Duration,
+    mut sleep: impl FnMut(Duration),
+    mut progress: impl FnMut(&str),
+) {
+    let mut delay = AUTOLINK_POLL_START;
+    loop {
+        let missing = missing();
+        if missing.is_empty() || elapsed() >= timeout {
+            return;
+        }
+        progress(&format!(
+            "Waiting for PipeWire nodes ({:.1}s/{}s): {}",
+            elapsed().as_secs_f32(),
+            timeout.as_secs(),
+            missing.join(", ")
+        ));
+        sleep(delay.min(timeout.saturating_sub(elapsed())));
+        delay = (delay * 2).min(AUTOLINK_POLL_MAX);
+    }
+}
+
+/// Links each send to its source and each recv to its sink, removing links that no longer fit.
+pub fn autolink_streams(cfg: &AppConfig) -> Result<AutoLinkSummary> {
+    let summary = link_streams(cfg)?;
+    // The record only feeds `rustban status`; failing to write it must not fail the link pass.
+    let _ = save_last_autolink_record(&summary);
+    Ok(summary)
+}
+
+fn link_streams(cfg: &AppConfig) -> Result<AutoLinkSummary> {
+    let mut managed = load_managed_links()?;
+    let jobs = link_jobs(cfg, &managed);
+    // Links of streams that were removed or disabled went away with their node.
+    let managed_before = managed.len();
+    managed.retain(|link| jobs.iter().any(|job| job.owns(link)));
+    if jobs.is_empty() {
+        if managed.len() != managed_before {
+            save_managed_links(&managed)?;
+        }
         return Ok(AutoLinkSummary::default());
     }
 
     let topology = load_pipewire_topology()?;
     let mut summary = AutoLinkSummary::default();
 
-    for send in sends_to_link {
-        let source_node_name = send.target_object.trim();
-        let send_node_name = send.node_name.trim();
-
-        let Some(&source_node_id) = topology.nodes_by_name.get(source_node_name) else {
-            summary.issues.push(format!(
-                "Source `{source_node_name}` not found in PipeWire."
-            ));
+    for job in jobs {
+        let Some(&node_id) = topology.nodes_by_name.get(job.node) else {
+            // A recv node appears with its first packet; the watcher links it then.
+            if job.is_send {
+                summary.issues.push(format!(
+                    "Send node `{}` not found (try `Apply + restart`).",
+                    job.node
+                ));
+            }
             continue;
         };
-        let Some(&send_node_id) = topology.nodes_by_name.get(send_node_name) else {
-            summary.issues.push(format!(
-                "Send node `{send_node_name}` not found (try `Apply + restart`)."
-            ));
-            continue;
+        let desired: Vec<LinkEnds> = if job.peer.is_empty() {
+            Vec::new()
+        } else {
+            desired_links(&topology, &job, node_id, &mut summary)
+                .into_iter()
+                .map(|(output_port, input_port)| LinkEnds {
+                    output: format!("{}:{output_port}", job.output_node()),
+                    input: format!("{}:{input_port}", job.input_node()),
+                })
+                .collect()
         };
 
-        let source_ports: Vec<_> = topology
-            .ports_by_node
-            .get(&source_node_id)
-            .map(|ports| ports.iter().filter(|port| !port.is_input).collect())
-            .unwrap_or_default();
-        let send_ports: Vec<_> = topology
-            .ports_by_node
-            .get(&send_node_id)
-            .map(|ports| ports.iter().filter(|port| port.is_input).collect())
-            .unwrap_or_default();
-
-        if source_ports.is_empty() {
-            summary.issues.push(format!(
-                "Source `{source_node_name}` has no output audio ports."
-            ));
-            continue;
-        }
-        if send_ports.is_empty() {
-            summary
-                .issues
-                .push(format!("Send `{send_node_name}` has no input audio ports."));
-            continue;
-        }
-
-        let planned_links = plan_autolinks(&source_ports, &send_ports);
-        if planned_links.is_empty() {
-            summary
-                .issues
-                .push(format!("No compatible ports found for `{send_node_name}`."));
-            continue;
+        let existing = if job.is_send {
+            topology.links_into(node_id)
+        } else {
+            topology.links_into_sinks(node_id)
+        };
+        for link in stale_links(
+            &existing,
+            &desired,
+            &managed,
+            cfg.autolink.unlink_manual_links,
+        ) {
+            match remove_pw_link(&link) {
+                Ok(()) => {
+                    summary.links_removed += 1;
+                    managed.remove(&link);
+                }
+                Err(e) => summary.issues.push(format!("{link}: {e:#}")),
+            }
         }
+        // Links that are gone from the graph (e.g. the peer was unplugged) are no longer ours.
+        managed.retain(|link| !job.owns(link) || existing.contains(link));
 
-        for (source_port, send_port) in planned_links {
-            match ensure_pw_link(source_node_name, &source_port, send_node_name, &send_port) {
+        for link in desired {
+            match ensure_pw_link(&link.output, &link.input) {
                 Ok(true) => {
                     summary.links_created += 1;
+                    managed.insert(link);
                 }
                 Ok(false) => {}
-                Err(e) => summary.issues.push(format!(
-                    "{source_node_name}:{source_port} -> {send_node_name}:{send_port}: {e:#}"
-                )),
+                Err(e) => summary.issues.push(format!("{link}: {e:#}")),
             }
         }
     }
 
+    save_managed_links(&managed)?;
     Ok(summary)
 }
 
-fn cleanup_removed_entries(dir: &Path, keep: &HashSet<String>) -> Result<()> {
-    for entry in fs::read_dir(dir)? {
-        let entry = entry?;
-        let name = entry.file_name();
-        let Some(name) = name.to_str() else {
-            continue;
-        };
+/// Port pairs the job's nodes should be linked with, or none after reporting why in `summary`.
+fn desired_links(
+    topology: &PipewireTopology,
+    job: &LinkJob,
+    node_id: u32,
+    summary: &mut AutoLinkSummary,
+) -> Vec<(String, String)> {
+    let Some(&peer_id) = topology.nodes_by_name.get(job.peer) else {
+        let role = if job.is_send { "Source" } else { "Sink" };
+        summary
+            .issues
+            .push(format!("{role} `{}` not found in PipeWire.", job.peer));
+        return Vec::new();
+    };
+    let (output_id, input_id) = if job.is_send {
+        (peer_id, node_id)
+    } else {
+        (node_id, peer_id)
+    };
+    let ports = |id: u32, is_input: bool| -> Vec<&PipewirePort> {
+        topology
+            .ports_by_node
+            .get(&id)
+            .map(|ports| {
+                ports
+                    .iter()
+                    .filter(|port| port.is_input == is_input)
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let output_ports = ports(output_id, false);
+    let input_ports = ports(input_id, true);
+
+    if output_ports.is_empty() {
+        summary.issues.push(format!(
+            "{} `{}` has no output audio ports.",
+            job.output_role(),
+            job.output_node()
+        ));
+        return Vec::new();
+    }
+    if input_ports.is_empty() {
+        summary.issues.push(format!(
+            "{} `{}` has no input audio ports.",
+            job.input_role(),
+            job.input_node()
+        ));
+        return Vec::new();
+    }
 
-        if !is_rustban_fragment(name) {
-            continue;
+    let planned_links = match job.channel_map {
+        Some(map) => {
+            let (links, issues) = plan_mapped_links(&output_ports, &input_ports, map);
+            summary.issues.extend(
+                issues
+                    .into_iter()
+                    .map(|issue| format!("Channel map of `{}`: {issue}", job.node)),
+            );
+            links
         }
-
-        if keep.contains(name) {
-            continue;
+        None if job.is_send => plan_autolinks(&output_ports, &input_ports),
+        None => {
+            let (links, unmatched) = plan_matched_links(&output_ports, &input_ports);
+            if !unmatched.is_empty() && !links.is_empty() {
+                summary.issues.push(format!(
+                    "Sink `{}`: no matching channel of `{}` for {} (left unlinked).",
+                    job.peer,
+                    job.node,
+                    unmatched.join(", ")
+                ));
+            }
+            links
         }
+    };
+    if planned_links.is_empty() {
+        summary
+            .issues
+            .push(format!("No compatible ports found for `{}`.", job.node));
+    }
+    planned_links
+}
 
-        let path = entry.path();
-        if path.is_file() {
-            fs::remove_file(path)?;
-        }
+/// Existing links of a stream that should go: those RustBAN made, or any with `unlink_manual`.
+fn stale_links(
+    existing: &[LinkEnds],
+    desired: &[LinkEnds],
+    managed: &HashSet<LinkEnds>,
+    unlink_manual: bool,
+) -> Vec<LinkEnds> {
+    existing
+        .iter()
+        .filter(|link| !desired.contains(link))
+        .filter(|link| unlink_manual || managed.contains(link))
+        .cloned()
+        .collect()
+}
+
+fn managed_links_path() -> Result<PathBuf> {
+    Ok(config_dir()?.join("autolinks.json"))
+}
+
+/// Links created by auto-linking that still exist, as far as the last pass saw.
+fn load_managed_links() -> Result<HashSet<LinkEnds>> {
+    let path = managed_links_path()?;
+    if !path.exists() {
+        return Ok(HashSet::new());
     }
+    let raw = fs::read_to_string(&path)?;
+    serde_json::from_str(&raw).with_context(|| format!("Could not parse {}", path.display()))
+}
 
+fn save_managed_links(links: &HashSet<LinkEnds>) -> Result<()> {
+    let mut links: Vec<_> = links.iter().collect();
+    links.sort();
+    let path = managed_links_path()?;
+    fs::create_dir_all(path.parent().context("State file has no parent")?)?;
+    fs::write(&path, serde_json::to_string_pretty(&links)?)?;
     Ok(())
 }
 
@@ -217,26 +1001,105 @@ fn is_rustban_fragment(name: &str) -> bool {
     (is_send || is_recv) && name.ends_with(".conf")
 }
 
-fn load_pipewire_topology() -> Result<PipewireTopology> {
-    let mut topology = PipewireTopology::default();
+/// Runs `pw-dump <object_type>` and returns its JSON objects.
+fn pw_dump(object_type: &str) -> Result<Vec<Value>> {
+    let output = Command::new("pw-dump")
+        .arg(object_type)
+        .output()
+        .with_context(|| format!("Could not execute `pw-dump {object_type}`"))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "`pw-dump {object_type}` exited with status {}",
+            output.status
+        );
+    }
+
+    serde_json::from_slice(&output.stdout)
+        .with_context(|| format!("Could not parse JSON output from `pw-dump {object_type}`"))
+}
+
+/// Lists the `libpipewire-module-vban-*` modules loaded in the running PipeWire.
+pub fn list_vban_modules() -> Result<Vec<PipewireModule>> {
+    Ok(extract_vban_modules(pw_dump("Module")?.into_iter()))
+}
+
+fn extract_vban_modules(entries: impl Iterator<Item = Value>) -> Vec<PipewireModule> {
+    let mut modules = Vec::new();
+    for entry in entries {
+        let Some(id) = entry.get("id").and_then(value_to_u32) else {
+            continue;
+        };
+        let Some(info) = entry.get("info") else {
+            continue;
+        };
+        let Some(name) = info
+            .get("name")
+            .and_then(Value::as_str)
+            .filter(|name| name.starts_with("libpipewire-module-vban-"))
+        else {
+            continue;
+        };
+        modules.push(PipewireModule {
+            id,
+            name: name.to_string(),
+            args: info
+                .get("args")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+        });
+    }
+    modules
+}
 
-    let nodes_output = Command::new("pw-dump")
-        .arg("Node")
+/// Destroys a global object of the running PipeWire, e.g. a module loaded by the daemon.
+pub fn destroy_pipewire_object(id: u32) -> Result<()> {
+    let output = Command::new("pw-cli")
+        .args(["destroy", &id.to_string()])
         .output()
-        .context("Could not execute `pw-dump Node`")?;
-    if !nodes_output.status.success() {
-        anyhow::bail!("`pw-dump Node` exited with status {}", nodes_output.status);
+        .with_context(|| format!("Could not execute `pw-cli destroy {id}`"))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "`pw-cli destroy {id}` failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
     }
+    Ok(())
+}
+
+/// Looks up the graph state of each node name; names without a node are left out.
+pub fn load_node_runtimes(node_names: &[&str]) -> Result<HashMap<String, NodeRuntime>> {
+    let topology = load_pipewire_topology()?;
+    Ok(node_names
+        .iter()
+        .filter_map(|name| Some((name.to_string(), topology.node_runtime(name)?)))
+        .collect())
+}
+
+fn load_pipewire_topology() -> Result<PipewireTopology> {
+    Ok(parse_topology(
+        pw_dump("Node")?,
+        pw_dump("Port")?,
+        pw_dump("Link")?,
+    ))
+}
+
+fn parse_topology(
+    node_entries: Vec<Value>,
+    port_entries: Vec<Value>,
+    link_entries: Vec<Value>,
+) -> PipewireTopology {
+    let mut topology = PipewireTopology::default();
 
-    let node_entries: Vec<Value> = serde_json::from_slice(&nodes_output.stdout)
-        .context("Could not parse JSON output from `pw-dump Node`")?;
     for entry in node_entries {
         let Some(node_id) = entry.get("id").and_then(value_to_u32) else {
             continue;
         };
-        let Some(node_name) = entry
-            .get("info")
-            .and_then(|info| info.get("props"))
+        let Some(info) = entry.get("info") else {
+            continue;
+        };
+        let Some(node_name) = info
+            .get("props")
             .and_then(|props| props.get("node.name"))
             .and_then(Value::as_str)
             .map(str::trim)
@@ -248,18 +1111,43 @@ fn load_pipewire_topology() -> Result<PipewireTopology> {
         topology
             .nodes_by_name
             .insert(node_name.to_string(), node_id);
+        let media_class = info
+            .get("props")
+            .and_then(|props| props.get("media.class"))
+            .and_then(Value::as_str);
+        if media_class.is_some_and(|class| class.eq_ignore_ascii_case("Audio/Sink")) {
+            topology.sink_nodes.insert(node_id);
+        }
+        if let Some(state) = info.get("state").and_then(Value::as_str) {
+            let error = info
+                .get("error")
+                .and_then(Value::as_str)
+                .filter(|error| !error.is_empty())
+                .map(ToOwned::to_owned);
+            topology
+                .node_states
+                .insert(node_id, (state.to_string(), error));
+        }
     }
 
-    let ports_output = Command::new("pw-dump")
-        .arg("Port")
-        .output()
-        .context("Could not execute `pw-dump Port`")?;
-    if !ports_output.status.success() {
-        anyhow::bail!("`pw-dump Port` exited with status {}", ports_output.status);
+    for entry in link_entries {
+        let Some(info) = entry.get("info") else {
+            continue;
+        };
+        let (Some(output_node), Some(input_node)) = (
+            info.get("output-node-id").and_then(value_to_u32),
+            info.get("input-node-id").and_then(value_to_u32),
+        ) else {
+            continue;
+        };
+        topology.links.push(PipewireLink {
+            output_node,
+            output_port: info.get("output-port-id").and_then(value_to_u32),
+            input_node,
+            input_port: info.get("input-port-id").and_then(value_to_u32),
+        });
     }
 
-    let port_entries: Vec<Value> = serde_json::from_slice(&ports_output.stdout)
-        .context("Could not parse JSON output from `pw-dump Port`")?;
     for entry in port_entries {
         let Some(info) = entry.get("info") else {
             continue;
@@ -300,13 +1188,15 @@ fn load_pipewire_topology() -> Result<PipewireTopology> {
             .entry(node_id)
             .or_default()
             .push(PipewirePort {
+                id: entry.get("id").and_then(value_to_u32),
                 port_name: port_name.to_string(),
                 is_input,
                 channel,
+                index: props.get("port.id").and_then(value_to_u32),
             });
     }
 
-    Ok(topology)
+    topology
 }
 
 fn plan_autolinks(
@@ -327,9 +1217,86 @@ fn plan_autolinks(
     links
 }
 
+fn in_port_order<'a>(ports: &[&'a PipewirePort]) -> Vec<&'a PipewirePort> {
+    let mut ports = ports.to_vec();
+    ports.sort_by(|a, b| {
+        let position = |port: &PipewirePort| port.index.unwrap_or(u32::MAX);
+        position(a)
+            .cmp(&position(b))
+            .then_with(|| a.port_name.cmp(&b.port_name))
+    });
+    ports
+}
+
+/// Links the routes of `map`, counting channels in `port.id` order; also returns the routes
+/// that point at a channel the node does not have.
+fn plan_mapped_links(
+    source_ports: &[&PipewirePort],
+    send_ports: &[&PipewirePort],
+    map: &ChannelMap,
+) -> (Vec<(String, String)>, Vec<String>) {
+    let source_ports = in_port_order(source_ports);
+    let send_ports = in_port_order(send_ports);
+
+    let mut links = Vec::new();
+    let mut issues = Vec::new();
+    for route in &map.routes {
+        let source = source_ports.get(usize::from(route.source).wrapping_sub(1));
+        let send = send_ports.get(usize::from(route.send).wrapping_sub(1));
+        match (source, send) {
+            (Some(source), Some(send)) => {
+                links.push((source.port_name.clone(), send.port_name.clone()))
+            }
+            (None, _) => issues.push(format!(
+                "source channel {} does not exist ({} available).",
+                route.source,
+                source_ports.len()
+            )),
+            (_, None) => issues.push(format!(
+                "send channel {} does not exist ({} available).",
+                route.send,
+                send_ports.len()
+            )),
+        }
+    }
+
+    links.sort();
+    links.dedup();
+    (links, issues)
+}
+
+/// Links only ports whose channels match (a MONO output feeds FL and FR); also returns the
+/// input ports left without a match, e.g. the extra channels of a surround sink.
+fn plan_matched_links(
+    output_ports: &[&PipewirePort],
+    input_ports: &[&PipewirePort],
+) -> (Vec<(String, String)>, Vec<String>) {
+    let mut links = Vec::new();
+    let mut unmatched = Vec::new();
+    for input_port in in_port_order(input_ports) {
+        match matching_source_port(output_ports, input_port) {
+            Some(output_port) => {
+                links.push((output_port.port_name.clone(), input_port.port_name.clone()))
+            }
+            None => unmatched.push(input_port.port_name.clone()),
+        }
+    }
+
+    links.sort();
+    links.dedup();
+    (links, unmatched)
+}
+
 fn pick_source_port_for_send<'a>(
     source_ports: &'a [&PipewirePort],
     send_port: &PipewirePort,
+) -> Option<&'a PipewirePort> {
+    matching_source_port(source_ports, send_port).or_else(|| source_ports.first().copied())
+}
+
+fn matching_source_port<'a>(
+    source_ports: &'a [&PipewirePort],
+    send_port: &PipewirePort,
 ) -> Option<&'a PipewirePort> {
     let target_channel = send_port.channel.as_deref().unwrap_or_default();
 
@@ -351,23 +1318,16 @@ fn pick_source_port_for_send<'a>(
         }
     }
 
-    source_ports.first().copied()
+    None
 }
 
 fn channels_match(left: Option<&str>, right: Option<&str>) -> bool {
     matches!((left, right), (Some(a), Some(b)) if a.eq_ignore_ascii_case(b))
 }
 
-fn ensure_pw_link(
-    source_node_name: &str,
-    source_port_name: &str,
-    send_node_name: &str,
-    send_port_name: &str,
-) -> Result<bool> {
-    let source = format!("{source_node_name}:{source_port_name}");
-    let target = format!("{send_node_name}:{send_port_name}");
+fn ensure_pw_link(source: &str, target: &str) -> Result<bool> {
     let output = Command::new("pw-link")
-        .args([source.as_str(), target.as_str()])
+        .args([source, target])
         .output()
         .with_context(|| format!("Could not execute `pw-link {source} {target}`"))?;
 
@@ -387,6 +1347,20 @@ fn ensure_pw_link(
     anyhow::bail!("`pw-link` failed: {}", stderr.trim());
 }
 
+fn remove_pw_link(link: &LinkEnds) -> Result<()> {
+    let output = Command::new("pw-link")
+        .args(["-d", link.output.as_str(), link.input.as_str()])
+        .output()
+        .with_context(|| format!("Could not execute `pw-link -d {link}`"))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "`pw-link -d` failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
 fn value_to_u32(value: &Value) -> Option<u32> {
     value
         .as_u64()
@@ -412,16 +1386,7 @@ pub fn list_microphone_sources() -> Result<Vec<AudioSourceDevice>> {
 }
 
 fn list_microphone_sources_pw_dump() -> Result<Vec<AudioSourceDevice>> {
-    let output = Command::new("pw-dump")
-        .arg("Node")
-        .output()
-        .context("Could not execute `pw-dump Node`")?;
-    if !output.status.success() {
-        anyhow::bail!("`pw-dump Node` exited with status {}", output.status);
-    }
-
-    let entries: Vec<Value> = serde_json::from_slice(&output.stdout)
-        .context("Could not parse JSON output from `pw-dump Node`")?;
+    let entries = pw_dump("Node")?;
     Ok(extract_audio_sources(entries.into_iter()))
 }
 
@@ -456,62 +1421,171 @@ fn extract_audio_sources(entries: impl Iterator<Item = Value>) -> Vec<AudioSourc
         else {
             continue;
         };
+        let prop = |key: &str| {
+            props
+                .get(key)
+                .and_then(Value::as_str)
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+        };
 
-        let media_class = props
-            .get("media.class")
-            .and_then(Value::as_str)
+        let node_name = prop("node.name")
+            .or_else(|| prop("source_name"))
+            .or_else(|| prop("name"))
+            .or_else(|| entry.get("name").and_then(Value::as_str).map(str::trim))
             .unwrap_or_default();
-        if is_pw_dump_entry && !is_audio_source_media_class(media_class) {
+        if node_name.is_empty() {
             continue;
         }
 
-        let node_name = props
-            .get("node.name")
-            .or_else(|| props.get("source_name"))
-            .or_else(|| props.get("name"))
-            .or_else(|| entry.get("name"))
-            .and_then(Value::as_str)
-            .map(str::trim)
-            .unwrap_or_default();
-        if node_name.is_empty() || is_monitor_source(node_name) {
-            continue;
-        }
+        let (kind, node_name) = if is_pw_dump_entry {
+            let Some(kind) = source_kind(prop("media.class").unwrap_or_default()) else {
+                continue;
+            };
+            if kind == SourceKind::Input && is_monitor_source(node_name) {
+                continue;
+            }
+            (kind, node_name)
+        } else {
+            // pactl names a sink monitor `<sink>.monitor`; in the graph it is the sink node.
+            match node_name.strip_suffix(".monitor") {
+                Some(sink) => (SourceKind::Monitor, sink),
+                None => (SourceKind::Input, node_name),
+            }
+        };
 
-        let description = props
-            .get("node.description")
-            .or_else(|| props.get("device.description"))
-            .or_else(|| props.get("description"))
-            .or_else(|| entry.get("description"))
-            .and_then(Value::as_str)
-            .map(str::trim)
+        let description = prop("node.description")
+            .or_else(|| prop("device.description"))
+            .or_else(|| prop("description"))
+            .or_else(|| {
+                entry
+                    .get("description")
+                    .and_then(Value::as_str)
+                    .map(str::trim)
+            })
             .filter(|desc| !desc.is_empty())
             .unwrap_or(node_name);
+        let description = match kind {
+            SourceKind::Monitor if is_pw_dump_entry => format!("Monitor of {description}"),
+            SourceKind::Application => match (prop("application.name"), prop("media.name")) {
+                (Some(app), Some(media)) => format!("{app}: {media}"),
+                (Some(app), None) => app.to_string(),
+                _ => description.to_string(),
+            },
+            _ => description.to_string(),
+        };
 
         if seen_names.insert(node_name.to_string()) {
             devices.push(AudioSourceDevice {
                 node_name: node_name.to_string(),
-                description: description.to_string(),
+                description,
+                kind,
             });
         }
     }
 
-    devices.sort_by(|a, b| {
-        let a_key = a.description.to_lowercase();
-        let b_key = b.description.to_lowercase();
-        a_key
-            .cmp(&b_key)
+    sort_audio_sources(&mut devices);
+    devices
+}
+
+/// `pw-dump` sinks, or `pactl` ones when `pw-dump` is unavailable.
+pub fn list_playback_sinks() -> Result<Vec<AudioSinkDevice>> {
+    let pw_dump_error = match pw_dump("Node") {
+        Ok(entries) => return Ok(extract_audio_sinks(entries.into_iter())),
+        Err(e) => e,
+    };
+    list_playback_sinks_pactl().map_err(|pactl_error| {
+        anyhow::anyhow!(
+            "Could not list PipeWire sinks. pw-dump: {pw_dump_error:#} | pactl: {pactl_error:#}"
+        )
+    })
+}
+
+fn list_playback_sinks_pactl() -> Result<Vec<AudioSinkDevice>> {
+    let output = Command::new("pactl")
+        .args(["-f", "json", "list", "sinks"])
+        .output()
+        .context("Could not execute `pactl -f json list sinks`")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "`pactl -f json list sinks` exited with status {}",
+            output.status
+        );
+    }
+
+    let entries: Vec<Value> = serde_json::from_slice(&output.stdout)
+        .context("Could not parse JSON output from `pactl -f json list sinks`")?;
+    Ok(extract_audio_sinks(entries.into_iter()))
+}
+
+/// Sinks from `pw-dump` nodes (filtered by media.class) or `pactl list sinks` entries.
+fn extract_audio_sinks(entries: impl Iterator<Item = Value>) -> Vec<AudioSinkDevice> {
+    let mut sinks: Vec<AudioSinkDevice> = entries
+        .filter_map(|entry| {
+            let is_pw_dump_entry = entry.get("info").is_some();
+            let props = entry
+                .get("info")
+                .and_then(|info| info.get("props"))
+                .or_else(|| entry.get("properties"))?;
+            fn text(value: Option<&Value>) -> Option<&str> {
+                value
+                    .and_then(Value::as_str)
+                    .map(str::trim)
+                    .filter(|value| !value.is_empty())
+            }
+            let prop = |key: &str| text(props.get(key));
+            let is_sink =
+                prop("media.class").is_some_and(|class| class.eq_ignore_ascii_case("Audio/Sink"));
+            if is_pw_dump_entry && !is_sink {
+                return None;
+            }
+            let node_name = prop("node.name").or_else(|| text(entry.get("name")))?;
+            let description = prop("node.description")
+                .or_else(|| text(entry.get("description")))
+                .unwrap_or(node_name);
+            Some(AudioSinkDevice {
+                node_name: node_name.to_string(),
+                description: description.to_string(),
+            })
+        })
+        .collect();
+    sinks.sort_by(|a, b| {
+        a.description
+            .to_lowercase()
+            .cmp(&b.description.to_lowercase())
             .then_with(|| a.node_name.cmp(&b.node_name))
     });
+    sinks
+}
 
-    devices
+fn source_kind(media_class: &str) -> Option<SourceKind> {
+    let is = |class: &str| media_class.eq_ignore_ascii_case(class);
+    let is_virtual_source = media_class
+        .get(..13)
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case("Audio/Source/"));
+    if is("Audio/Source") || is_virtual_source {
+        Some(SourceKind::Input)
+    } else if is("Audio/Sink") {
+        Some(SourceKind::Monitor)
+    } else if is("Stream/Output/Audio") {
+        Some(SourceKind::Application)
+    } else {
+        None
+    }
 }
 
-fn is_audio_source_media_class(media_class: &str) -> bool {
-    media_class.eq_ignore_ascii_case("Audio/Source")
-        || media_class
-            .get(..13)
-            .map(|prefix| prefix.eq_ignore_ascii_case("Audio/Source/"))
-            .unwrap_or(false)
+/// Grouped by kind, then by description.
+fn sort_audio_sources(devices: &mut [AudioSourceDevice]) {
+    devices.sort_by(|a, b| {
+        a.kind
+            .cmp(&b.kind)
+            .then_with(|| {
+                a.description
+                    .to_lowercase()
+                    .cmp(&b.description.to_lowercase())
+            })
+            .then_with(|| a.node_name.cmp(&b.node_name))
+    });
 }
 
 fn merge_audio_sources(
@@ -527,14 +1601,7 @@ fn merge_audio_sources(
         }
     }
 
-    merged.sort_by(|a, b| {
-        let a_key = a.description.to_lowercase();
-        let b_key = b.description.to_lowercase();
-        a_key
-            .cmp(&b_key)
-            .then_with(|| a.node_name.cmp(&b.node_name))
-    });
-
+    sort_audio_sources(&mut merged);
     merged
 }
 
@@ -586,6 +1653,37 @@ mod tests {
         })
     }
 
+    fn node_entry(id: u32, node_name: &str, media_class: &str) -> Value {
+        json!({
+            "id": id,
+            "info": { "props": { "node.name": node_name, "media.class": media_class } }
+        })
+    }
+
+    /// A pw-dump port whose `audio.channel` is the name suffix, as PipeWire names them.
+    fn port_entry(id: u32, node: u32, port_name: &str, direction: &str) -> Value {
+        let channel = port_name.rsplit('_').next().unwrap_or_default();
+        json!({
+            "id": id,
+            "info": {
+                "direction": direction,
+                "props": { "node.id": node, "port.name": port_name, "audio.channel": channel }
+            }
+        })
+    }
+
+    /// A pw-dump link between (node id, port id) pairs.
+    fn link_entry(output: (u32, u32), input: (u32, u32)) -> Value {
+        json!({
+            "info": {
+                "output-node-id": output.0,
+                "output-port-id": output.1,
+                "input-node-id": input.0,
+                "input-port-id": input.1
+            }
+        })
+    }
+
     #[test]
     fn includes_audio_source_virtual_from_pw_dump() {
         let entries = vec![source_entry(
@@ -601,13 +1699,454 @@ mod tests {
 
     #[test]
     fn excludes_non_source_pw_dump_nodes() {
-        let entries = vec![source_entry(
-            "alsa_output.some_sink",
-            "Some Sink",
-            "Audio/Sink",
-        )];
+        let entries = vec![
+            source_entry("v4l2_input.webcam", "Webcam", "Video/Source"),
+            source_entry("firefox", "Firefox", "Stream/Input/Audio"),
+        ];
 
         let devices = extract_audio_sources(entries.into_iter());
         assert!(devices.is_empty());
     }
+
+    #[test]
+    fn lists_sink_monitors_and_application_outputs() {
+        let mut app = source_entry("Firefox", "Firefox", "Stream/Output/Audio");
+        app["info"]["props"]["application.name"] = json!("Firefox");
+        app["info"]["props"]["media.name"] = json!("Radio");
+        let entries = vec![
+            app,
+            source_entry("alsa_output.hdmi", "HDMI", "Audio/Sink"),
+            source_entry("alsa_input.usb", "USB Mic", "Audio/Source"),
+        ];
+
+        let devices = extract_audio_sources(entries.into_iter());
+        let listed: Vec<_> = devices
+            .iter()
+            .map(|device| {
+                (
+                    device.kind,
+                    device.node_name.as_str(),
+                    device.description.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            listed,
+            [
+                (SourceKind::Input, "alsa_input.usb", "USB Mic"),
+                (SourceKind::Monitor, "alsa_output.hdmi", "Monitor of HDMI"),
+                (SourceKind::Application, "Firefox", "Firefox: Radio"),
+            ]
+        );
+
+        let pactl = json!({ "name": "alsa_output.hdmi.monitor", "description": "Monitor of HDMI", "properties": {} });
+        let devices = extract_audio_sources(std::iter::once(pactl));
+        assert_eq!(devices[0].kind, SourceKind::Monitor);
+        assert_eq!(devices[0].node_name, "alsa_output.hdmi");
+    }
+
+    #[test]
+    fn recv_links_into_its_sink_by_channel() {
+        let topology = parse_topology(
+            vec![
+                node_entry(40, "vban-recv-a", "Audio/Source"),
+                node_entry(50, "alsa_output.hdmi", "Audio/Sink"),
+            ],
+            vec![
+                port_entry(41, 40, "capture_FR", "output"),
+                port_entry(42, 40, "capture_FL", "output"),
+                port_entry(51, 50, "playback_FL", "input"),
+                port_entry(52, 50, "playback_FR", "input"),
+                port_entry(53, 50, "monitor_FL", "output"),
+            ],
+            Vec::new(),
+        );
+        let cfg = AppConfig {
+            recvs: vec![crate::model::VbanRecv {
+                node_name: "vban-recv-a".into(),
+                target_sink: "alsa_output.hdmi".into(),
+                ..Default::default()
+            }],
+            ..AppConfig::default()
+        };
+        let jobs = link_jobs(&cfg, &HashSet::new());
+        let mut summary = AutoLinkSummary::default();
+
+        let links = desired_links(&topology, &jobs[0], 40, &mut summary);
+        assert_eq!(
+            links,
+            [
+                ("capture_FL".to_string(), "playback_FL".to_string()),
+                ("capture_FR".to_string(), "playback_FR".to_string())
+            ]
+        );
+        assert!(summary.issues.is_empty());
+        assert!(jobs[0].owns(&LinkEnds {
+            output: "vban-recv-a:capture_FL".into(),
+            input: "alsa_output.other:playback_FL".into(),
+        }));
+    }
+
+    #[test]
+    fn lists_playback_sinks_from_pw_dump_and_pactl() {
+        let sinks = extract_audio_sinks(
+            [
+                source_entry("alsa_output.hdmi", "HDMI", "Audio/Sink"),
+                source_entry("alsa_input.usb", "USB Mic", "Audio/Source"),
+            ]
+            .into_iter(),
+        );
+        assert_eq!(sinks.len(), 1);
+        assert_eq!(sinks[0].description, "HDMI");
+
+        let pactl =
+            json!({ "name": "alsa_output.usb", "description": "USB DAC", "properties": {} });
+        let sinks = extract_audio_sinks(std::iter::once(pactl));
+        assert_eq!(sinks[0].node_name, "alsa_output.usb");
+        assert_eq!(sinks[0].description, "USB DAC");
+    }
+
+    #[test]
+    fn recv_reconciliation_ignores_apps_recording_from_it() {
+        let topology = parse_topology(
+            vec![
+                node_entry(40, "vban-recv-a", "Audio/Source"),
+                node_entry(50, "alsa_output.hdmi", "Audio/Sink"),
+                node_entry(60, "call-app", "Stream/Input/Audio"),
+            ],
+            vec![
+                port_entry(41, 40, "capture_MONO", "output"),
+                port_entry(51, 50, "playback_FL", "input"),
+                port_entry(61, 60, "input_MONO", "input"),
+            ],
+            vec![
+                link_entry((40, 41), (50, 51)),
+                link_entry((40, 41), (60, 61)),
+            ],
+        );
+
+        assert_eq!(
+            topology.links_into_sinks(40),
+            [LinkEnds {
+                output: "vban-recv-a:capture_MONO".into(),
+                input: "alsa_output.hdmi:playback_FL".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn recv_into_surround_sink_links_only_matching_channels() {
+        let port = |name: &str, is_input: bool, index: u32, channel: &str| PipewirePort {
+            id: None,
+            port_name: name.to_string(),
+            is_input,
+            channel: Some(channel.into()),
+            index: Some(index),
+        };
+        let stereo = [
+            port("capture_FL", false, 0, "FL"),
+            port("capture_FR", false, 1, "FR"),
+        ];
+        let mono = [port("capture_MONO", false, 0, "MONO")];
+        let sink: Vec<_> = ["FL", "FR", "FC", "LFE", "RL", "RR"]
+            .iter()
+            .enumerate()
+            .map(|(i, channel)| port(&format!("playback_{channel}"), true, i as u32, channel))
+            .collect();
+        let sink: Vec<_> = sink.iter().collect();
+        let link = |a: &str, b: &str| (a.to_string(), b.to_string());
+
+        let (links, unmatched) = plan_matched_links(&stereo.iter().collect::<Vec<_>>(), &sink);
+        assert_eq!(
+            links,
+            [
+                link("capture_FL", "playback_FL"),
+                link("capture_FR", "playback_FR")
+            ]
+        );
+        assert_eq!(
+            unmatched,
+            ["playback_FC", "playback_LFE", "playback_RL", "playback_RR"]
+        );
+
+        let (links, unmatched) = plan_matched_links(&mono.iter().collect::<Vec<_>>(), &sink);
+        assert_eq!(
+            links,
+            [
+                link("capture_MONO", "playback_FL"),
+                link("capture_MONO", "playback_FR")
+            ]
+        );
+        assert_eq!(unmatched.len(), 4);
+    }
+
+    #[test]
+    fn plan_reports_each_fragment_change() {
+        let dir = std::env::temp_dir().join(format!("rustban-plan-{}", uuid::Uuid::new_v4()));
+        let mut cfg = AppConfig::default();
+        cfg.sends.push(Default::default());
+        cfg.recvs.push(Default::default());
+
+        let plan = plan_fragments_in(&dir, &cfg).unwrap();
+        assert_eq!(plan.summary(), "2 create");
+        assert!(plan.changes[0].diff.starts_with("--- /dev/null\n"));
+        plan.apply(false).unwrap();
+        assert!(!plan_fragments_in(&dir, &cfg).unwrap().has_changes());
+
+        fs::write(dir.join("99-rustban-send-stale.conf"), "").unwrap();
+        fs::write(dir.join("50-hand-written.conf"), "").unwrap();
+        cfg.sends[0].destination_ip = "10.0.0.7".into();
+        cfg.recvs[0].enabled = false;
+        let plan = plan_fragments_in(&dir, &cfg).unwrap();
+        let kinds: Vec<_> = plan.changes.iter().map(|c| c.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                FragmentChangeKind::Modify,
+                FragmentChangeKind::Delete,
+                FragmentChangeKind::Delete
+            ]
+        );
+        assert!(plan.changes[0]
+            .diff
+            .lines()
+            .any(|line| line.starts_with('+') && line.ends_with("destination.ip = \"10.0.0.7\"")));
+        assert_eq!(plan.changes[2].file_name, "99-rustban-send-stale.conf");
+
+        plan.apply(false).unwrap();
+        assert_eq!(
+            plan_fragments_in(&dir, &cfg).unwrap().summary(),
+            "1 unchanged"
+        );
+        assert!(dir.join("50-hand-written.conf").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn snapshot_restores_previous_fragments() {
+        let dir = std::env::temp_dir().join(format!("rustban-snapshot-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("99-rustban-send-a.conf"), "old a").unwrap();
+        fs::write(dir.join("99-rustban-send-b.conf"), "old b").unwrap();
+
+        let snapshot = FragmentSnapshot::take(&dir).unwrap();
+        write_atomically(&dir.join("99-rustban-send-a.conf"), "new a").unwrap();
+        fs::remove_file(dir.join("99-rustban-send-b.conf")).unwrap();
+        fs::write(dir.join("99-rustban-recv-c.conf"), "new c").unwrap();
+        snapshot.restore().unwrap();
+
+        assert_eq!(
+            rustban_fragments_in(&dir).unwrap(),
+            ["99-rustban-send-a.conf", "99-rustban-send-b.conf"]
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("99-rustban-send-a.conf")).unwrap(),
+            "old a"
+        );
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn node_runtime_reports_state_and_linked_nodes() {
+        let node = |id: u32, name: &str, class: &str, state: &str| {
+            let mut entry = node_entry(id, name, class);
+            entry["info"]["state"] = json!(state);
+            entry["info"]["error"] = Value::Null;
+            entry
+        };
+        let topology = parse_topology(
+            vec![
+                node(30, "alsa_input.usb", "Audio/Source", "running"),
+                node(40, "vban-send-a", "Audio/Sink", "running"),
+                node(50, "vban-recv-b", "Audio/Source", "idle"),
+                node(60, "alsa_output.hdmi", "Audio/Sink", "suspended"),
+            ],
+            Vec::new(),
+            vec![
+                link_entry((30, 31), (40, 41)),
+                link_entry((30, 32), (40, 42)),
+                link_entry((50, 51), (60, 61)),
+            ],
+        );
+
+        let send = topology.node_runtime("vban-send-a").unwrap();
+        assert_eq!((send.id, send.state.as_str()), (40, "running"));
+        assert_eq!(send.linked_nodes, ["alsa_input.usb"]);
+        let recv = topology.node_runtime(" vban-recv-b ").unwrap();
+        assert_eq!(recv.state, "idle");
+        assert_eq!(recv.error, None);
+        assert_eq!(recv.linked_nodes, ["alsa_output.hdmi"]);
+        assert!(topology.node_runtime("vban-send-missing").is_none());
+    }
+
+    /// Runs `wait_for_nodes` on a fake clock that only advances while sleeping; returns the
+    /// sleeps and the number of progress lines.
+    fn simulate_wait(timeout: Duration, ready_after_polls: usize) -> (Vec<Duration>, usize) {
+        use std::cell::{Cell, RefCell};
+        let clock = Cell::new(Duration::ZERO);
+        let sleeps = RefCell::new(Vec::new());
+        let mut polls = 0;
+        let mut progress_lines = 0;
+        wait_for_nodes(
+            timeout,
+            || {
+                polls += 1;
+                if polls > ready_after_polls {
+                    Vec::new()
+                } else {
+                    vec!["vban-send-a".to_string()]
+                }
+            },
+            || clock.get(),
+            |delay| {
+                clock.set(clock.get() + delay);
+                sleeps.borrow_mut().push(delay);
+            },
+            |_| progress_lines += 1,
+        );
+        (sleeps.into_inner(), progress_lines)
+    }
+
+    #[test]
+    fn node_wait_stops_once_nothing_is_missing() {
+        let ms = Duration::from_millis;
+        assert_eq!(simulate_wait(Duration::from_secs(15), 0), (vec![], 0));
+        assert_eq!(
+            simulate_wait(Duration::from_secs(15), 3),
+            (vec![ms(250), ms(500), ms(1_000)], 3)
+        );
+    }
+
+    #[test]
+    fn node_wait_backs_off_up_to_the_cap_and_respects_the_timeout() {
+        let ms = Duration::from_millis;
+        let (sleeps, progress_lines) = simulate_wait(Duration::from_secs(10), usize::MAX);
+        assert_eq!(
+            sleeps,
+            [
+                ms(250),
+                ms(500),
+                ms(1_000),
+                AUTOLINK_POLL_MAX,
+                AUTOLINK_POLL_MAX,
+                AUTOLINK_POLL_MAX,
+                AUTOLINK_POLL_MAX,
+                ms(250)
+            ]
+        );
+        assert_eq!(sleeps.iter().sum::<Duration>(), Duration::from_secs(10));
+        assert_eq!(progress_lines, sleeps.len());
+    }
+
+    #[test]
+    fn channel_map_routes_by_port_position() {
+        let port = |name: &str, is_input: bool, index: u32| PipewirePort {
+            id: None,
+            port_name: name.to_string(),
+            is_input,
+            channel: Some("AUX".into()),
+            index: Some(index),
+        };
+        let source = [
+            port("capture_AUX3", false, 3),
+            port("capture_AUX0", false, 0),
+            port("capture_AUX2", false, 2),
+            port("capture_AUX1", false, 1),
+        ];
+        let send = [port("input_FR", true, 1), port("input_FL", true, 0)];
+        let source: Vec<_> = source.iter().collect();
+        let send: Vec<_> = send.iter().collect();
+        let link = |a: &str, b: &str| (a.to_string(), b.to_string());
+
+        let map: ChannelMap = "3:1,4:2".parse().unwrap();
+        let (links, issues) = plan_mapped_links(&source, &send, &map);
+        assert_eq!(
+            links,
+            [
+                link("capture_AUX2", "input_FL"),
+                link("capture_AUX3", "input_FR")
+            ]
+        );
+        assert!(issues.is_empty());
+
+        let map: ChannelMap = "1:1,1:2,5:1,1:3".parse().unwrap();
+        let (links, issues) = plan_mapped_links(&source, &send, &map);
+        assert_eq!(
+            links,
+            [
+                link("capture_AUX0", "input_FL"),
+                link("capture_AUX0", "input_FR")
+            ]
+        );
+        assert_eq!(
+            issues,
+            [
+                "source channel 5 does not exist (4 available).",
+                "send channel 3 does not exist (2 available)."
+            ]
+        );
+    }
+
+    #[test]
+    fn only_managed_links_are_stale_unless_unlinking_manual_ones() {
+        let topology = parse_topology(
+            vec![
+                node_entry(30, "old_mic", "Audio/Source"),
+                node_entry(35, "new_mic", "Audio/Source"),
+                node_entry(40, "vban-send-a", "Audio/Sink"),
+            ],
+            vec![
+                port_entry(31, 30, "capture_MONO", "output"),
+                port_entry(36, 35, "capture_MONO", "output"),
+                port_entry(41, 40, "input_MONO", "input"),
+            ],
+            vec![
+                link_entry((30, 31), (40, 41)),
+                link_entry((35, 36), (40, 41)),
+            ],
+        );
+        let ends = |output: &str| LinkEnds {
+            output: output.to_string(),
+            input: "vban-send-a:input_MONO".to_string(),
+        };
+
+        let existing = topology.links_into(40);
+        assert_eq!(
+            existing,
+            [ends("old_mic:capture_MONO"), ends("new_mic:capture_MONO")]
+        );
+        assert_eq!(existing[0].input_node(), "vban-send-a");
+
+        let desired = [ends("new_mic:capture_MONO")];
+        assert!(stale_links(&existing, &desired, &HashSet::new(), false).is_empty());
+        let managed = HashSet::from([ends("old_mic:capture_MONO")]);
+        assert_eq!(
+            stale_links(&existing, &desired, &managed, false),
+            [ends("old_mic:capture_MONO")]
+        );
+        assert_eq!(stale_links(&existing, &[], &HashSet::new(), true), existing);
+    }
+
+    #[test]
+    fn keeps_only_vban_modules_from_pw_dump() {
+        let entries = vec![
+            json!({ "id": 3, "info": { "name": "libpipewire-module-rt", "args": null } }),
+            json!({
+                "id": 42,
+                "info": {
+                    "name": "libpipewire-module-vban-send",
+                    "args": "{ destination.ip = 10.0.0.9 }"
+                }
+            }),
+            json!({ "id": 43, "info": { "name": "libpipewire-module-vban-recv", "args": null } }),
+        ];
+
+        let modules = extract_vban_modules(entries.into_iter());
+        assert_eq!(modules.len(), 2);
+        assert_eq!(modules[0].id, 42);
+        assert_eq!(modules[0].args, "{ destination.ip = 10.0.0.9 }");
+        assert_eq!(modules[1].name, "libpipewire-module-vban-recv");
+        assert!(modules[1].args.is_empty());
+    }
 }
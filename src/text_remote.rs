@@ -0,0 +1,75 @@
+use anyhow::{bail, Context, Result};
+use std::{
+    net::{ToSocketAddrs, UdpSocket},
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use crate::{
+    model::{TextMacro, TextRemote, TextTarget},
+    vban::{VbanHeader, MAX_DATA_SIZE},
+};
+
+static FRAME_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+pub fn build_text_packet(stream_name: &str, frame_counter: u32, command: &str) -> Result<Vec<u8>> {
+    if command.len() > MAX_DATA_SIZE {
+        bail!(
+            "VBAN-TEXT command is {} bytes, the limit is {MAX_DATA_SIZE}",
+            command.len()
+        );
+    }
+
+    let header = VbanHeader::text(stream_name.trim(), frame_counter).to_bytes()?;
+    let mut packet = header.to_vec();
+    packet.extend_from_slice(command.as_bytes());
+    Ok(packet)
+}
+
+/// Sends `command` to `target` as a single VBAN-TEXT packet.
+pub fn send_text_command(target: &TextTarget, command: &str) -> Result<()> {
+    let command = command.trim();
+    if command.is_empty() {
+        bail!("Command is empty");
+    }
+
+    let frame_counter = FRAME_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let packet = build_text_packet(&target.stream_name, frame_counter, command)?;
+    let addr = (target.ip.trim(), target.port)
+        .to_socket_addrs()
+        .with_context(|| format!("Could not resolve `{}`", target.ip))?
+        .next()
+        .with_context(|| format!("No address for `{}`", target.ip))?;
+
+    let socket = UdpSocket::bind(("0.0.0.0", 0)).context("Could not open UDP socket")?;
+    socket
+        .send_to(&packet, addr)
+        .with_context(|| format!("Could not send VBAN-TEXT to {addr}"))?;
+    Ok(())
+}
+
+pub fn send_macro(remote: &TextRemote, text_macro: &TextMacro) -> Result<()> {
+    let target = remote
+        .targets
+        .iter()
+        .find(|target| target.id == text_macro.target_id)
+        .with_context(|| format!("Macro `{}` has no target", text_macro.name))?;
+    send_text_command(target, &text_macro.command)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vban::{SubProtocol, HEADER_SIZE};
+
+    #[test]
+    fn packet_carries_command_after_text_header() {
+        let packet = build_text_packet("Command1", 9, "Strip[0].Mute=1;").unwrap();
+        let header = VbanHeader::parse(&packet).unwrap();
+
+        assert_eq!(header.sub_protocol, SubProtocol::Text);
+        assert_eq!(header.stream_name, "Command1");
+        assert_eq!(header.frame_counter, 9);
+        assert_eq!(&packet[HEADER_SIZE..], b"Strip[0].Mute=1;");
+        assert!(build_text_packet("Command1", 0, &"x".repeat(MAX_DATA_SIZE + 1)).is_err());
+    }
+}
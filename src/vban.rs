@@ -0,0 +1,570 @@
+//! VBAN packet header codec.
+//!
+//! Every VBAN packet starts with the same 28-byte header:
+//!
+//! | offset | size | field                                           |
+//! |--------|------|-------------------------------------------------|
+//! | 0      | 4    | magic `VBAN`                                    |
+//! | 4      | 1    | sub-protocol (bits 5-7) + rate index (bits 0-4) |
+//! | 5      | 1    | samples per frame - 1                           |
+//! | 6      | 1    | channels - 1                                    |
+//! | 7      | 1    | format (bits 4-7) + data type (bits 0-2)        |
+//!
+//! The format bits are the codec for audio packets and mean something else for the other
+//! sub-protocols (the text encoding for VBAN-TEXT).
+//! | 8      | 16   | stream name, ASCII, zero padded                 |
+//! | 24     | 4    | frame counter, little endian                    |
+
+use anyhow::{bail, Result};
+
+pub const MAGIC: [u8; 4] = *b"VBAN";
+pub const HEADER_SIZE: usize = 28;
+pub const STREAM_NAME_SIZE: usize = 16;
+pub const MAX_DATA_SIZE: usize = 1436;
+pub const MAX_PACKET_SIZE: usize = HEADER_SIZE + MAX_DATA_SIZE;
+pub const MAX_SAMPLES_PER_FRAME: u16 = 256;
+pub const MAX_CHANNELS: u16 = 256;
+
+/// VBAN-TEXT stream type carried in the format bits of byte 7.
+pub const TEXT_FORMAT_UTF8: u8 = 0x10;
+/// Index of 256 kbps in the VBAN serial/text bit-rate table, the rate Voicemeeter uses for
+/// VBAN-TEXT.
+const TEXT_BIT_RATE_INDEX: u8 = 18;
+
+const SUB_PROTOCOL_MASK: u8 = 0xE0;
+const RATE_INDEX_MASK: u8 = 0x1F;
+const DATA_TYPE_MASK: u8 = 0x07;
+const CODEC_MASK: u8 = 0xF0;
+
+/// Official VBAN sample-rate table, indexed by the low 5 bits of byte 4.
+pub const SAMPLE_RATES: [u32; 21] = [
+    6_000, 12_000, 24_000, 48_000, 96_000, 192_000, 384_000, 8_000, 16_000, 32_000, 64_000,
+    128_000, 256_000, 512_000, 11_025, 22_050, 44_100, 88_200, 176_400, 352_800, 705_600,
+];
+
+pub fn sample_rate_from_index(index: u8) -> Option<u32> {
+    SAMPLE_RATES.get(usize::from(index)).copied()
+}
+
+pub fn sample_rate_index(rate: u32) -> Option<u8> {
+    SAMPLE_RATES
+        .iter()
+        .position(|&candidate| candidate == rate)
+        .and_then(|index| u8::try_from(index).ok())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubProtocol {
+    Audio,
+    Serial,
+    Text,
+    Service,
+    Undefined(u8),
+}
+
+impl SubProtocol {
+    pub fn from_bits(bits: u8) -> Self {
+        match bits & SUB_PROTOCOL_MASK {
+            0x00 => Self::Audio,
+            0x20 => Self::Serial,
+            0x40 => Self::Text,
+            0x60 => Self::Service,
+            other => Self::Undefined(other),
+        }
+    }
+
+    pub fn bits(self) -> u8 {
+        match self {
+            Self::Audio => 0x00,
+            Self::Serial => 0x20,
+            Self::Text => 0x40,
+            Self::Service => 0x60,
+            Self::Undefined(bits) => bits & SUB_PROTOCOL_MASK,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataType {
+    Byte8,
+    Int16,
+    Int24,
+    Int32,
+    Float32,
+    Float64,
+    Bits12,
+    Bits10,
+}
+
+impl DataType {
+    pub fn from_bits(bits: u8) -> Self {
+        match bits & DATA_TYPE_MASK {
+            0 => Self::Byte8,
+            1 => Self::Int16,
+            2 => Self::Int24,
+            3 => Self::Int32,
+            4 => Self::Float32,
+            5 => Self::Float64,
+            6 => Self::Bits12,
+            _ => Self::Bits10,
+        }
+    }
+
+    pub fn bits(self) -> u8 {
+        match self {
+            Self::Byte8 => 0,
+            Self::Int16 => 1,
+            Self::Int24 => 2,
+            Self::Int32 => 3,
+            Self::Float32 => 4,
+            Self::Float64 => 5,
+            Self::Bits12 => 6,
+            Self::Bits10 => 7,
+        }
+    }
+
+    /// Size of one sample on the wire, `None` for the packed 10/12-bit types.
+    pub fn sample_size(self) -> Option<usize> {
+        match self {
+            Self::Byte8 => Some(1),
+            Self::Int16 => Some(2),
+            Self::Int24 => Some(3),
+            Self::Int32 | Self::Float32 => Some(4),
+            Self::Float64 => Some(8),
+            Self::Bits12 | Self::Bits10 => None,
+        }
+    }
+
+    /// Appends one normalized sample (-1.0..=1.0) in this format, little endian.
+    pub fn encode_sample(self, sample: f32, out: &mut Vec<u8>) -> Result<()> {
+        let sample = sample.clamp(-1.0, 1.0);
+        match self {
+            Self::Byte8 => out.push(((sample * 127.0) + 128.0).round() as u8),
+            Self::Int16 => {
+                out.extend_from_slice(&((sample * 32_767.0).round() as i16).to_le_bytes())
+            }
+            Self::Int24 => {
+                let value = (sample * 8_388_607.0).round() as i32;
+                out.extend_from_slice(&value.to_le_bytes()[..3]);
+            }
+            Self::Int32 => out.extend_from_slice(
+                &((f64::from(sample) * 2_147_483_647.0).round() as i32).to_le_bytes(),
+            ),
+            Self::Float32 => out.extend_from_slice(&sample.to_le_bytes()),
+            Self::Float64 => out.extend_from_slice(&f64::from(sample).to_le_bytes()),
+            Self::Bits12 | Self::Bits10 => bail!("{} samples are not supported", self.label()),
+        }
+        Ok(())
+    }
+
+    /// Reads one little-endian sample in this format back to -1.0..=1.0.
+    pub fn decode_sample(self, bytes: &[u8]) -> Result<f32> {
+        let size = self.sample_size().unwrap_or(0);
+        if bytes.len() < size {
+            bail!("Sample needs {size} bytes, got {}", bytes.len());
+        }
+        Ok(match self {
+            Self::Byte8 => (f32::from(bytes[0]) - 128.0) / 128.0,
+            Self::Int16 => f32::from(i16::from_le_bytes([bytes[0], bytes[1]])) / 32_768.0,
+            Self::Int24 => {
+                let value = i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]) >> 8;
+                value as f32 / 8_388_608.0
+            }
+            Self::Int32 => {
+                let value = i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                (f64::from(value) / 2_147_483_648.0) as f32
+            }
+            Self::Float32 => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            Self::Float64 => {
+                let mut raw = [0u8; 8];
+                raw.copy_from_slice(&bytes[..8]);
+                f64::from_le_bytes(raw) as f32
+            }
+            Self::Bits12 | Self::Bits10 => bail!("{} samples are not supported", self.label()),
+        })
+    }
+
+    /// Maps a PipeWire `audio.format` (as used by `VbanSend`) to its VBAN data type. VBAN 8-bit
+    /// PCM is unsigned, so only `U8` maps to `Byte8`.
+    pub fn from_audio_format(format: &str) -> Option<Self> {
+        match format.trim().to_ascii_uppercase().as_str() {
+            "U8" => Some(Self::Byte8),
+            "S16LE" | "S16" => Some(Self::Int16),
+            "S24LE" | "S24" => Some(Self::Int24),
+            "S32LE" | "S32" => Some(Self::Int32),
+            "F32LE" | "F32" => Some(Self::Float32),
+            "F64LE" | "F64" => Some(Self::Float64),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Byte8 => "8-bit",
+            Self::Int16 => "16-bit",
+            Self::Int24 => "24-bit",
+            Self::Int32 => "32-bit",
+            Self::Float32 => "32-bit float",
+            Self::Float64 => "64-bit float",
+            Self::Bits12 => "12-bit",
+            Self::Bits10 => "10-bit",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    Pcm,
+    Vbca,
+    Vbcv,
+    User,
+    Undefined(u8),
+}
+
+impl Codec {
+    pub fn from_bits(bits: u8) -> Self {
+        match bits & CODEC_MASK {
+            0x00 => Self::Pcm,
+            0x10 => Self::Vbca,
+            0x20 => Self::Vbcv,
+            0xF0 => Self::User,
+            other => Self::Undefined(other),
+        }
+    }
+
+    pub fn bits(self) -> u8 {
+        match self {
+            Self::Pcm => 0x00,
+            Self::Vbca => 0x10,
+            Self::Vbcv => 0x20,
+            Self::User => 0xF0,
+            Self::Undefined(bits) => bits & CODEC_MASK,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VbanHeader {
+    pub sub_protocol: SubProtocol,
+    /// Sample-rate index for audio, bit-rate index for serial/text.
+    pub rate_index: u8,
+    /// 1..=256
+    pub samples_per_frame: u16,
+    /// 1..=256
+    pub channels: u16,
+    pub data_type: DataType,
+    /// High bits of byte 7, see [`Self::codec`] for audio packets.
+    pub format_bits: u8,
+    pub stream_name: String,
+    pub frame_counter: u32,
+}
+
+impl VbanHeader {
+    /// Builds a PCM audio header; fails when `rate` is not in the VBAN table.
+    pub fn audio(
+        stream_name: &str,
+        rate: u32,
+        channels: u16,
+        samples_per_frame: u16,
+        data_type: DataType,
+    ) -> Result<Self> {
+        let Some(rate_index) = sample_rate_index(rate) else {
+            bail!("Sample rate {rate} Hz is not supported by VBAN");
+        };
+
+        let header = Self {
+            sub_protocol: SubProtocol::Audio,
+            rate_index,
+            samples_per_frame,
+            channels,
+            data_type,
+            format_bits: Codec::Pcm.bits(),
+            stream_name: stream_name.to_string(),
+            frame_counter: 0,
+        };
+        header.validate()?;
+        Ok(header)
+    }
+
+    /// Builds a UTF-8 VBAN-TEXT header as sent by Voicemeeter remote tools.
+    pub fn text(stream_name: &str, frame_counter: u32) -> Self {
+        Self {
+            sub_protocol: SubProtocol::Text,
+            rate_index: TEXT_BIT_RATE_INDEX,
+            samples_per_frame: 1,
+            channels: 1,
+            data_type: DataType::Byte8,
+            format_bits: TEXT_FORMAT_UTF8,
+            stream_name: stream_name.to_string(),
+            frame_counter,
+        }
+    }
+
+    pub fn parse(packet: &[u8]) -> Result<Self> {
+        if packet.len() < HEADER_SIZE {
+            bail!(
+                "Packet too short for a VBAN header ({} < {HEADER_SIZE} bytes)",
+                packet.len()
+            );
+        }
+        if packet[..4] != MAGIC {
+            bail!("Missing VBAN magic");
+        }
+
+        Ok(Self {
+            sub_protocol: SubProtocol::from_bits(packet[4]),
+            rate_index: packet[4] & RATE_INDEX_MASK,
+            samples_per_frame: u16::from(packet[5]) + 1,
+            channels: u16::from(packet[6]) + 1,
+            data_type: DataType::from_bits(packet[7]),
+            format_bits: packet[7] & CODEC_MASK,
+            stream_name: decode_stream_name(&packet[8..24]),
+            frame_counter: u32::from_le_bytes([packet[24], packet[25], packet[26], packet[27]]),
+        })
+    }
+
+    pub fn to_bytes(&self) -> Result<[u8; HEADER_SIZE]> {
+        self.validate()?;
+
+        let mut out = [0u8; HEADER_SIZE];
+        out[..4].copy_from_slice(&MAGIC);
+        out[4] = self.sub_protocol.bits() | self.rate_index;
+        out[5] = (self.samples_per_frame - 1) as u8;
+        out[6] = (self.channels - 1) as u8;
+        out[7] = (self.format_bits & CODEC_MASK) | self.data_type.bits();
+        out[8..24].copy_from_slice(&encode_stream_name(&self.stream_name)?);
+        out[24..].copy_from_slice(&self.frame_counter.to_le_bytes());
+        Ok(out)
+    }
+
+    /// Audio codec, `None` for the other sub-protocols.
+    pub fn codec(&self) -> Option<Codec> {
+        (self.sub_protocol == SubProtocol::Audio).then(|| Codec::from_bits(self.format_bits))
+    }
+
+    /// Sample rate in Hz, only meaningful for audio packets.
+    pub fn sample_rate(&self) -> Option<u32> {
+        sample_rate_from_index(self.rate_index)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.rate_index > RATE_INDEX_MASK {
+            bail!("Rate index {} does not fit in 5 bits", self.rate_index);
+        }
+        if !(1..=MAX_SAMPLES_PER_FRAME).contains(&self.samples_per_frame) {
+            bail!(
+                "Samples per frame must be within 1..={MAX_SAMPLES_PER_FRAME}, got {}",
+                self.samples_per_frame
+            );
+        }
+        if !(1..=MAX_CHANNELS).contains(&self.channels) {
+            bail!(
+                "Channels must be within 1..={MAX_CHANNELS}, got {}",
+                self.channels
+            );
+        }
+        encode_stream_name(&self.stream_name)?;
+        Ok(())
+    }
+}
+
+/// Encodes a stream name into the zero-padded 16-byte header field.
+pub fn encode_stream_name(name: &str) -> Result<[u8; STREAM_NAME_SIZE]> {
+    if !name.is_ascii() {
+        bail!("VBAN stream name `{name}` must be ASCII");
+    }
+    if name.len() > STREAM_NAME_SIZE {
+        bail!("VBAN stream name `{name}` is longer than {STREAM_NAME_SIZE} characters");
+    }
+
+    let mut out = [0u8; STREAM_NAME_SIZE];
+    out[..name.len()].copy_from_slice(name.as_bytes());
+    Ok(out)
+}
+
+/// Decodes a zero-padded header string, stopping at the first NUL.
+pub fn decode_stream_name(raw: &[u8]) -> String {
+    let end = raw.iter().position(|&b| b == 0).unwrap_or(raw.len());
+    String::from_utf8_lossy(&raw[..end]).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Voicemeeter "Stream1", 48 kHz, 256 samples, stereo, INT16 PCM, frame 0x01020304.
+    const VOICEMEETER_STREAM1: [u8; HEADER_SIZE] = [
+        b'V', b'B', b'A', b'N', 0x03, 0xFF, 0x01, 0x01, b'S', b't', b'r', b'e', b'a', b'm', b'1',
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0x04, 0x03, 0x02, 0x01,
+    ];
+
+    #[test]
+    fn parses_known_audio_header() {
+        let header = VbanHeader::parse(&VOICEMEETER_STREAM1).unwrap();
+
+        assert_eq!(header.sub_protocol, SubProtocol::Audio);
+        assert_eq!(header.rate_index, 3);
+        assert_eq!(header.sample_rate(), Some(48_000));
+        assert_eq!(header.samples_per_frame, 256);
+        assert_eq!(header.channels, 2);
+        assert_eq!(header.data_type, DataType::Int16);
+        assert_eq!(header.codec(), Some(Codec::Pcm));
+        assert_eq!(header.stream_name, "Stream1");
+        assert_eq!(header.frame_counter, 0x0102_0304);
+    }
+
+    #[test]
+    fn serializes_known_audio_header() {
+        let mut header = VbanHeader::audio("Stream1", 48_000, 2, 256, DataType::Int16).unwrap();
+        header.frame_counter = 0x0102_0304;
+
+        assert_eq!(header.to_bytes().unwrap(), VOICEMEETER_STREAM1);
+    }
+
+    #[test]
+    fn parses_header_with_trailing_payload() {
+        let mut packet = VOICEMEETER_STREAM1.to_vec();
+        packet.extend_from_slice(&[0xAA; 1024]);
+
+        let header = VbanHeader::parse(&packet).unwrap();
+        assert_eq!(header.stream_name, "Stream1");
+    }
+
+    #[test]
+    fn parses_full_length_stream_name_and_mono_float() {
+        let packet = [
+            b'V', b'B', b'A', b'N', 0x10, 0x7F, 0x00, 0x04, b'A', b'B', b'C', b'D', b'E', b'F',
+            b'G', b'H', b'I', b'J', b'K', b'L', b'M', b'N', b'O', b'P', 0xFF, 0xFF, 0xFF, 0xFF,
+        ];
+
+        let header = VbanHeader::parse(&packet).unwrap();
+        assert_eq!(header.sample_rate(), Some(44_100));
+        assert_eq!(header.samples_per_frame, 128);
+        assert_eq!(header.channels, 1);
+        assert_eq!(header.data_type, DataType::Float32);
+        assert_eq!(header.stream_name, "ABCDEFGHIJKLMNOP");
+        assert_eq!(header.frame_counter, u32::MAX);
+        assert_eq!(header.to_bytes().unwrap(), packet);
+    }
+
+    #[test]
+    fn parses_service_and_text_sub_protocols() {
+        let mut packet = VOICEMEETER_STREAM1;
+        packet[4] = 0x60;
+        assert_eq!(
+            VbanHeader::parse(&packet).unwrap().sub_protocol,
+            SubProtocol::Service
+        );
+
+        packet[4] = 0xA0;
+        assert_eq!(
+            VbanHeader::parse(&packet).unwrap().sub_protocol,
+            SubProtocol::Undefined(0xA0)
+        );
+    }
+
+    #[test]
+    fn parses_known_text_header_without_an_audio_codec() {
+        // Voicemeeter "Command1" VBAN-TEXT, 256 kbps, UTF-8, frame 3.
+        let packet = [
+            b'V', b'B', b'A', b'N', 0x52, 0x00, 0x00, 0x10, b'C', b'o', b'm', b'm', b'a', b'n',
+            b'd', b'1', 0, 0, 0, 0, 0, 0, 0, 0, 0x03, 0x00, 0x00, 0x00,
+        ];
+
+        let header = VbanHeader::parse(&packet).unwrap();
+        assert_eq!(header.sub_protocol, SubProtocol::Text);
+        assert_eq!(header.rate_index, TEXT_BIT_RATE_INDEX);
+        assert_eq!(header.format_bits, TEXT_FORMAT_UTF8);
+        assert_eq!(header.codec(), None);
+        assert_eq!(header.data_type, DataType::Byte8);
+        assert_eq!(header.stream_name, "Command1");
+        assert_eq!(header, VbanHeader::text("Command1", 3));
+        assert_eq!(header.to_bytes().unwrap(), packet);
+    }
+
+    #[test]
+    fn serializes_text_header_like_voicemeeter() {
+        let header = VbanHeader::text("Command1", 3);
+        let bytes = header.to_bytes().unwrap();
+
+        assert_eq!(
+            &bytes[..8],
+            &[b'V', b'B', b'A', b'N', 0x52, 0x00, 0x00, 0x10]
+        );
+        assert_eq!(&bytes[8..16], b"Command1");
+        assert_eq!(&bytes[24..], &[3, 0, 0, 0]);
+    }
+
+    #[test]
+    fn rejects_short_packets_and_bad_magic() {
+        assert!(VbanHeader::parse(&VOICEMEETER_STREAM1[..27]).is_err());
+
+        let mut packet = VOICEMEETER_STREAM1;
+        packet[0] = b'X';
+        assert!(VbanHeader::parse(&packet).is_err());
+    }
+
+    #[test]
+    fn sample_rate_table_round_trips() {
+        assert_eq!(SAMPLE_RATES.len(), 21);
+        for (index, &rate) in SAMPLE_RATES.iter().enumerate() {
+            assert_eq!(sample_rate_index(rate), Some(index as u8));
+            assert_eq!(sample_rate_from_index(index as u8), Some(rate));
+        }
+        assert_eq!(sample_rate_index(44_000), None);
+        assert_eq!(sample_rate_from_index(21), None);
+        assert_eq!(sample_rate_from_index(31), None);
+    }
+
+    #[test]
+    fn data_type_and_codec_bits_round_trip() {
+        for bits in 0..8u8 {
+            assert_eq!(DataType::from_bits(bits).bits(), bits);
+        }
+        for bits in (0..16u8).map(|nibble| nibble << 4) {
+            assert_eq!(Codec::from_bits(bits).bits(), bits);
+        }
+        assert_eq!(Codec::from_bits(0xF3), Codec::User);
+        assert_eq!(DataType::from_bits(0xF3), DataType::Int32);
+        assert_eq!(DataType::from_audio_format("s24le"), Some(DataType::Int24));
+        assert_eq!(DataType::from_audio_format("S24_32LE"), None);
+        assert_eq!(DataType::from_audio_format("u8"), Some(DataType::Byte8));
+        assert_eq!(DataType::from_audio_format("S8"), None);
+    }
+
+    #[test]
+    fn encodes_samples_little_endian() {
+        let mut out = Vec::new();
+        DataType::Int16.encode_sample(1.0, &mut out).unwrap();
+        DataType::Int16.encode_sample(-1.0, &mut out).unwrap();
+        DataType::Int24.encode_sample(-1.0, &mut out).unwrap();
+        DataType::Byte8.encode_sample(0.0, &mut out).unwrap();
+        DataType::Float32.encode_sample(2.0, &mut out).unwrap();
+        assert_eq!(
+            out,
+            vec![0xFF, 0x7F, 0x01, 0x80, 0x01, 0x00, 0x80, 0x80, 0x00, 0x00, 0x80, 0x3F]
+        );
+        assert!(DataType::Bits12.encode_sample(0.0, &mut out).is_err());
+
+        assert_eq!(
+            DataType::Int16.decode_sample(&out[2..4]).unwrap(),
+            -32_767.0 / 32_768.0
+        );
+        assert_eq!(
+            DataType::Int24.decode_sample(&out[4..7]).unwrap(),
+            -8_388_607.0 / 8_388_608.0
+        );
+        assert_eq!(DataType::Byte8.decode_sample(&out[7..8]).unwrap(), 0.0);
+        assert_eq!(DataType::Float32.decode_sample(&out[8..]).unwrap(), 1.0);
+        assert!(DataType::Int32.decode_sample(&out[..2]).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_headers_on_serialize() {
+        assert!(VbanHeader::audio("x", 44_000, 2, 256, DataType::Int16).is_err());
+        assert!(VbanHeader::audio("x", 48_000, 0, 256, DataType::Int16).is_err());
+        assert!(VbanHeader::audio("x", 48_000, 2, 257, DataType::Int16).is_err());
+        assert!(VbanHeader::audio("seventeen-chars!!", 48_000, 2, 256, DataType::Int16).is_err());
+        assert!(VbanHeader::audio("café", 48_000, 2, 256, DataType::Int16).is_err());
+    }
+}
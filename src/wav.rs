@@ -0,0 +1,307 @@
+use anyhow::{bail, Context, Result};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use crate::vban::{Codec, DataType, VbanHeader};
+
+const WAV_HEADER_SIZE: usize = 44;
+const WAVE_FORMAT_PCM: u16 = 1;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 3;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+/// RIFF sizes are 32-bit; keep the data chunk below that.
+pub const MAX_WAV_DATA: u64 = u32::MAX as u64 - WAV_HEADER_SIZE as u64;
+
+/// Sample layout shared by a WAV file and a VBAN PCM stream.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WavFormat {
+    pub rate: u32,
+    pub channels: u16,
+    pub data_type: DataType,
+}
+
+impl WavFormat {
+    pub fn from_header(header: &VbanHeader) -> Result<Self> {
+        match header.codec() {
+            Some(Codec::Pcm) => {}
+            Some(codec) => bail!("Codec {codec:?} is not PCM"),
+            None => bail!("Not an audio packet"),
+        }
+        let Some(rate) = header.sample_rate() else {
+            bail!("Header has no valid sample rate");
+        };
+        if header.data_type.sample_size().is_none() {
+            bail!("{} samples are not supported", header.data_type.label());
+        }
+        Ok(Self {
+            rate,
+            channels: header.channels,
+            data_type: header.data_type,
+        })
+    }
+
+    pub fn frame_size(self) -> usize {
+        usize::from(self.channels) * self.data_type.sample_size().unwrap_or(1)
+    }
+
+    pub fn label(self) -> String {
+        format!(
+            "{} Hz, {}ch, {}",
+            self.rate,
+            self.channels,
+            self.data_type.label()
+        )
+    }
+
+    /// Canonical 44-byte RIFF/WAVE header; VBAN PCM is already interleaved little endian.
+    fn wav_header(self, data_bytes: u32) -> [u8; WAV_HEADER_SIZE] {
+        let sample_size = self.data_type.sample_size().unwrap_or(1) as u16;
+        let block_align = self.channels * sample_size;
+        let format_tag = match self.data_type {
+            DataType::Float32 | DataType::Float64 => WAVE_FORMAT_IEEE_FLOAT,
+            _ => WAVE_FORMAT_PCM,
+        };
+
+        let mut header = [0u8; WAV_HEADER_SIZE];
+        header[0..4].copy_from_slice(b"RIFF");
+        header[4..8].copy_from_slice(&(data_bytes + 36).to_le_bytes());
+        header[8..12].copy_from_slice(b"WAVE");
+        header[12..16].copy_from_slice(b"fmt ");
+        header[16..20].copy_from_slice(&16u32.to_le_bytes());
+        header[20..22].copy_from_slice(&format_tag.to_le_bytes());
+        header[22..24].copy_from_slice(&self.channels.to_le_bytes());
+        header[24..28].copy_from_slice(&self.rate.to_le_bytes());
+        header[28..32].copy_from_slice(&(self.rate * u32::from(block_align)).to_le_bytes());
+        header[32..34].copy_from_slice(&block_align.to_le_bytes());
+        header[34..36].copy_from_slice(&(sample_size * 8).to_le_bytes());
+        header[36..40].copy_from_slice(b"data");
+        header[40..44].copy_from_slice(&data_bytes.to_le_bytes());
+        header
+    }
+
+    /// Decodes a `fmt ` chunk body.
+    fn parse_fmt(chunk: &[u8]) -> Result<Self> {
+        if chunk.len() < 16 {
+            bail!("WAV fmt chunk is too short");
+        }
+        let u16_at = |i: usize| u16::from_le_bytes([chunk[i], chunk[i + 1]]);
+        let mut format_tag = u16_at(0);
+        if format_tag == WAVE_FORMAT_EXTENSIBLE && chunk.len() >= 26 {
+            // The sub-format GUID starts with the actual format tag.
+            format_tag = u16_at(24);
+        }
+        let channels = u16_at(2);
+        let rate = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]);
+        let bits = u16_at(14);
+
+        let data_type = match (format_tag, bits) {
+            (WAVE_FORMAT_PCM, 8) => DataType::Byte8,
+            (WAVE_FORMAT_PCM, 16) => DataType::Int16,
+            (WAVE_FORMAT_PCM, 24) => DataType::Int24,
+            (WAVE_FORMAT_PCM, 32) => DataType::Int32,
+            (WAVE_FORMAT_IEEE_FLOAT, 32) => DataType::Float32,
+            (WAVE_FORMAT_IEEE_FLOAT, 64) => DataType::Float64,
+            _ => bail!("Unsupported WAV encoding (format {format_tag:#06x}, {bits}-bit)"),
+        };
+        if channels == 0 {
+            bail!("WAV file has no channels");
+        }
+        Ok(Self {
+            rate,
+            channels,
+            data_type,
+        })
+    }
+}
+
+pub struct WavWriter {
+    file: BufWriter<File>,
+    format: WavFormat,
+    data_bytes: u64,
+}
+
+impl WavWriter {
+    pub fn create(path: &Path, format: WavFormat) -> Result<Self> {
+        let file =
+            File::create(path).with_context(|| format!("Could not create `{}`", path.display()))?;
+        let mut file = BufWriter::new(file);
+        file.write_all(&format.wav_header(0))?;
+        Ok(Self {
+            file,
+            format,
+            data_bytes: 0,
+        })
+    }
+
+    pub fn format(&self) -> WavFormat {
+        self.format
+    }
+
+    pub fn data_bytes(&self) -> u64 {
+        self.data_bytes
+    }
+
+    pub fn append(&mut self, data: &[u8]) -> Result<()> {
+        self.file.write_all(data)?;
+        self.data_bytes += data.len() as u64;
+        Ok(())
+    }
+
+    /// Patches the RIFF sizes and flushes; returns the audio data size.
+    pub fn finish(mut self) -> Result<u64> {
+        let header = self.format.wav_header(self.data_bytes as u32);
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(&header)?;
+        self.file.flush()?;
+        Ok(self.data_bytes)
+    }
+}
+
+pub struct WavReader<R> {
+    reader: R,
+    format: WavFormat,
+    data_start: u64,
+    data_len: u64,
+    position: u64,
+}
+
+impl WavReader<BufReader<File>> {
+    pub fn open(path: &Path) -> Result<Self> {
+        let file =
+            File::open(path).with_context(|| format!("Could not open `{}`", path.display()))?;
+        Self::new(BufReader::new(file))
+            .with_context(|| format!("`{}` is not a usable WAV file", path.display()))
+    }
+}
+
+impl<R: Read + Seek> WavReader<R> {
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut riff = [0u8; 12];
+        reader.read_exact(&mut riff).context("File is too short")?;
+        if &riff[0..4] != b"RIFF" || &riff[8..12] != b"WAVE" {
+            bail!("Missing RIFF/WAVE signature");
+        }
+
+        let mut format = None;
+        loop {
+            let mut chunk = [0u8; 8];
+            reader
+                .read_exact(&mut chunk)
+                .context("No data chunk found")?;
+            let size = u64::from(u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]));
+
+            match &chunk[0..4] {
+                b"fmt " => {
+                    let mut body = vec![0u8; size as usize];
+                    reader.read_exact(&mut body)?;
+                    format = Some(WavFormat::parse_fmt(&body)?);
+                    if size % 2 == 1 {
+                        reader.seek(SeekFrom::Current(1))?;
+                    }
+                }
+                b"data" => {
+                    let Some(format) = format else {
+                        bail!("Data chunk comes before the fmt chunk");
+                    };
+                    let data_start = reader.stream_position()?;
+                    return Ok(Self {
+                        reader,
+                        format,
+                        data_start,
+                        data_len: size - size % format.frame_size() as u64,
+                        position: 0,
+                    });
+                }
+                _ => {
+                    // Chunks are word aligned.
+                    reader.seek(SeekFrom::Current((size + size % 2) as i64))?;
+                }
+            }
+        }
+    }
+
+    pub fn format(&self) -> WavFormat {
+        self.format
+    }
+
+    /// Reads up to `frames` whole frames into `out`; returns how many were read (0 at the end).
+    pub fn read_frames(&mut self, frames: usize, out: &mut Vec<u8>) -> Result<usize> {
+        let frame_size = self.format.frame_size();
+        let left = ((self.data_len - self.position) / frame_size as u64) as usize;
+        let frames = frames.min(left);
+
+        out.resize(frames * frame_size, 0);
+        self.reader
+            .read_exact(out)
+            .context("WAV data ended early")?;
+        self.position += out.len() as u64;
+        Ok(frames)
+    }
+
+    pub fn rewind(&mut self) -> Result<()> {
+        self.reader.seek(SeekFrom::Start(self.data_start))?;
+        self.position = 0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn wav_header_matches_stream_format() {
+        let format = WavFormat {
+            rate: 48_000,
+            channels: 2,
+            data_type: DataType::Int24,
+        };
+        let header = format.wav_header(1_440);
+
+        assert_eq!(&header[0..4], b"RIFF");
+        assert_eq!(u32::from_le_bytes(header[4..8].try_into().unwrap()), 1_476);
+        assert_eq!(
+            u16::from_le_bytes([header[20], header[21]]),
+            WAVE_FORMAT_PCM
+        );
+        assert_eq!(u16::from_le_bytes([header[22], header[23]]), 2);
+        assert_eq!(
+            u32::from_le_bytes(header[28..32].try_into().unwrap()),
+            48_000 * 6
+        );
+        assert_eq!(u16::from_le_bytes([header[34], header[35]]), 24);
+        assert_eq!(
+            u32::from_le_bytes(header[40..44].try_into().unwrap()),
+            1_440
+        );
+    }
+
+    #[test]
+    fn reader_skips_unknown_chunks_and_stops_at_data_end() {
+        let format = WavFormat {
+            rate: 44_100,
+            channels: 1,
+            data_type: DataType::Float32,
+        };
+        let header = format.wav_header(8);
+        let mut file = header[..36].to_vec();
+        file.extend_from_slice(b"LIST\x03\x00\x00\x00abc\x00");
+        file.extend_from_slice(&header[36..]);
+        file.extend_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8, 9]);
+
+        let mut reader = WavReader::new(Cursor::new(file)).unwrap();
+        assert_eq!(reader.format(), format);
+
+        let mut out = Vec::new();
+        assert_eq!(reader.read_frames(5, &mut out).unwrap(), 2);
+        assert_eq!(out, [1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(reader.read_frames(5, &mut out).unwrap(), 0);
+
+        reader.rewind().unwrap();
+        assert_eq!(reader.read_frames(1, &mut out).unwrap(), 1);
+        assert_eq!(out, [1, 2, 3, 4]);
+    }
+}